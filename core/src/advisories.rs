@@ -0,0 +1,677 @@
+use crate::eol;
+use crate::models::{Issue, Node, Severity, Status, SystemState};
+use crate::scanner;
+use crate::system_provider::SystemProvider;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Advisory database written by `preflight import-advisories`.
+pub const DATABASE: &str = ".preflight/advisories.json";
+
+/// Calendar products whose service versions are matched against OSV's
+/// Bitnami ecosystem (and Go's `stdlib` module for the toolchain).
+const SERVICE_PACKAGES: &[(&str, &str, &str)] = &[
+    ("nodejs", "Bitnami", "node"),
+    ("python", "Bitnami", "python"),
+    ("postgresql", "Bitnami", "postgresql"),
+    ("mysql", "Bitnami", "mysql"),
+    ("redis", "Bitnami", "redis"),
+    ("go", "Go", "stdlib"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AffectedRange {
+    pub introduced: Option<String>,
+    pub fixed: Option<String>,
+    pub last_affected: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Affected {
+    pub ecosystem: String,
+    pub package: String,
+    pub ranges: Vec<AffectedRange>,
+    /// Explicitly enumerated affected versions.
+    pub versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Advisory {
+    pub id: String,
+    pub aliases: Vec<String>,
+    pub summary: String,
+    /// CRITICAL, HIGH, MODERATE, LOW or UNKNOWN.
+    pub severity: String,
+    pub affected: Vec<Affected>,
+}
+
+fn score_level(score: f64) -> String {
+    match score {
+        s if s >= 9.0 => "CRITICAL",
+        s if s >= 7.0 => "HIGH",
+        s if s >= 4.0 => "MODERATE",
+        _ => "LOW",
+    }
+    .into()
+}
+
+/// CVSS 3.x rounding: the smallest one-decimal value not below `value`.
+fn round_up(value: f64) -> f64 {
+    let scaled = (value * 100_000.0).round() as u64;
+    if scaled.is_multiple_of(10_000) {
+        scaled as f64 / 100_000.0
+    } else {
+        (scaled / 10_000 + 1) as f64 / 10.0
+    }
+}
+
+/// Base score of a `CVSS:3.x/AV:N/AC:L/...` vector. Other vector versions
+/// are not scored.
+pub fn cvss3_base_score(vector: &str) -> Option<f64> {
+    let mut parts = vector.trim().split('/');
+    if !matches!(parts.next()?, "CVSS:3.0" | "CVSS:3.1") {
+        return None;
+    }
+    let metrics: BTreeMap<&str, &str> = parts.filter_map(|p| p.split_once(':')).collect();
+    let changed = match *metrics.get("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let av: f64 = match *metrics.get("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let ac = match *metrics.get("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let pr = match (*metrics.get("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let ui = match *metrics.get("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let cia = |key: &str| match *metrics.get(key)? {
+        "H" => Some(0.56),
+        "L" => Some(0.22),
+        "N" => Some(0.0),
+        _ => None,
+    };
+    let iss: f64 = 1.0 - (1.0 - cia("C")?) * (1.0 - cia("I")?) * (1.0 - cia("A")?);
+    let impact = if changed {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15)
+    } else {
+        6.42 * iss
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * av * ac * pr * ui;
+    let total = if changed {
+        1.08 * (impact + exploitability)
+    } else {
+        impact + exploitability
+    };
+    Some(round_up(total.min(10.0)))
+}
+
+fn normalize_severity(raw: &str) -> Option<String> {
+    let upper = raw.trim().to_uppercase();
+    if let Ok(score) = upper.parse::<f64>() {
+        return Some(score_level(score));
+    }
+    match upper.as_str() {
+        "CRITICAL" | "HIGH" | "MODERATE" | "LOW" => Some(upper),
+        "MEDIUM" => Some("MODERATE".into()),
+        _ => None,
+    }
+}
+
+/// GHSA records carry a label in `database_specific`; other feeds put it per
+/// package or only publish a CVSS vector, which is scored here.
+fn osv_severity(record: &Value) -> String {
+    let labelled = std::iter::once(record.get("database_specific"))
+        .chain(
+            record
+                .get("affected")
+                .and_then(|a| a.as_array())
+                .into_iter()
+                .flatten()
+                .flat_map(|a| [a.get("ecosystem_specific"), a.get("database_specific")]),
+        )
+        .flatten()
+        .filter_map(|v| v.get("severity").and_then(|s| s.as_str()))
+        .find_map(normalize_severity);
+    let scored = || {
+        record
+            .get("severity")
+            .and_then(|s| s.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|s| s.get("score").and_then(|v| v.as_str()))
+            .find_map(|score| cvss3_base_score(score).map(score_level))
+    };
+    labelled.or_else(scored).unwrap_or_else(|| "UNKNOWN".into())
+}
+
+fn osv_ranges(affected: &Value) -> Vec<AffectedRange> {
+    let mut ranges = Vec::new();
+    for range in affected
+        .get("ranges")
+        .and_then(|r| r.as_array())
+        .into_iter()
+        .flatten()
+    {
+        // GIT ranges name commits, not releases.
+        if range.get("type").and_then(|t| t.as_str()) == Some("GIT") {
+            continue;
+        }
+        let mut current: Option<AffectedRange> = None;
+        for event in range
+            .get("events")
+            .and_then(|e| e.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let field = |key: &str| event.get(key).and_then(|v| v.as_str()).map(String::from);
+            if let Some(introduced) = field("introduced") {
+                if let Some(open) = current.take() {
+                    ranges.push(open);
+                }
+                current = Some(AffectedRange {
+                    introduced: Some(introduced),
+                    ..AffectedRange::default()
+                });
+            } else if let Some(open) = current.as_mut() {
+                open.fixed = open.fixed.take().or_else(|| field("fixed"));
+                open.last_affected = open.last_affected.take().or_else(|| field("last_affected"));
+                if open.fixed.is_some() || open.last_affected.is_some() {
+                    ranges.extend(current.take());
+                }
+            }
+        }
+        ranges.extend(current);
+    }
+    ranges
+}
+
+fn parse_record(record: &Value) -> Option<Advisory> {
+    let id = record.get("id")?.as_str()?.to_string();
+    let affected: Vec<Affected> = record
+        .get("affected")
+        .and_then(|a| a.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|a| {
+            let package = a.get("package")?;
+            Some(Affected {
+                ecosystem: package.get("ecosystem")?.as_str()?.to_string(),
+                package: package.get("name")?.as_str()?.to_string(),
+                ranges: osv_ranges(a),
+                versions: a
+                    .get("versions")
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect(),
+            })
+        })
+        .collect();
+    if affected.is_empty() || record.get("withdrawn").is_some() {
+        return None;
+    }
+    Some(Advisory {
+        summary: record
+            .get("summary")
+            .or_else(|| record.get("details"))
+            .and_then(|s| s.as_str())
+            .and_then(|s| s.lines().next())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "No summary provided".into()),
+        aliases: record
+            .get("aliases")
+            .and_then(|a| a.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str().map(String::from))
+            .collect(),
+        severity: osv_severity(record),
+        affected,
+        id,
+    })
+}
+
+/// Preference for the ID a merged advisory is reported under: GitHub's,
+/// then the ecosystem database's, with CVE IDs last.
+fn id_rank(id: &str) -> u8 {
+    if id.starts_with("GHSA-") {
+        0
+    } else if id.starts_with("CVE-") {
+        2
+    } else {
+        1
+    }
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "CRITICAL" => 4,
+        "HIGH" => 3,
+        "MODERATE" => 2,
+        "LOW" => 1,
+        _ => 0,
+    }
+}
+
+fn names(advisory: &Advisory) -> BTreeSet<&str> {
+    std::iter::once(advisory.id.as_str())
+        .chain(advisory.aliases.iter().map(String::as_str))
+        .collect()
+}
+
+fn combine(a: Advisory, b: Advisory) -> Advisory {
+    let (mut keep, other) = if (id_rank(&b.id), &b.id) < (id_rank(&a.id), &a.id) {
+        (b, a)
+    } else {
+        (a, b)
+    };
+    let mut aliases: BTreeSet<String> = keep
+        .aliases
+        .drain(..)
+        .chain(other.aliases)
+        .chain(std::iter::once(other.id))
+        .collect();
+    aliases.remove(&keep.id);
+    keep.aliases = aliases.into_iter().collect();
+    if severity_rank(&other.severity) > severity_rank(&keep.severity) {
+        keep.severity = other.severity;
+    }
+    for affected in other.affected {
+        if !keep.affected.contains(&affected) {
+            keep.affected.push(affected);
+        }
+    }
+    keep
+}
+
+/// Folds records that describe the same vulnerability, such as a PYSEC and a
+/// GHSA entry naming each other or a shared CVE in `aliases`, into one
+/// advisory under a single canonical ID.
+pub fn merge_aliases(advisories: &[Advisory]) -> Vec<Advisory> {
+    let mut merged: Vec<Advisory> = Vec::new();
+    for advisory in advisories {
+        let (same, rest): (Vec<Advisory>, Vec<Advisory>) = merged
+            .into_iter()
+            .partition(|m| !names(m).is_disjoint(&names(advisory)));
+        merged = rest;
+        merged.push(same.into_iter().fold(advisory.clone(), combine));
+    }
+    merged.sort_by(|a, b| a.id.cmp(&b.id));
+    merged
+}
+
+/// Parses one OSV record or an array of them. Withdrawn records and records
+/// with no package entries are dropped.
+pub fn parse_osv(raw: &str) -> Result<Vec<Advisory>, String> {
+    let value: Value = serde_json::from_str(raw).map_err(|e| format!("Invalid OSV JSON: {e}"))?;
+    let records = match value {
+        Value::Array(items) => items,
+        record => vec![record],
+    };
+    Ok(records.iter().filter_map(parse_record).collect())
+}
+
+/// Reads every `*.json` OSV file under `dir`, as unpacked from the
+/// per-ecosystem `all.zip` dumps.
+pub fn import_dir(dir: &Path) -> Result<Vec<Advisory>, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()));
+    }
+    let mut by_id: BTreeMap<String, Advisory> = BTreeMap::new();
+    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().and_then(|e| e.to_str()) != Some("json")
+        {
+            continue;
+        }
+        let raw = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+        let advisories = parse_osv(&raw).map_err(|e| format!("{}: {e}", path.display()))?;
+        for advisory in advisories {
+            by_id.insert(advisory.id.clone(), advisory);
+        }
+    }
+    Ok(merge_aliases(&by_id.into_values().collect::<Vec<_>>()))
+}
+
+/// Imports OSV dumps from a local directory into the advisory database.
+pub fn import(from: &Path) -> Result<String, String> {
+    let advisories = import_dir(from)?;
+    fs::create_dir_all(".preflight").map_err(|e| e.to_string())?;
+    let raw = serde_json::to_string_pretty(&advisories).map_err(|e| e.to_string())?;
+    fs::write(DATABASE, raw).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "Imported {} advisories into {}",
+        advisories.len(),
+        DATABASE
+    ))
+}
+
+pub fn load() -> Vec<Advisory> {
+    fs::read_to_string(DATABASE)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Splits a version into numeric components, each with any trailing
+/// pre-release text (`1.0rc1` -> `[(1, ""), (0, "rc1")]`).
+fn version_key(version: &str) -> Vec<(u64, String)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split('+').next().unwrap_or(version);
+    version
+        .split(['.', '-', '_'])
+        .filter(|p| !p.is_empty())
+        .map(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            let suffix = part[digits.len()..].to_lowercase();
+            (digits.parse().unwrap_or(0), suffix)
+        })
+        .collect()
+}
+
+/// Orders PyPI, npm and plain dotted versions. Components with pre-release
+/// text sort before the bare release.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_key(a), version_key(b));
+    let blank = (0, String::new());
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = (a.get(i).unwrap_or(&blank), b.get(i).unwrap_or(&blank));
+        let ordering =
+            x.0.cmp(&y.0)
+                .then_with(|| match (x.1.is_empty(), y.1.is_empty()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) => x.1.cmp(&y.1),
+                });
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// The range `version` falls in, or an empty range for an enumerated match.
+fn affected_range(affected: &Affected, version: &str) -> Option<AffectedRange> {
+    if affected
+        .versions
+        .iter()
+        .any(|v| compare_versions(v, version) == Ordering::Equal)
+    {
+        let fixed = affected
+            .ranges
+            .iter()
+            .filter_map(|r| r.fixed.clone())
+            .find(|f| compare_versions(version, f) == Ordering::Less);
+        return Some(AffectedRange {
+            fixed,
+            ..AffectedRange::default()
+        });
+    }
+    affected
+        .ranges
+        .iter()
+        .find(|range| {
+            let after_start = match range.introduced.as_deref() {
+                None | Some("0") => true,
+                Some(start) => compare_versions(version, start) != Ordering::Less,
+            };
+            let before_end = match (&range.fixed, &range.last_affected) {
+                (Some(fixed), _) => compare_versions(version, fixed) == Ordering::Less,
+                (None, Some(last)) => compare_versions(version, last) != Ordering::Greater,
+                (None, None) => true,
+            };
+            after_start && before_end
+        })
+        .cloned()
+}
+
+/// PyPI normalises `_`, `.` and case; npm names are compared as-is.
+fn same_package(ecosystem: &str, a: &str, b: &str) -> bool {
+    if ecosystem == "PyPI" {
+        let normalize = |s: &str| s.to_lowercase().replace(['_', '.'], "-");
+        normalize(a) == normalize(b)
+    } else {
+        a == b
+    }
+}
+
+/// Installed npm packages from npm's hidden lockfile, keyed by install path
+/// so nested copies of the same package are each checked.
+pub fn node_packages(hidden_lockfile: &str) -> BTreeMap<String, String> {
+    let Ok(lock) = serde_json::from_str::<Value>(hidden_lockfile) else {
+        return BTreeMap::new();
+    };
+    lock.get("packages")
+        .and_then(|p| p.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(path, entry)| {
+            let version = entry.get("version")?.as_str()?;
+            (!path.is_empty()).then(|| (path.clone(), version.to_string()))
+        })
+        .collect()
+}
+
+fn affected_by(advisories: &[Advisory], ecosystem: &str, package: &str, version: &str) -> bool {
+    advisories.iter().flat_map(|a| &a.affected).any(|a| {
+        a.ecosystem == ecosystem
+            && same_package(ecosystem, &a.package, package)
+            && affected_range(a, version).is_some()
+    })
+}
+
+/// Records the pip and npm packages that match an advisory in the local
+/// database. Full package lists stay out of the scan.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    detect_with_database(provider, nodes, &load());
+}
+
+pub fn detect_with_database<P: SystemProvider>(
+    provider: &P,
+    nodes: &mut [Node],
+    advisories: &[Advisory],
+) {
+    if advisories.is_empty() {
+        return;
+    }
+    for node in nodes.iter_mut().filter(|n| n.status == Status::Active) {
+        let (ecosystem, packages) = match node.id.as_str() {
+            "python" => ("PyPI", scanner::pip_packages(provider)),
+            "nodejs" => (
+                "npm",
+                provider
+                    .read_file("node_modules/.package-lock.json")
+                    .map(|c| node_packages(&c))
+                    .unwrap_or_default(),
+            ),
+            _ => continue,
+        };
+        let matched: Vec<Value> = packages
+            .iter()
+            .filter_map(|(key, version)| {
+                let package = key.rsplit("node_modules/").next().unwrap_or(key);
+                affected_by(advisories, ecosystem, package, version).then(|| {
+                    json!({
+                        "package": package,
+                        "version": version,
+                        "location": if ecosystem == "npm" { key.as_str() } else { "pip" },
+                    })
+                })
+            })
+            .collect();
+        if !matched.is_empty() {
+            node.metadata
+                .insert("vulnerable_packages".into(), json!(matched));
+        }
+    }
+}
+
+struct Installed {
+    ecosystem: &'static str,
+    package: String,
+    version: String,
+    location: String,
+}
+
+fn recorded_packages(
+    state: &SystemState,
+    node_id: &str,
+    ecosystem: &'static str,
+) -> Vec<Installed> {
+    state
+        .nodes
+        .iter()
+        .find(|n| n.id == node_id)
+        .and_then(|n| n.metadata.get("vulnerable_packages"))
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let field = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(String::from);
+            Some(Installed {
+                ecosystem,
+                package: field("package")?,
+                version: field("version")?,
+                location: field("location")?,
+            })
+        })
+        .collect()
+}
+
+fn installed_packages(state: &SystemState) -> Vec<Installed> {
+    let mut installed = recorded_packages(state, "python", "PyPI");
+    installed.extend(recorded_packages(state, "nodejs", "npm"));
+    for (product, version) in eol::installed_versions(state) {
+        for (key, ecosystem, package) in SERVICE_PACKAGES {
+            if *key == product {
+                installed.push(Installed {
+                    ecosystem,
+                    package: package.to_string(),
+                    version: version.clone(),
+                    location: format!("{} service", product),
+                });
+            }
+        }
+    }
+    installed
+}
+
+fn issue_code(id: &str) -> String {
+    let sanitized: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("SEC_VULN_{}", sanitized)
+}
+
+fn upgrade_suggestion(found: &Installed, fixed: Option<&str>) -> String {
+    match (found.ecosystem, fixed) {
+        ("PyPI", Some(fixed)) => {
+            format!("Upgrade with `pip install '{}>={}'`.", found.package, fixed)
+        }
+        ("npm", Some(fixed)) => format!(
+            "Upgrade with `npm install {}@{}` or update the dependency that pulls it in.",
+            found.package, fixed
+        ),
+        (_, Some(fixed)) => format!("Upgrade {} to {} or later.", found.package, fixed),
+        (_, None) => format!(
+            "No fixed release is listed; review the advisory and mitigate or replace {}.",
+            found.package
+        ),
+    }
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    evaluate_with_database(state, &load())
+}
+
+pub fn evaluate_with_database(state: &SystemState, advisories: &[Advisory]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if advisories.is_empty() {
+        return issues;
+    }
+    let advisories = merge_aliases(advisories);
+    for found in installed_packages(state) {
+        for advisory in &advisories {
+            let Some(range) = advisory
+                .affected
+                .iter()
+                .filter(|a| {
+                    a.ecosystem == found.ecosystem
+                        && same_package(found.ecosystem, &a.package, &found.package)
+                })
+                .find_map(|a| affected_range(a, &found.version))
+            else {
+                continue;
+            };
+            let fixed = range.fixed.as_deref();
+            let aliases = if advisory.aliases.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", advisory.aliases.join(", "))
+            };
+            issues.push(Issue {
+                code: issue_code(&advisory.id),
+                severity: match advisory.severity.as_str() {
+                    "CRITICAL" | "HIGH" => Severity::Critical,
+                    _ => Severity::Warning,
+                },
+                title: format!(
+                    "{} {} is affected by {}",
+                    found.package, found.version, advisory.id
+                ),
+                description: format!(
+                    "{}{} [{}]: {}. {} {} installed at {}; fixed in {}.",
+                    advisory.id,
+                    aliases,
+                    advisory.severity,
+                    advisory.summary.trim_end_matches('.'),
+                    found.package,
+                    found.version,
+                    found.location,
+                    fixed.unwrap_or("no released version"),
+                ),
+                suggestion: upgrade_suggestion(&found, fixed),
+            });
+        }
+    }
+    issues
+}
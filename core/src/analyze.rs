@@ -1,5 +1,5 @@
 use crate::fix;
-use crate::models::Issue;
+use crate::models::{Issue, SystemState};
 use crate::utils::json_envelope;
 use serde::Serialize;
 use serde_json::json;
@@ -11,27 +11,20 @@ pub struct AnalysisItem {
     pub suggested_fix: String,
 }
 
-fn load_issues() -> Result<Vec<Issue>, String> {
-    let state = fix::load_state()?;
-    Ok(state.issues)
-}
-
-fn suggest_fix(code: &str, issue: &Issue) -> String {
+fn suggest_fix(state: &SystemState, issue: &Issue) -> String {
     let commands = fix::commands();
-    commands
-        .get(code)
-        .map(|c| c.to_string())
-        .unwrap_or_else(|| issue.suggestion.clone())
+    fix::command_for(&commands, state, &issue.code).unwrap_or_else(|| issue.suggestion.clone())
 }
 
 pub fn run(json_output: bool) -> Result<(), String> {
-    let issues = load_issues()?;
-    let analysis: Vec<AnalysisItem> = issues
+    let state = fix::load_state()?;
+    let analysis: Vec<AnalysisItem> = state
+        .issues
         .iter()
         .map(|issue| AnalysisItem {
             issue: issue.code.clone(),
             root_cause: issue.description.clone(),
-            suggested_fix: suggest_fix(&issue.code, issue),
+            suggested_fix: suggest_fix(&state, issue),
         })
         .collect();
 
@@ -58,13 +51,14 @@ pub fn run(json_output: bool) -> Result<(), String> {
 }
 
 pub fn write_json() -> Result<String, String> {
-    let issues = load_issues()?;
-    let analysis: Vec<AnalysisItem> = issues
+    let state = fix::load_state()?;
+    let analysis: Vec<AnalysisItem> = state
+        .issues
         .iter()
         .map(|issue| AnalysisItem {
             issue: issue.code.clone(),
             root_cause: issue.description.clone(),
-            suggested_fix: suggest_fix(&issue.code, issue),
+            suggested_fix: suggest_fix(&state, issue),
         })
         .collect();
     let payload = json_envelope("analyze", "ok", json!({ "analysis": analysis }));
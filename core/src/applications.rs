@@ -0,0 +1,576 @@
+use crate::models::{Node, NodeType, Status};
+use crate::scanner::ensure_port_node;
+use crate::system_provider::SystemProvider;
+use crate::webserver::compose_ports;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// package.json scripts that start the app, in preference order.
+const NPM_SCRIPTS: &[&str] = &["dev", "start", "serve", "preview"];
+
+/// Make and just targets that run the project rather than build or test it.
+const RUN_TARGETS: &[&str] = &["run", "serve", "server", "start", "dev", "web", "worker"];
+
+/// Commands that hand off to an entry point discovered from its own file.
+const DELEGATES: &[&str] = &[
+    "npm ",
+    "yarn ",
+    "pnpm ",
+    "manage.py",
+    "docker compose",
+    "docker-compose",
+];
+
+/// Ports frameworks listen on when the command does not name one.
+const DEFAULT_PORTS: &[(&str, u16)] = &[
+    ("next dev", 3000),
+    ("next start", 3000),
+    ("react-scripts start", 3000),
+    ("nest start", 3000),
+    ("vite preview", 4173),
+    ("vite", 5173),
+    ("runserver", 8000),
+    ("flask run", 5000),
+    ("uvicorn", 8000),
+    ("gunicorn", 8000),
+];
+
+/// One way to start an app and where it is declared.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntryPoint {
+    pub source: String,
+    pub command: String,
+}
+
+#[derive(Debug, Default)]
+struct App {
+    entry_points: Vec<EntryPoint>,
+    ports: BTreeSet<u16>,
+    env_vars: BTreeSet<String>,
+    requires: BTreeSet<String>,
+}
+
+fn regex(pattern: &str) -> Regex {
+    Regex::new(pattern).expect("regex construction invariant: constant pattern")
+}
+
+/// The scan node a client library or image name needs.
+fn service_for_package(name: &str) -> Option<&'static str> {
+    match name.to_lowercase().as_str() {
+        "pg" | "postgres" | "pg-promise" | "psycopg" | "psycopg2" | "psycopg2-binary"
+        | "asyncpg" | "postgis" => Some("postgres"),
+        "mysql"
+        | "mysql2"
+        | "mysqlclient"
+        | "pymysql"
+        | "mysqldb"
+        | "aiomysql"
+        | "mysql-connector-python"
+        | "mariadb" => Some("mysql"),
+        "redis" | "ioredis" | "bull" | "bullmq" | "django-redis" | "django_redis" | "aioredis"
+        | "valkey" => Some("redis"),
+        _ => None,
+    }
+}
+
+fn service_for_env(name: &str) -> Option<&'static str> {
+    if name.starts_with("PG") || name.starts_with("POSTGRES_") {
+        Some("postgres")
+    } else if name.starts_with("MYSQL_") {
+        Some("mysql")
+    } else if name.starts_with("REDIS_") {
+        Some("redis")
+    } else {
+        None
+    }
+}
+
+fn runtime_for_command(command: &str) -> Option<&'static str> {
+    command
+        .split_whitespace()
+        .find_map(|word| match word.rsplit('/').next().unwrap_or(word) {
+            "node" | "npm" | "npx" | "yarn" | "pnpm" | "next" | "vite" | "nodemon" | "ts-node"
+            | "tsx" | "react-scripts" | "nest" => Some("nodejs"),
+            "python" | "python3" | "gunicorn" | "uvicorn" | "flask" | "celery" | "hypercorn"
+            | "daphne" | "manage.py" => Some("python"),
+            "docker" | "docker-compose" => Some("docker"),
+            _ => None,
+        })
+}
+
+/// Variables a shell command expands.
+fn shell_vars(command: &str) -> BTreeSet<String> {
+    regex(r"\$\{?([A-Z_][A-Z0-9_]*)")
+        .captures_iter(command)
+        .map(|c| c[1].to_string())
+        .collect()
+}
+
+/// Environment variables read by JavaScript or Python source.
+fn env_reads(contents: &str) -> BTreeSet<String> {
+    let patterns = [
+        r"process\.env\.([A-Za-z_][A-Za-z0-9_]*)",
+        r#"process\.env\[\s*['"]([A-Za-z_][A-Za-z0-9_]*)['"]"#,
+        r"import\.meta\.env\.([A-Za-z_][A-Za-z0-9_]*)",
+        r#"os\.environ\[\s*['"]([A-Za-z_][A-Za-z0-9_]*)['"]"#,
+        r#"os\.(?:environ\.get|getenv)\(\s*['"]([A-Za-z_][A-Za-z0-9_]*)['"]"#,
+        r#"\benv(?:\.\w+)?\(\s*['"]([A-Z_][A-Z0-9_]*)['"]"#,
+    ];
+    patterns
+        .iter()
+        .flat_map(|p| {
+            regex(p)
+                .captures_iter(contents)
+                .map(|c| c[1].to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Records the runtime, ports, environment variables and source files a
+/// start command implies.
+fn analyze_command<P: SystemProvider>(provider: &P, command: &str, app: &mut App) {
+    let runtime = runtime_for_command(command);
+    app.requires.extend(runtime.map(String::from));
+    app.env_vars.extend(shell_vars(command));
+    let explicit: Vec<u16> = [
+        r"(?:--port|-p|--bind|-b)[= ]\s*(?:[\d.]+:|\[[^\]]*\]:)?(\d{2,5})\b",
+        r"\bPORT=(\d{2,5})\b",
+        r"runserver\s+(?:[\w.]+:)?(\d{2,5})\b",
+    ]
+    .iter()
+    .flat_map(|p| {
+        regex(p)
+            .captures_iter(command)
+            .filter_map(|c| c[1].parse().ok())
+            .collect::<Vec<_>>()
+    })
+    .collect();
+    // A port taken from `$PORT` is only known at run time.
+    if explicit.is_empty() && !shell_vars(command).contains("PORT") {
+        app.ports.extend(
+            DEFAULT_PORTS
+                .iter()
+                .find(|(tool, _)| command.contains(tool))
+                .map(|(_, port)| *port),
+        );
+    }
+    app.ports.extend(explicit);
+
+    for word in command.split_whitespace() {
+        let file = if [".js", ".mjs", ".cjs", ".ts", ".py"]
+            .iter()
+            .any(|ext| word.ends_with(ext))
+        {
+            word.to_string()
+        } else if runtime == Some("python")
+            && regex(r"^[A-Za-z_][\w.]*:[A-Za-z_]\w*$").is_match(word)
+        {
+            // `gunicorn app:app` and `uvicorn pkg.main:app` name a module.
+            let module = word.split(':').next().unwrap_or(word);
+            format!("{}.py", module.replace('.', "/"))
+        } else {
+            continue;
+        };
+        scan_source(provider, &file, app);
+    }
+}
+
+/// Reads an entry point's source for environment variables, listen ports
+/// and database clients it imports.
+fn scan_source<P: SystemProvider>(provider: &P, path: &str, app: &mut App) {
+    let Some(contents) = provider.read_file(path) else {
+        return;
+    };
+    app.env_vars.extend(env_reads(&contents));
+    for pattern in [
+        r"\.listen\(\s*(?:[\w.\[\]'\x22]+\s*(?:\|\||\?\?)\s*)?(\d{2,5})\b",
+        // `port=` only counts inside a server start call; client
+        // connections pass the port they dial the same way.
+        r"(?:\.run|\.run_app|\bserve)\((?:[^()]|\([^()]*\))*?\bport\s*=\s*(\d{2,5})\b",
+    ] {
+        app.ports.extend(
+            regex(pattern)
+                .captures_iter(&contents)
+                .filter_map(|c| c[1].parse::<u16>().ok()),
+        );
+    }
+    let imports = regex(
+        r#"(?m)require\(\s*['"]([^'"/]+)|from\s+['"]([^'"/]+)['"]|^\s*(?:import|from)\s+([A-Za-z_]\w*)"#,
+    );
+    for c in imports.captures_iter(&contents) {
+        let name = c.get(1).or_else(|| c.get(2)).or_else(|| c.get(3));
+        app.requires.extend(
+            name.and_then(|m| service_for_package(m.as_str()))
+                .map(String::from),
+        );
+    }
+    // Django settings name their database and cache backends.
+    for (marker, service) in [
+        ("django.db.backends.postgresql", "postgres"),
+        ("django.contrib.gis.db.backends.postgis", "postgres"),
+        ("django.db.backends.mysql", "mysql"),
+        ("django_redis", "redis"),
+        ("RedisCache", "redis"),
+    ] {
+        if contents.contains(marker) {
+            app.requires.insert(service.into());
+        }
+    }
+}
+
+/// Package names declared in requirements.txt and pyproject.toml.
+fn python_dependencies<P: SystemProvider>(provider: &P) -> Vec<String> {
+    let name = regex(r"^\s*([A-Za-z0-9_.-]+)");
+    let mut names: Vec<String> = provider
+        .read_file("requirements.txt")
+        .unwrap_or_default()
+        .lines()
+        .filter(|l| !l.trim_start().starts_with(['#', '-']))
+        .filter_map(|l| name.captures(l).map(|c| c[1].to_string()))
+        .collect();
+    let pyproject = provider
+        .read_file("pyproject.toml")
+        .and_then(|c| toml::from_str::<toml::Value>(&c).ok());
+    if let Some(pyproject) = pyproject {
+        let declared = pyproject
+            .get("project")
+            .and_then(|p| p.get("dependencies"))
+            .and_then(|d| d.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|d| name.captures(d.as_str()?).map(|c| c[1].to_string()));
+        names.extend(declared);
+        let poetry = pyproject
+            .get("tool")
+            .and_then(|t| t.get("poetry"))
+            .and_then(|p| p.get("dependencies"))
+            .and_then(|d| d.as_table());
+        names.extend(poetry.into_iter().flat_map(|t| t.keys().cloned()));
+    }
+    names
+}
+
+fn from_package_json<P: SystemProvider>(provider: &P, apps: &mut BTreeMap<String, App>) {
+    let Some(pkg) = provider
+        .read_file("package.json")
+        .and_then(|c| serde_json::from_str::<Value>(&c).ok())
+    else {
+        return;
+    };
+    let scripts: Vec<(&str, &str)> = NPM_SCRIPTS
+        .iter()
+        .filter_map(|s| Some((*s, pkg.get("scripts")?.get(*s)?.as_str()?)))
+        .collect();
+    if scripts.is_empty() {
+        return;
+    }
+    let name = pkg
+        .get("name")
+        .and_then(|n| n.as_str())
+        .map(|n| n.rsplit('/').next().unwrap_or(n))
+        .unwrap_or("node");
+    let app = apps.entry(name.to_string()).or_default();
+    app.requires.insert("nodejs".into());
+    for (script, command) in scripts {
+        app.entry_points.push(EntryPoint {
+            source: format!("package.json scripts.{}", script),
+            command: command.to_string(),
+        });
+        analyze_command(provider, command, app);
+    }
+    let dependencies = ["dependencies", "devDependencies"]
+        .iter()
+        .filter_map(|key| pkg.get(*key)?.as_object())
+        .flat_map(|deps| deps.keys());
+    for dependency in dependencies {
+        app.requires
+            .extend(service_for_package(dependency).map(String::from));
+    }
+    if let Some(main) = pkg.get("main").and_then(|m| m.as_str()) {
+        scan_source(provider, main, app);
+    }
+}
+
+fn from_procfile<P: SystemProvider>(provider: &P, apps: &mut BTreeMap<String, App>) {
+    let Some(contents) = provider.read_file("Procfile") else {
+        return;
+    };
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        let Some((name, command)) = line.split_once(':') else {
+            continue;
+        };
+        let app = apps.entry(name.trim().to_string()).or_default();
+        app.entry_points.push(EntryPoint {
+            source: format!("Procfile:{}", index + 1),
+            command: command.trim().to_string(),
+        });
+        analyze_command(provider, command.trim(), app);
+    }
+}
+
+/// Run targets of a Makefile or justfile with their recipe lines.
+fn run_targets(contents: &str) -> Vec<(usize, String, String)> {
+    let header = regex(r"^@?([A-Za-z0-9_.-]+)(?:\s+[^:=#]*)?:(?:[^=]|$)");
+    let mut targets = Vec::new();
+    let mut current: Option<(usize, String, Vec<String>)> = None;
+    for (index, line) in contents.lines().enumerate() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, _, recipe)) = current.as_mut() {
+                let step = line.trim().trim_start_matches(['@', '-']).trim();
+                if !step.is_empty() && !step.starts_with('#') {
+                    recipe.push(step.to_string());
+                }
+            }
+            continue;
+        }
+        targets.extend(current.take());
+        if let Some(c) = header.captures(line) {
+            current = Some((index + 1, c[1].to_string(), Vec::new()));
+        }
+    }
+    targets.extend(current);
+    targets
+        .into_iter()
+        .filter(|(_, name, recipe)| RUN_TARGETS.contains(&name.as_str()) && !recipe.is_empty())
+        .map(|(line, name, recipe)| (line, name, recipe.join(" && ")))
+        .collect()
+}
+
+fn from_task_runners<P: SystemProvider>(provider: &P, apps: &mut BTreeMap<String, App>) {
+    for file in ["Makefile", "justfile", "Justfile"] {
+        let Some(contents) = provider.read_file(file) else {
+            continue;
+        };
+        for (line, target, command) in run_targets(&contents) {
+            if DELEGATES.iter().any(|d| command.contains(d)) {
+                continue;
+            }
+            let app = apps.entry(target).or_default();
+            app.entry_points.push(EntryPoint {
+                source: format!("{}:{}", file, line),
+                command: command.clone(),
+            });
+            analyze_command(provider, &command, app);
+        }
+    }
+}
+
+fn from_django<P: SystemProvider>(provider: &P, apps: &mut BTreeMap<String, App>) {
+    let Some(manage) = provider.read_file("manage.py") else {
+        return;
+    };
+    let settings = regex(r#"DJANGO_SETTINGS_MODULE['"]\s*,\s*['"]([\w.]+)['"]"#)
+        .captures(&manage)
+        .map(|c| c[1].to_string());
+    let name = settings
+        .as_deref()
+        .and_then(|s| s.split('.').next())
+        .unwrap_or("django");
+    let app = apps.entry(name.to_string()).or_default();
+    let command = "python manage.py runserver";
+    app.entry_points.push(EntryPoint {
+        source: "manage.py".into(),
+        command: command.into(),
+    });
+    analyze_command(provider, command, app);
+    if let Some(module) = settings {
+        let path = module.replace('.', "/");
+        scan_source(provider, &format!("{}.py", path), app);
+        scan_source(provider, &format!("{}/__init__.py", path), app);
+    }
+}
+
+fn from_pyproject<P: SystemProvider>(provider: &P, apps: &mut BTreeMap<String, App>) {
+    let Some(pyproject) = provider
+        .read_file("pyproject.toml")
+        .and_then(|c| toml::from_str::<toml::Value>(&c).ok())
+    else {
+        return;
+    };
+    let tables = [
+        ("[project.scripts]", pyproject.get("project")),
+        (
+            "[tool.poetry.scripts]",
+            pyproject.get("tool").and_then(|t| t.get("poetry")),
+        ),
+    ];
+    for (section, parent) in tables {
+        let Some(scripts) = parent
+            .and_then(|p| p.get("scripts"))
+            .and_then(|s| s.as_table())
+        else {
+            continue;
+        };
+        for (name, target) in scripts {
+            let Some(target) = target.as_str() else {
+                continue;
+            };
+            let app = apps.entry(name.clone()).or_default();
+            app.requires.insert("python".into());
+            app.entry_points.push(EntryPoint {
+                source: format!("pyproject.toml {}", section),
+                command: target.to_string(),
+            });
+            let module = target.split(':').next().unwrap_or(target).replace('.', "/");
+            for candidate in [
+                format!("{}.py", module),
+                format!("src/{}.py", module),
+                format!("{}/__init__.py", module),
+                format!("src/{}/__init__.py", module),
+            ] {
+                scan_source(provider, &candidate, app);
+            }
+        }
+    }
+}
+
+/// Compose services built from the project; image-only services are the
+/// databases and caches they depend on.
+fn from_compose(contents: &str, file: &str, apps: &mut BTreeMap<String, App>) {
+    let Ok(doc) = serde_yaml::from_str::<serde_yaml::Value>(contents) else {
+        return;
+    };
+    let Some(services) = doc.get("services").and_then(|s| s.as_mapping()) else {
+        return;
+    };
+    let published = compose_ports(contents);
+    let image_of = |name: &str| {
+        services
+            .get(name)
+            .and_then(|s| s.get("image"))
+            .and_then(|i| i.as_str())
+            .map(|i| {
+                let repository = i.split([':', '@']).next().unwrap_or(i);
+                repository
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(repository)
+                    .to_string()
+            })
+    };
+    for (name, service) in services {
+        let (Some(name), Some(_)) = (name.as_str(), service.get("build")) else {
+            continue;
+        };
+        let app = apps.entry(name.to_string()).or_default();
+        app.requires.insert("docker".into());
+        let command = match service.get("command") {
+            Some(serde_yaml::Value::String(c)) => c.clone(),
+            Some(serde_yaml::Value::Sequence(parts)) => parts
+                .iter()
+                .filter_map(|p| p.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => format!("docker compose up {}", name),
+        };
+        app.env_vars.extend(shell_vars(&command));
+        app.entry_points.push(EntryPoint {
+            source: format!("{} services.{}", file, name),
+            command,
+        });
+        app.ports
+            .extend(published.get(name).into_iter().flatten().copied());
+        match service.get("environment") {
+            Some(serde_yaml::Value::Mapping(vars)) => app
+                .env_vars
+                .extend(vars.keys().filter_map(|k| k.as_str().map(String::from))),
+            Some(serde_yaml::Value::Sequence(vars)) => app.env_vars.extend(
+                vars.iter()
+                    .filter_map(|v| v.as_str()?.split('=').next().map(String::from)),
+            ),
+            _ => {}
+        }
+        let depends_on: Vec<String> = match service.get("depends_on") {
+            Some(serde_yaml::Value::Sequence(deps)) => deps
+                .iter()
+                .filter_map(|d| d.as_str().map(String::from))
+                .collect(),
+            Some(serde_yaml::Value::Mapping(deps)) => deps
+                .keys()
+                .filter_map(|d| d.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for dependency in depends_on {
+            let service = image_of(&dependency).and_then(|image| service_for_package(&image));
+            app.requires.extend(service.map(String::from));
+        }
+    }
+}
+
+/// Discovers the project's applications from package.json scripts, the
+/// Procfile, Makefile and justfile run targets, `manage.py`, pyproject
+/// scripts and compose services, and adds an application node for each
+/// with the ports it binds, the environment variables it reads and the
+/// runtime and service nodes it requires.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let mut apps: BTreeMap<String, App> = BTreeMap::new();
+    from_package_json(provider, &mut apps);
+    from_procfile(provider, &mut apps);
+    from_task_runners(provider, &mut apps);
+    from_django(provider, &mut apps);
+    from_pyproject(provider, &mut apps);
+    let compose_file = nodes
+        .iter()
+        .find(|n| n.id == "docker")
+        .and_then(|n| n.metadata.get("compose_file"))
+        .and_then(|f| f.as_str())
+        .map(String::from);
+    if let Some(file) = compose_file {
+        if let Some(contents) = provider.read_file(&file) {
+            from_compose(&contents, &file, &mut apps);
+        }
+    }
+    if apps.is_empty() {
+        return;
+    }
+
+    let python_services: BTreeSet<&str> = python_dependencies(provider)
+        .iter()
+        .filter_map(|d| service_for_package(d))
+        .collect();
+    for (name, mut app) in apps {
+        if app.requires.contains("python") {
+            app.requires
+                .extend(python_services.iter().map(|s| s.to_string()));
+        }
+        let from_env: Vec<String> = app
+            .env_vars
+            .iter()
+            .filter_map(|v| service_for_env(v).map(String::from))
+            .collect();
+        app.requires.extend(from_env);
+        for port in &app.ports {
+            ensure_port_node(provider, nodes, *port);
+        }
+        // An app counts as running when something holds one of its ports.
+        let listening = app.ports.iter().any(|port| {
+            nodes
+                .iter()
+                .any(|n| n.id == format!("port{}", port) && n.status == Status::Active)
+        });
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("entry_points".into(), json!(app.entry_points));
+        metadata.insert("listen_ports".into(), json!(app.ports));
+        metadata.insert("env_vars".into(), json!(app.env_vars));
+        metadata.insert("requires".into(), json!(app.requires));
+        nodes.push(Node {
+            id: format!("app:{}", name),
+            node_type: NodeType::Application,
+            label: name,
+            status: if listening {
+                Status::Active
+            } else {
+                Status::Inactive
+            },
+            metadata,
+        });
+    }
+}
@@ -0,0 +1,294 @@
+use crate::models::Node;
+use crate::path_scan::path_entries;
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// How a build prerequisite is located on the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    /// An executable on PATH.
+    Tool(&'static str),
+    /// A shared library soname prefix in the `ldconfig -p` cache.
+    Library(&'static str),
+    /// A pkg-config module.
+    PkgConfig(&'static str),
+}
+
+/// A system tool, library or header set a source build needs, with the
+/// Debian/Ubuntu package that provides it.
+pub struct Prerequisite {
+    pub name: &'static str,
+    pub probe: Probe,
+    pub apt_package: &'static str,
+}
+
+pub const PREREQUISITES: &[Prerequisite] = &[
+    Prerequisite {
+        name: "gcc",
+        probe: Probe::Tool("gcc"),
+        apt_package: "build-essential",
+    },
+    Prerequisite {
+        name: "g++",
+        probe: Probe::Tool("g++"),
+        apt_package: "build-essential",
+    },
+    Prerequisite {
+        name: "make",
+        probe: Probe::Tool("make"),
+        apt_package: "build-essential",
+    },
+    Prerequisite {
+        name: "pkg-config",
+        probe: Probe::Tool("pkg-config"),
+        apt_package: "pkg-config",
+    },
+    Prerequisite {
+        name: "python3",
+        probe: Probe::Tool("python3"),
+        apt_package: "python3",
+    },
+    Prerequisite {
+        name: "python3-dev",
+        probe: Probe::Tool("python3-config"),
+        apt_package: "python3-dev",
+    },
+    Prerequisite {
+        name: "pg_config",
+        probe: Probe::Tool("pg_config"),
+        apt_package: "libpq-dev",
+    },
+    Prerequisite {
+        name: "libpq",
+        probe: Probe::Library("libpq.so"),
+        apt_package: "libpq-dev",
+    },
+    Prerequisite {
+        name: "libffi",
+        probe: Probe::PkgConfig("libffi"),
+        apt_package: "libffi-dev",
+    },
+    Prerequisite {
+        name: "libxml2",
+        probe: Probe::PkgConfig("libxml-2.0"),
+        apt_package: "libxml2-dev",
+    },
+    Prerequisite {
+        name: "libxslt",
+        probe: Probe::PkgConfig("libxslt"),
+        apt_package: "libxslt1-dev",
+    },
+    Prerequisite {
+        name: "mysqlclient",
+        probe: Probe::PkgConfig("mysqlclient"),
+        apt_package: "default-libmysqlclient-dev",
+    },
+    Prerequisite {
+        name: "libldap",
+        probe: Probe::Library("libldap"),
+        apt_package: "libldap2-dev",
+    },
+    Prerequisite {
+        name: "libsasl2",
+        probe: Probe::Library("libsasl2.so"),
+        apt_package: "libsasl2-dev",
+    },
+    Prerequisite {
+        name: "cairo",
+        probe: Probe::PkgConfig("cairo"),
+        apt_package: "libcairo2-dev",
+    },
+    Prerequisite {
+        name: "pango",
+        probe: Probe::PkgConfig("pangocairo"),
+        apt_package: "libpango1.0-dev",
+    },
+    Prerequisite {
+        name: "libjpeg",
+        probe: Probe::PkgConfig("libjpeg"),
+        apt_package: "libjpeg-dev",
+    },
+    Prerequisite {
+        name: "portaudio",
+        probe: Probe::PkgConfig("portaudio-2.0"),
+        apt_package: "portaudio19-dev",
+    },
+];
+
+/// Python packages that compile C extensions at install time.
+pub const PYTHON_PACKAGES: &[(&str, &[&str])] = &[
+    ("psycopg2", &["gcc", "python3-dev", "pg_config", "libpq"]),
+    (
+        "mysqlclient",
+        &["gcc", "python3-dev", "pkg-config", "mysqlclient"],
+    ),
+    ("cffi", &["gcc", "python3-dev", "libffi"]),
+    ("lxml", &["gcc", "python3-dev", "libxml2", "libxslt"]),
+    (
+        "python-ldap",
+        &["gcc", "python3-dev", "libldap", "libsasl2"],
+    ),
+    ("pycairo", &["gcc", "python3-dev", "pkg-config", "cairo"]),
+    ("pyaudio", &["gcc", "python3-dev", "portaudio"]),
+    ("uwsgi", &["gcc", "python3-dev"]),
+];
+
+/// npm packages built with node-gyp when no prebuilt binary matches.
+pub const NODE_PACKAGES: &[(&str, &[&str])] = &[
+    ("bcrypt", &["make", "g++", "python3"]),
+    ("argon2", &["make", "g++", "python3"]),
+    ("better-sqlite3", &["make", "g++", "python3"]),
+    ("sqlite3", &["make", "g++", "python3"]),
+    ("node-sass", &["make", "g++", "python3"]),
+    ("re2", &["make", "g++", "python3"]),
+    (
+        "canvas",
+        &[
+            "make",
+            "g++",
+            "python3",
+            "pkg-config",
+            "cairo",
+            "pango",
+            "libjpeg",
+        ],
+    ),
+    (
+        "pg-native",
+        &["make", "g++", "python3", "pg_config", "libpq"],
+    ),
+];
+
+fn prerequisite(name: &str) -> Option<&'static Prerequisite> {
+    PREREQUISITES.iter().find(|p| p.name == name)
+}
+
+struct Probes {
+    path: Vec<String>,
+    ldconfig: Option<String>,
+    cache: BTreeMap<&'static str, bool>,
+}
+
+impl Probes {
+    fn available<P: SystemProvider>(&mut self, provider: &P, prereq: &Prerequisite) -> bool {
+        if let Some(found) = self.cache.get(prereq.name) {
+            return *found;
+        }
+        let found = match prereq.probe {
+            Probe::Tool(tool) => self
+                .path
+                .iter()
+                .any(|dir| provider.file_exists(&format!("{}/{}", dir, tool))),
+            Probe::Library(soname) => self
+                .ldconfig
+                .get_or_insert_with(|| {
+                    provider
+                        .command_output("ldconfig", &["-p"])
+                        .unwrap_or_default()
+                })
+                .lines()
+                .any(|line| line.trim_start().starts_with(soname)),
+            // `--exists` prints nothing, so ask for the version instead.
+            Probe::PkgConfig(module) => provider
+                .command_output("pkg-config", &["--modversion", module])
+                .is_some(),
+        };
+        self.cache.insert(prereq.name, found);
+        found
+    }
+}
+
+fn check_packages<P: SystemProvider>(
+    provider: &P,
+    probes: &mut Probes,
+    table: &[(&str, &[&str])],
+    pending: &[String],
+) -> Vec<Value> {
+    let mut findings = Vec::new();
+    for (package, needs) in table {
+        if !pending.iter().any(|p| p == package) {
+            continue;
+        }
+        let missing: Vec<Value> = needs
+            .iter()
+            .filter_map(|name| prerequisite(name))
+            .filter(|prereq| !probes.available(provider, prereq))
+            .map(|prereq| {
+                let (probe, target) = match prereq.probe {
+                    Probe::Tool(t) => ("path", t),
+                    Probe::Library(l) => ("ldconfig", l),
+                    Probe::PkgConfig(m) => ("pkg-config", m),
+                };
+                json!({
+                    "name": prereq.name,
+                    "probe": probe,
+                    "target": target,
+                    "apt_package": prereq.apt_package,
+                })
+            })
+            .collect();
+        if !missing.is_empty() {
+            findings.push(json!({
+                "package": package,
+                "missing": missing,
+            }));
+        }
+    }
+    findings
+}
+
+/// npm dependencies declared in package.json with no copy in node_modules.
+fn pending_node_packages<P: SystemProvider>(provider: &P) -> Vec<String> {
+    let Some(pkg) = provider
+        .read_file("package.json")
+        .and_then(|c| serde_json::from_str::<Value>(&c).ok())
+    else {
+        return Vec::new();
+    };
+    ["dependencies", "devDependencies", "optionalDependencies"]
+        .iter()
+        .filter_map(|key| pkg.get(*key).and_then(|v| v.as_object()))
+        .flat_map(|deps| deps.keys().cloned())
+        .filter(|name| !provider.file_exists(&format!("node_modules/{}/package.json", name)))
+        .collect()
+}
+
+fn string_list(node: &Node, key: &str) -> Vec<String> {
+    node.metadata
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Checks packages that are declared but not yet installed for the compilers,
+/// headers and libraries their source builds need.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let mut probes = Probes {
+        path: path_entries(provider),
+        ldconfig: None,
+        cache: BTreeMap::new(),
+    };
+
+    if let Some(python) = nodes.iter_mut().find(|n| n.id == "python") {
+        let pending = string_list(python, "python_requirements_missing");
+        let findings = check_packages(provider, &mut probes, PYTHON_PACKAGES, &pending);
+        python
+            .metadata
+            .insert("build_prerequisites_missing".into(), json!(findings));
+    }
+
+    if let Some(nodejs) = nodes.iter_mut().find(|n| n.id == "nodejs") {
+        let pending = pending_node_packages(provider);
+        let findings = check_packages(provider, &mut probes, NODE_PACKAGES, &pending);
+        nodejs
+            .metadata
+            .insert("build_prerequisites_missing".into(), json!(findings));
+    }
+}
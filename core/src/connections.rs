@@ -0,0 +1,546 @@
+use crate::models::{Issue, Node, NodeType, Severity, Status, SystemState};
+use crate::proxy::redact_credentials;
+use crate::scanner::ensure_port_node;
+use crate::system_provider::SystemProvider;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Variables read from the process environment; `.env` and compose
+/// `environment:` entries are also matched by their `_URL`, `_URI` or
+/// `_DSN` suffix.
+const URL_VARIABLES: &[&str] = &[
+    "DATABASE_URL",
+    "REDIS_URL",
+    "AMQP_URL",
+    "MONGO_URL",
+    "ELASTICSEARCH_URL",
+];
+
+struct ServiceKind {
+    id: &'static str,
+    label: &'static str,
+    schemes: &'static [&'static str],
+    default_port: u16,
+    /// Image names, without registry or tag, that run the service.
+    images: &'static [&'static str],
+    /// Scan node for the service, when the scanner has one.
+    node: Option<&'static str>,
+}
+
+const SERVICES: &[ServiceKind] = &[
+    ServiceKind {
+        id: "postgres",
+        label: "PostgreSQL",
+        schemes: &["postgres", "postgresql", "postgis"],
+        default_port: 5432,
+        images: &["postgres", "postgis"],
+        node: Some("postgres"),
+    },
+    ServiceKind {
+        id: "mysql",
+        label: "MySQL",
+        schemes: &["mysql", "mysql2", "mariadb"],
+        default_port: 3306,
+        images: &["mysql", "mariadb", "percona"],
+        node: Some("mysql"),
+    },
+    ServiceKind {
+        id: "redis",
+        label: "Redis",
+        schemes: &["redis", "rediss"],
+        default_port: 6379,
+        images: &["redis", "redis-stack", "valkey"],
+        node: Some("redis"),
+    },
+    ServiceKind {
+        id: "rabbitmq",
+        label: "RabbitMQ",
+        schemes: &["amqp", "amqps"],
+        default_port: 5672,
+        images: &["rabbitmq"],
+        node: None,
+    },
+    ServiceKind {
+        id: "mongodb",
+        label: "MongoDB",
+        schemes: &["mongodb", "mongodb+srv"],
+        default_port: 27017,
+        images: &["mongo"],
+        node: None,
+    },
+    ServiceKind {
+        id: "elasticsearch",
+        label: "Elasticsearch",
+        schemes: &[],
+        default_port: 9200,
+        images: &["elasticsearch", "opensearch"],
+        node: None,
+    },
+];
+
+/// A service URL found in the environment, `.env` or compose.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Connection {
+    pub variable: String,
+    /// `.env:3`, `compose.yaml services.api` or `$DATABASE_URL`.
+    pub origin: String,
+    /// The URL with credentials masked.
+    pub url: String,
+    pub service: String,
+    pub host: String,
+    pub port: u16,
+    /// The host is this machine.
+    pub local: bool,
+    /// The host names a service in the compose file.
+    pub compose_service: Option<String>,
+    /// Host port the compose service publishes the URL's port on.
+    pub published_port: Option<u16>,
+    /// Scan node the URL resolves to: the service node, or the port node
+    /// for services the scanner has no node for.
+    pub node: Option<String>,
+    /// Application nodes that read the variable.
+    pub consumers: Vec<String>,
+    /// Whether anything listens on a local host's port.
+    pub listening: Option<bool>,
+}
+
+fn kind(id: &str) -> Option<&'static ServiceKind> {
+    SERVICES.iter().find(|s| s.id == id)
+}
+
+/// Service an image runs, judged by its name without registry or tag
+/// (`docker.io/library/postgres:16` is `postgres`).
+fn image_kind(image: &str) -> Option<&'static ServiceKind> {
+    let last = image.rsplit('/').next().unwrap_or(image);
+    let name = last.split(['@', ':']).next().unwrap_or(last);
+    SERVICES.iter().find(|s| s.images.contains(&name))
+}
+
+/// Service, host and port of a connection URL. The scheme picks the
+/// service (`postgresql+psycopg2` counts as `postgresql`), except that
+/// HTTP URLs count as Elasticsearch when the variable names it.
+pub fn parse_connection_url(variable: &str, url: &str) -> Option<(&'static str, String, u16)> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let scheme = scheme.to_lowercase();
+    let base = scheme.split('+').next().unwrap_or(&scheme);
+    let upper = variable.to_uppercase();
+    let service = SERVICES
+        .iter()
+        .find(|s| s.schemes.contains(&scheme.as_str()) || s.schemes.contains(&base))
+        .or_else(|| {
+            (matches!(base, "http" | "https")
+                && (upper.contains("ELASTIC") || upper.contains("OPENSEARCH")))
+            .then(|| kind("elasticsearch"))
+            .flatten()
+        })?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, hp)| hp);
+    // Clustered URLs list several hosts; the first is enough to resolve.
+    let host_port = host_port.split(',').next()?;
+    let (host, port) = match host_port.strip_prefix('[') {
+        Some(v6) => {
+            let (host, rest) = v6.split_once(']')?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => service.default_port,
+    };
+    // An empty host is a Unix socket, which has no port to check.
+    (!host.is_empty()).then(|| (service.id, host.to_lowercase(), port))
+}
+
+fn is_local(host: &str) -> bool {
+    matches!(
+        host,
+        "localhost" | "::1" | "0.0.0.0" | "host.docker.internal"
+    ) || host.starts_with("127.")
+}
+
+fn url_variable(name: &str) -> bool {
+    URL_VARIABLES.contains(&name)
+        || ["_URL", "_URI", "_DSN"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// `KEY=value` lines of a dotenv file with their line numbers.
+fn dotenv(contents: &str) -> Vec<(usize, String, String)> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = line.trim().trim_start_matches("export ").trim();
+            if line.starts_with('#') {
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim().trim_matches(['"', '\'']);
+            Some((index + 1, key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Expands `${VAR}` and `${VAR:-default}` the way compose does: the
+/// process environment first, then the project's `.env`.
+fn interpolate<P: SystemProvider>(
+    provider: &P,
+    re: &Regex,
+    value: &str,
+    env: &BTreeMap<String, String>,
+) -> Option<String> {
+    let mut complete = true;
+    let expanded = re.replace_all(value, |c: &regex::Captures| {
+        let set = provider.env_var(&c[1]).or_else(|| env.get(&c[1]).cloned());
+        match (set, c.get(2)) {
+            (Some(v), _) => v,
+            (None, Some(default)) => default.as_str().to_string(),
+            (None, None) => {
+                complete = false;
+                String::new()
+            }
+        }
+    });
+    complete.then(|| expanded.into_owned())
+}
+
+/// What a compose service runs and the ports it publishes.
+#[derive(Debug, Default)]
+struct ComposeService {
+    image: Option<String>,
+    /// (container port, host port) pairs.
+    published: Vec<(u16, u16)>,
+    /// `environment:` entries as (variable, value).
+    environment: Vec<(String, String)>,
+}
+
+/// Container and host port of a `ports:` entry such as `"5433:5432"`,
+/// `"127.0.0.1:5433:5432/tcp"` or `{target: 5432, published: 5433}`.
+/// A lone container port publishes on a random host port and is skipped.
+fn published_port(entry: &serde_yaml::Value) -> Option<(u16, u16)> {
+    let number = |v: &serde_yaml::Value| {
+        v.as_u64()
+            .and_then(|n| u16::try_from(n).ok())
+            .or_else(|| v.as_str()?.parse().ok())
+    };
+    match entry {
+        serde_yaml::Value::String(spec) => {
+            let spec = spec.split('/').next().unwrap_or(spec);
+            let (host, container) = spec.rsplit_once(':')?;
+            let host = host.rsplit(':').next()?;
+            Some((container.parse().ok()?, host.parse().ok()?))
+        }
+        serde_yaml::Value::Mapping(_) => Some((
+            number(entry.get("target")?)?,
+            number(entry.get("published")?)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Compose services by name.
+fn compose_services(contents: &str) -> BTreeMap<String, ComposeService> {
+    let Ok(doc) = serde_yaml::from_str::<serde_yaml::Value>(contents) else {
+        return Default::default();
+    };
+    let Some(services) = doc.get("services").and_then(|s| s.as_mapping()) else {
+        return Default::default();
+    };
+    let mut described = BTreeMap::new();
+    for (name, service) in services {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let environment = match service.get("environment") {
+            Some(serde_yaml::Value::Mapping(vars)) => vars
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.as_str()?.to_string())))
+                .collect(),
+            Some(serde_yaml::Value::Sequence(vars)) => vars
+                .iter()
+                .filter_map(|v| {
+                    let (k, v) = v.as_str()?.split_once('=')?;
+                    Some((k.to_string(), v.to_string()))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        described.insert(
+            name.to_string(),
+            ComposeService {
+                image: service
+                    .get("image")
+                    .and_then(|i| i.as_str())
+                    .map(String::from),
+                published: service
+                    .get("ports")
+                    .and_then(|p| p.as_sequence())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(published_port)
+                    .collect(),
+                environment,
+            },
+        );
+    }
+    described
+}
+
+/// Ports a service node was scanned on: its processes' listening ports,
+/// or its default port when that is bound.
+fn scanned_ports(node: &Node) -> Vec<u16> {
+    let listening: Vec<u16> = node
+        .metadata
+        .get("listening_ports")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    if !listening.is_empty() {
+        return listening;
+    }
+    let bound = node.metadata.get("port_bound").and_then(|v| v.as_bool()) == Some(true);
+    node.metadata
+        .get("port")
+        .and_then(|p| p.as_u64())
+        .and_then(|p| u16::try_from(p).ok())
+        .filter(|_| bound)
+        .into_iter()
+        .collect()
+}
+
+/// Collects service URLs from the process environment, `.env` and compose
+/// `environment:`, resolves each to a service or port node and the
+/// applications that read it, and records them on the os node.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    // (variable, origin, value, compose service that sets it)
+    let mut found: Vec<(String, String, String, Option<String>)> = Vec::new();
+    for variable in URL_VARIABLES {
+        if let Some(value) = provider.env_var(variable) {
+            found.push((variable.to_string(), format!("${}", variable), value, None));
+        }
+    }
+    let dotenv_values = provider
+        .read_file(".env")
+        .map(|c| dotenv(&c))
+        .unwrap_or_default();
+    for (line, key, value) in &dotenv_values {
+        if url_variable(key) {
+            found.push((key.clone(), format!(".env:{}", line), value.clone(), None));
+        }
+    }
+    let env: BTreeMap<String, String> = dotenv_values.into_iter().map(|(_, k, v)| (k, v)).collect();
+    let compose_file = nodes
+        .iter()
+        .find(|n| n.id == "docker")
+        .and_then(|n| n.metadata.get("compose_file"))
+        .and_then(|f| f.as_str())
+        .map(String::from);
+    let compose_services = compose_file
+        .as_ref()
+        .and_then(|f| provider.read_file(f))
+        .map(|c| compose_services(&c))
+        .unwrap_or_default();
+    let variable_ref = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::?-([^}]*))?\}")
+        .expect("regex construction invariant: constant pattern");
+    for (service, described) in &compose_services {
+        for (key, value) in &described.environment {
+            if !url_variable(key) {
+                continue;
+            }
+            if let Some(value) = interpolate(provider, &variable_ref, value, &env) {
+                let origin = format!(
+                    "{} services.{}",
+                    compose_file.as_deref().unwrap_or("compose"),
+                    service
+                );
+                found.push((key.clone(), origin, value, Some(service.clone())));
+            }
+        }
+    }
+
+    let apps: Vec<(String, Vec<String>)> = nodes
+        .iter()
+        .filter(|n| n.node_type == NodeType::Application)
+        .map(|n| {
+            let vars = n
+                .metadata
+                .get("env_vars")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default();
+            (n.id.clone(), vars)
+        })
+        .collect();
+
+    let mut seen = BTreeSet::new();
+    let mut connections = Vec::new();
+    for (variable, origin, value, set_by) in found {
+        // The same URL exported and in `.env` is one connection.
+        if !seen.insert((variable.clone(), value.clone(), set_by.clone())) {
+            continue;
+        }
+        let Some((service, host, port)) = parse_connection_url(&variable, &value) else {
+            continue;
+        };
+        let local = is_local(&host);
+        let compose_service = compose_services.get(&host);
+        // A compose host name reaches the container; from this machine it is
+        // only checkable through the host port the service publishes.
+        let (kind_id, checked_port, published_port) = match compose_service {
+            Some(described) if !local => {
+                let image_service = described.image.as_deref().and_then(image_kind);
+                let published = described
+                    .published
+                    .iter()
+                    .find(|(container, _)| *container == port)
+                    .map(|(_, host_port)| *host_port);
+                (
+                    image_service.map_or(service, |k| k.id),
+                    published,
+                    published,
+                )
+            }
+            _ => (service, local.then_some(port), None),
+        };
+        let service_node = kind(kind_id)
+            .and_then(|k| k.node)
+            .filter(|id| nodes.iter().any(|n| n.id == *id))
+            .map(String::from);
+        let (node, listening) = match checked_port {
+            Some(checked) => {
+                ensure_port_node(provider, nodes, checked);
+                let port_id = format!("port{}", checked);
+                let listening = nodes
+                    .iter()
+                    .any(|n| n.id == port_id && n.status == Status::Active);
+                (service_node.or(Some(port_id)), Some(listening))
+            }
+            None => (compose_service.and(service_node), None),
+        };
+        // Compose sets the variable for its own service; `.env` and the
+        // process environment reach only the apps that read it. A URL no
+        // app names stays on the os node without an edge.
+        let consumers: Vec<String> = apps
+            .iter()
+            .filter(|(id, vars)| match &set_by {
+                Some(service) => *id == format!("app:{}", service),
+                None => vars.contains(&variable),
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        let compose_service = compose_service.map(|_| host.clone());
+        connections.push(Connection {
+            variable,
+            origin,
+            url: redact_credentials(&value),
+            service: service.into(),
+            host,
+            port,
+            local,
+            compose_service,
+            published_port,
+            node,
+            consumers,
+            listening,
+        });
+    }
+    if connections.is_empty() {
+        return;
+    }
+    if let Some(os) = nodes.iter_mut().find(|n| n.id == "os") {
+        os.metadata.insert("connections".into(), json!(connections));
+    }
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let Some(os) = state.nodes.iter().find(|n| n.id == "os") else {
+        return issues;
+    };
+    let connections: Vec<Connection> = os
+        .metadata
+        .get("connections")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    for connection in connections {
+        if connection.listening != Some(false) {
+            continue;
+        }
+        let Some(service) = kind(&connection.service) else {
+            continue;
+        };
+        if let (Some(compose), Some(published)) =
+            (&connection.compose_service, connection.published_port)
+        {
+            let target = format!("localhost:{}", published);
+            issues.push(Issue {
+                code: "CONNECTION_NO_LISTENER".into(),
+                severity: Severity::Warning,
+                title: format!("Nothing listens on {} for {}", target, connection.variable),
+                description: format!(
+                    "{} ({}) points at the {} compose service, which publishes port {} on {}, but nothing is listening there.",
+                    connection.variable, connection.origin, compose, connection.port, target
+                ),
+                suggestion: format!(
+                    "Start the {} compose service with `docker compose up -d {}`, or fix {}.",
+                    compose, compose, connection.variable
+                ),
+            });
+            continue;
+        }
+        let target = format!("{}:{}", connection.host, connection.port);
+        let scanned = service
+            .node
+            .and_then(|id| state.nodes.iter().find(|n| n.id == id))
+            .filter(|n| n.status == Status::Active)
+            .map(scanned_ports)
+            .unwrap_or_default();
+        if let Some(actual) = scanned.first() {
+            issues.push(Issue {
+                code: "CONNECTION_PORT_MISMATCH".into(),
+                severity: Severity::Critical,
+                title: format!(
+                    "{} points at port {} but {} listens on {}",
+                    connection.variable, connection.port, service.label, actual
+                ),
+                description: format!(
+                    "{} ({}) connects to {} at {}, but the scan found {} on port {}.",
+                    connection.variable,
+                    connection.origin,
+                    service.label,
+                    target,
+                    service.label,
+                    scanned
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                suggestion: format!(
+                    "Change the port in {} ({}) to {}, or move {} to port {}.",
+                    connection.variable, connection.origin, actual, service.label, connection.port
+                ),
+            });
+        } else {
+            issues.push(Issue {
+                code: "CONNECTION_NO_LISTENER".into(),
+                severity: Severity::Warning,
+                title: format!("Nothing listens on {} for {}", target, connection.variable),
+                description: format!(
+                    "{} ({}) points at {} on {}, but no process is listening on port {}.",
+                    connection.variable, connection.origin, service.label, target, connection.port
+                ),
+                suggestion: format!(
+                    "Start {} on port {} (for example with `docker compose up -d`), or fix {}.",
+                    service.label, connection.port, connection.variable
+                ),
+            });
+        }
+    }
+    issues
+}
@@ -0,0 +1,554 @@
+use crate::eol;
+use crate::models::{Issue, Node, NodeType, Severity, Status, SystemState};
+use crate::system_provider::SystemProvider;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+const DEVCONTAINER_FILES: &[&str] = &[".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+/// One `FROM` line; `image` is the ARG-resolved reference or the name of
+/// an earlier stage.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Stage {
+    pub image: String,
+    pub name: Option<String>,
+    pub line: usize,
+}
+
+/// A language runtime version a container is built with.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContainerRuntime {
+    /// End-of-life calendar key of the runtime (`python`, `nodejs`, `go`, `java`).
+    pub runtime: String,
+    pub version: String,
+    /// `file:line` the version comes from.
+    pub origin: String,
+    /// The image, ARG/ENV or feature that sets it.
+    pub evidence: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dockerfile {
+    pub stages: Vec<Stage>,
+    pub exposed_ports: Vec<u16>,
+    pub runtimes: Vec<ContainerRuntime>,
+    pub unresolved_args: Vec<String>,
+}
+
+fn runtime_for_image(repository: &str) -> Option<&'static str> {
+    match repository {
+        "python" | "pypy" => Some("python"),
+        "node" | "javascript-node" | "typescript-node" => Some("nodejs"),
+        "golang" | "go" => Some("go"),
+        "openjdk" | "eclipse-temurin" | "amazoncorretto" | "sapmachine" | "java" => Some("java"),
+        _ => None,
+    }
+}
+
+fn runtime_for_variable(name: &str) -> Option<&'static str> {
+    match name {
+        "PYTHON_VERSION" => Some("python"),
+        "NODE_VERSION" => Some("nodejs"),
+        "GO_VERSION" | "GOLANG_VERSION" => Some("go"),
+        "JAVA_VERSION" => Some("java"),
+        _ => None,
+    }
+}
+
+fn runtime_label(runtime: &str) -> &str {
+    match runtime {
+        "python" => "Python",
+        "nodejs" => "Node.js",
+        "go" => "Go",
+        "java" => "Java",
+        other => other,
+    }
+}
+
+fn pin_file(runtime: &str) -> &'static str {
+    match runtime {
+        "python" => ".python-version",
+        "nodejs" => ".nvmrc",
+        "java" => ".sdkmanrc",
+        _ => ".tool-versions",
+    }
+}
+
+/// Leading numeric version of a tag or variable, e.g. `3.11` from `3.11-slim`.
+fn leading_version(raw: &str) -> Option<String> {
+    Regex::new(r"^v?(\d+(?:\.\d+)*)")
+        .expect("regex construction invariant: constant pattern")
+        .captures(raw.trim())
+        .map(|c| c[1].to_string())
+}
+
+/// The runtime and version an image reference implies, such as
+/// `("python", "3.11")` for `docker.io/library/python:3.11-slim@sha256:…`.
+pub fn image_runtime(image: &str) -> Option<(&'static str, String)> {
+    let image = image.split('@').next().unwrap_or(image);
+    let (path, tag) = match image.rsplit_once(':') {
+        Some((path, tag)) if !tag.contains('/') => (path, tag),
+        _ => (image, ""),
+    };
+    let runtime = runtime_for_image(path.rsplit('/').next().unwrap_or(path))?;
+    // Dev container images prefix the runtime version with their own
+    // major version: `devcontainers/python:1-3.11-bullseye`.
+    let tag = match tag.split_once('-') {
+        Some((major, rest))
+            if path.contains("devcontainers/") && major.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            rest
+        }
+        _ => tag,
+    };
+    Some((runtime, leading_version(tag)?))
+}
+
+/// Logical instructions as (line, keyword, arguments), with continuation
+/// lines joined and comments dropped.
+fn instructions(contents: &str) -> Vec<(usize, String, String)> {
+    let mut result = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (index, raw) in contents.lines().enumerate() {
+        let line = raw.trim();
+        if line.starts_with('#') || (line.is_empty() && current.is_none()) {
+            continue;
+        }
+        let (text, continues) = match line.strip_suffix('\\') {
+            Some(text) => (text.trim_end(), true),
+            None => (line, false),
+        };
+        let entry = current.get_or_insert_with(|| (index + 1, String::new()));
+        if !entry.1.is_empty() {
+            entry.1.push(' ');
+        }
+        entry.1.push_str(text);
+        if !continues {
+            if let Some((at, text)) = current.take() {
+                let (keyword, args) = text.split_once(char::is_whitespace).unwrap_or((&text, ""));
+                result.push((at, keyword.to_uppercase(), args.trim().to_string()));
+            }
+        }
+    }
+    result
+}
+
+/// Expands `$VAR`, `${VAR}`, `${VAR:-default}` and `${VAR:+alt}`,
+/// recording variables with no value.
+fn substitute(value: &str, vars: &BTreeMap<String, String>, missing: &mut Vec<String>) -> String {
+    let re =
+        Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::([-+])([^}]*))?\}|\$([A-Za-z_][A-Za-z0-9_]*)")
+            .expect("regex construction invariant: constant pattern");
+    re.replace_all(value, |c: &regex::Captures| {
+        let name = c
+            .get(1)
+            .or_else(|| c.get(4))
+            .map(|m| m.as_str())
+            .unwrap_or("");
+        let set = vars.get(name).filter(|v| !v.is_empty());
+        match (c.get(2).map(|m| m.as_str()), set) {
+            (Some("-"), None) => c[3].to_string(),
+            (Some("+"), Some(_)) => c[3].to_string(),
+            (Some("+"), None) => String::new(),
+            (_, Some(v)) => v.clone(),
+            (_, None) => {
+                if !missing.iter().any(|m| m == name) {
+                    missing.push(name.to_string());
+                }
+                String::new()
+            }
+        }
+    })
+    .into_owned()
+}
+
+/// `KEY=value` pairs of an ARG or ENV instruction, including the legacy
+/// `ENV KEY value` form.
+fn assignments(keyword: &str, args: &str) -> Vec<(String, Option<String>)> {
+    if keyword == "ENV" && !args.split_whitespace().next().unwrap_or("").contains('=') {
+        return match args.split_once(char::is_whitespace) {
+            Some((key, value)) => vec![(key.to_string(), Some(value.trim().to_string()))],
+            None => Vec::new(),
+        };
+    }
+    args.split_whitespace()
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.to_string(), Some(value.trim_matches('"').to_string())),
+            None => (pair.to_string(), None),
+        })
+        .collect()
+}
+
+/// Parses a Dockerfile, resolving ARG and ENV values with `build_args`
+/// overriding ARG defaults.
+pub fn parse_dockerfile(
+    path: &str,
+    contents: &str,
+    build_args: &BTreeMap<String, String>,
+) -> Dockerfile {
+    let mut parsed = Dockerfile::default();
+    let mut global: BTreeMap<String, String> = BTreeMap::new();
+    let mut stage_vars: Vec<BTreeMap<String, String>> = Vec::new();
+    let mut vars: BTreeMap<String, String> = BTreeMap::new();
+    let mut ports = BTreeSet::new();
+    let mut seen = BTreeSet::new();
+    let mut runtimes = Vec::new();
+
+    for (line, keyword, args) in instructions(contents) {
+        match keyword.as_str() {
+            "FROM" => {
+                let mut words = args.split_whitespace().filter(|w| !w.starts_with("--"));
+                let image = substitute(
+                    words.next().unwrap_or(""),
+                    &global,
+                    &mut parsed.unresolved_args,
+                );
+                let name = match (words.next(), words.next()) {
+                    (Some(as_), Some(name)) if as_.eq_ignore_ascii_case("as") => {
+                        Some(name.to_string())
+                    }
+                    _ => None,
+                };
+                if !parsed.stages.is_empty() {
+                    stage_vars.push(std::mem::take(&mut vars));
+                }
+                // A stage built on an earlier one inherits its environment.
+                if let Some(parent) = parsed
+                    .stages
+                    .iter()
+                    .position(|s| s.name.as_deref() == Some(image.as_str()))
+                {
+                    vars = stage_vars[parent].clone();
+                } else if let Some((runtime, version)) = image_runtime(&image) {
+                    runtimes.push(ContainerRuntime {
+                        runtime: runtime.into(),
+                        version,
+                        origin: format!("{}:{}", path, line),
+                        evidence: format!("FROM {}", image),
+                    });
+                }
+                parsed.stages.push(Stage { image, name, line });
+            }
+            "ARG" | "ENV" => {
+                let in_stage = !parsed.stages.is_empty();
+                for (key, value) in assignments(&keyword, &args) {
+                    let scope = if in_stage { &vars } else { &global };
+                    let value = match (keyword.as_str(), build_args.get(&key), value) {
+                        ("ARG", Some(arg), _) => Some(arg.clone()),
+                        (_, _, Some(v)) => Some(substitute(&v, scope, &mut parsed.unresolved_args)),
+                        // `ARG NAME` inside a stage brings the global default into scope.
+                        ("ARG", None, None) => global.get(&key).cloned(),
+                        _ => None,
+                    };
+                    let Some(value) = value else {
+                        continue;
+                    };
+                    if in_stage {
+                        if let (Some(runtime), Some(version)) =
+                            (runtime_for_variable(&key), leading_version(&value))
+                        {
+                            runtimes.push(ContainerRuntime {
+                                runtime: runtime.into(),
+                                version,
+                                origin: format!("{}:{}", path, line),
+                                evidence: format!("{} {}={}", keyword, key, value),
+                            });
+                        }
+                        vars.insert(key, value);
+                    } else {
+                        global.insert(key, value);
+                    }
+                }
+            }
+            "EXPOSE" => {
+                for word in args.split_whitespace() {
+                    let word = substitute(word, &vars, &mut parsed.unresolved_args);
+                    if let Ok(port) = word.split('/').next().unwrap_or("").parse::<u16>() {
+                        ports.insert(port);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for runtime in runtimes {
+        if seen.insert((runtime.runtime.clone(), runtime.version.clone())) {
+            parsed.runtimes.push(runtime);
+        }
+    }
+    parsed.exposed_ports = ports.into_iter().collect();
+    parsed
+}
+
+/// Drops `//` and `/* */` comments and trailing commas so devcontainer.json
+/// (JSON with comments) parses as JSON.
+fn strip_jsonc(contents: &str) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    Regex::new(r",(\s*[}\]])")
+        .expect("regex construction invariant: constant pattern")
+        .replace_all(&out, "$1")
+        .into_owned()
+}
+
+fn image_node(id: String, label: String, metadata: BTreeMap<String, Value>) -> Node {
+    Node {
+        id,
+        node_type: NodeType::DockerImages,
+        label,
+        status: Status::Active,
+        metadata,
+    }
+}
+
+fn is_dockerfile(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower == "dockerfile"
+        || lower == "containerfile"
+        || lower.starts_with("dockerfile.")
+        || lower.ends_with(".dockerfile")
+}
+
+fn dockerfile_metadata(path: &str, parsed: &Dockerfile) -> BTreeMap<String, Value> {
+    let stage_names: BTreeSet<&str> = parsed
+        .stages
+        .iter()
+        .filter_map(|s| s.name.as_deref())
+        .collect();
+    let base_images: Vec<&str> = parsed
+        .stages
+        .iter()
+        .map(|s| s.image.as_str())
+        .filter(|image| !stage_names.contains(image))
+        .collect();
+    let mut metadata = BTreeMap::new();
+    metadata.insert("dockerfile".into(), json!(path));
+    metadata.insert("base_images".into(), json!(base_images));
+    metadata.insert("stages".into(), json!(parsed.stages));
+    metadata.insert("exposed_ports".into(), json!(parsed.exposed_ports));
+    metadata.insert("runtimes".into(), json!(parsed.runtimes));
+    metadata.insert("unresolved_args".into(), json!(parsed.unresolved_args));
+    metadata
+}
+
+fn detect_devcontainer<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let Some((path, config)) = DEVCONTAINER_FILES.iter().find_map(|path| {
+        let contents = provider.read_file(path)?;
+        Some((
+            *path,
+            serde_json::from_str::<Value>(&strip_jsonc(&contents)).ok()?,
+        ))
+    }) else {
+        return;
+    };
+    let dir = path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or(".");
+    let mut metadata = BTreeMap::new();
+    metadata.insert("config_file".into(), json!(path));
+    let mut runtimes = Vec::new();
+
+    let build = config.get("build");
+    let dockerfile = build
+        .and_then(|b| b.get("dockerfile"))
+        .or_else(|| config.get("dockerFile"))
+        .and_then(|v| v.as_str());
+    if let Some(dockerfile) = dockerfile {
+        let build_args: BTreeMap<String, String> = build
+            .and_then(|b| b.get("args"))
+            .and_then(|a| a.as_object())
+            .map(|args| {
+                args.iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let file = format!("{}/{}", dir, dockerfile.trim_start_matches("./"));
+        if let Some(contents) = provider.read_file(&file) {
+            let parsed = parse_dockerfile(&file, &contents, &build_args);
+            runtimes.extend(parsed.runtimes.clone());
+            metadata.extend(dockerfile_metadata(&file, &parsed));
+        }
+    } else if let Some(image) = config.get("image").and_then(|v| v.as_str()) {
+        metadata.insert("base_images".into(), json!([image]));
+        if let Some((runtime, version)) = image_runtime(image) {
+            runtimes.push(ContainerRuntime {
+                runtime: runtime.into(),
+                version,
+                origin: path.into(),
+                evidence: format!("image {}", image),
+            });
+        }
+    }
+
+    let mut features = Vec::new();
+    for (id, options) in config
+        .get("features")
+        .and_then(|f| f.as_object())
+        .into_iter()
+        .flatten()
+    {
+        features.push(id.clone());
+        // `ghcr.io/devcontainers/features/node:1` with `{"version": "20"}`.
+        let name = id.rsplit('/').next().unwrap_or(id);
+        let name = name.split(':').next().unwrap_or(name);
+        let version = options
+            .get("version")
+            .and_then(|v| v.as_str())
+            .or_else(|| options.as_str())
+            .and_then(leading_version);
+        let runtime = match name {
+            "python" => Some("python"),
+            "node" => Some("nodejs"),
+            "go" => Some("go"),
+            "java" => Some("java"),
+            _ => None,
+        };
+        if let (Some(runtime), Some(version)) = (runtime, version) {
+            runtimes.push(ContainerRuntime {
+                runtime: runtime.into(),
+                version,
+                origin: path.into(),
+                evidence: format!("feature {}", id),
+            });
+        }
+    }
+    let forward_ports: BTreeSet<u16> = ["forwardPorts", "appPort"]
+        .iter()
+        .filter_map(|key| config.get(*key))
+        .flat_map(|v| match v {
+            Value::Array(items) => items.clone(),
+            other => vec![other.clone()],
+        })
+        .filter_map(|p| match p {
+            Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+            // `"db:5432"` forwards a compose service's port.
+            Value::String(s) => s.rsplit(':').next()?.parse().ok(),
+            _ => None,
+        })
+        .collect();
+    metadata.insert("features".into(), json!(features));
+    metadata.insert("forward_ports".into(), json!(forward_ports));
+    metadata.insert("runtimes".into(), json!(runtimes));
+    let label = config
+        .get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Dev Container");
+    nodes.push(image_node("devcontainer".into(), label.into(), metadata));
+}
+
+/// Adds a node per Dockerfile in the project root and one for the dev
+/// container, recording base images, exposed ports and the runtime
+/// versions they build with.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let mut names: Vec<String> = provider
+        .list_dir(".")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| is_dockerfile(name))
+        .collect();
+    names.sort();
+    for name in names {
+        let Some(contents) = provider.read_file(&name) else {
+            continue;
+        };
+        let parsed = parse_dockerfile(&name, &contents, &BTreeMap::new());
+        nodes.push(image_node(
+            format!("dockerfile:{}", name),
+            name.clone(),
+            dockerfile_metadata(&name, &parsed),
+        ));
+    }
+    detect_devcontainer(provider, nodes);
+}
+
+/// True when the host version is on the release line the container pins,
+/// compared to at most major.minor.
+fn same_line(container: &str, host: &str) -> bool {
+    let parts = |v: &str| -> Vec<String> { v.split('.').map(str::to_string).collect() };
+    let (container, host) = (parts(container), parts(host));
+    let depth = container.len().min(2);
+    host.len() >= depth && container[..depth] == host[..depth]
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let host: BTreeMap<&str, String> = eol::installed_versions(state).into_iter().collect();
+    let mut issues = Vec::new();
+    for node in state
+        .nodes
+        .iter()
+        .filter(|n| n.node_type == NodeType::DockerImages)
+    {
+        let runtimes: Vec<ContainerRuntime> = node
+            .metadata
+            .get("runtimes")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+        for runtime in runtimes {
+            let Some(host_version) = host.get(runtime.runtime.as_str()) else {
+                continue;
+            };
+            if same_line(&runtime.version, host_version) {
+                continue;
+            }
+            let label = runtime_label(&runtime.runtime);
+            issues.push(Issue {
+                code: "CONTAINER_RUNTIME_MISMATCH".into(),
+                severity: Severity::Warning,
+                title: format!(
+                    "{} uses {} {} but the host has {}",
+                    node.label, label, runtime.version, host_version
+                ),
+                description: format!(
+                    "{} ({}) builds with {} {} while the host runs {}, so tests run inside and outside the container exercise different runtimes.",
+                    runtime.origin, runtime.evidence, label, runtime.version, host_version
+                ),
+                suggestion: format!(
+                    "Install {} {} on the host and pin it in {}, or change {} to match the host.",
+                    label,
+                    runtime.version,
+                    pin_file(&runtime.runtime),
+                    runtime.origin
+                ),
+            });
+        }
+    }
+    issues
+}
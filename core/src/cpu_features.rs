@@ -0,0 +1,243 @@
+use crate::models::Node;
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use serde_json::Value;
+
+/// x86-64 psABI microarchitecture levels and the flags each adds over the
+/// previous one, as named in `/proc/cpuinfo`.
+pub const X86_64_LEVELS: &[(&str, &[&str])] = &[
+    (
+        "x86-64-v2",
+        &["cx16", "lahf_lm", "popcnt", "sse4_1", "sse4_2", "ssse3"],
+    ),
+    (
+        "x86-64-v3",
+        &[
+            "avx", "avx2", "bmi1", "bmi2", "f16c", "fma", "abm", "movbe", "xsave",
+        ],
+    ),
+    (
+        "x86-64-v4",
+        &["avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl"],
+    ),
+];
+
+/// Packages whose published wheels are compiled with CPU extensions and
+/// crash with an illegal instruction without them.
+const WHEEL_FLAG_REQUIREMENTS: &[(&str, &str, &str)] = &[
+    (
+        "tensorflow",
+        "avx",
+        "tensorflow built from source without AVX",
+    ),
+    (
+        "tensorflow-cpu",
+        "avx",
+        "tensorflow built from source without AVX",
+    ),
+    ("jaxlib", "avx", "jaxlib built from source without AVX"),
+    ("polars", "avx2", "polars-lts-cpu"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CpuInfo {
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub flags: Vec<String>,
+}
+
+/// Reads the first processor block of `/proc/cpuinfo`. x86 lists `flags`;
+/// ARM lists `Features` and identifies the core by implementer and part.
+pub fn parse_cpuinfo(contents: &str) -> CpuInfo {
+    let mut info = CpuInfo::default();
+    let mut implementer = None;
+    let mut part = None;
+    for line in contents.lines() {
+        if line.trim().is_empty() && !info.flags.is_empty() {
+            break;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "vendor_id" => info.vendor = Some(value.to_string()),
+            "model name" | "Model" | "cpu model" => info.model = Some(value.to_string()),
+            "flags" | "Features" => {
+                info.flags = value.split_whitespace().map(|f| f.to_string()).collect()
+            }
+            "CPU implementer" => implementer = Some(value.to_string()),
+            "CPU part" => part = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if info.vendor.is_none() {
+        info.vendor = implementer.clone();
+    }
+    if info.model.is_none() {
+        if let (Some(implementer), Some(part)) = (implementer, part) {
+            info.model = Some(format!("implementer {} part {}", implementer, part));
+        }
+    }
+    info.flags.sort();
+    info.flags.dedup();
+    info
+}
+
+/// Highest x86-64 microarchitecture level the flags satisfy.
+pub fn x86_64_level(flags: &[String]) -> &'static str {
+    let mut level = "x86-64";
+    for (name, required) in X86_64_LEVELS {
+        if required.iter().all(|f| flags.iter().any(|have| have == f)) {
+            level = name;
+        } else {
+            break;
+        }
+    }
+    level
+}
+
+/// Normalises `uname -m` and wheel platform spellings onto one name.
+pub fn normalize_arch(arch: &str) -> String {
+    match arch.trim().to_lowercase().as_str() {
+        "amd64" | "x64" | "x86_64" => "x86_64".into(),
+        "arm64" | "aarch64" => "aarch64".into(),
+        "i386" | "i686" | "x86" => "i686".into(),
+        other => other.into(),
+    }
+}
+
+/// Architecture encoded in a wheel tag such as
+/// `cp311-cp311-manylinux_2_17_x86_64`. Pure and universal wheels return `None`.
+pub fn wheel_arch(tag: &str) -> Option<String> {
+    let platform = tag.rsplit('-').next()?;
+    if platform == "any" || platform.ends_with("universal2") {
+        return None;
+    }
+    let arch = [
+        "x86_64", "aarch64", "arm64", "amd64", "i686", "ppc64le", "s390x", "armv7l",
+    ]
+    .into_iter()
+    .find(|a| platform.ends_with(a))
+    .or_else(|| platform.ends_with("win32").then_some("i686"))?;
+    Some(normalize_arch(arch))
+}
+
+fn site_packages<P: SystemProvider>(provider: &P) -> Option<String> {
+    let args = [
+        "-c",
+        "import sysconfig; print(sysconfig.get_paths()['platlib'])",
+    ];
+    provider
+        .command_output("python", &args)
+        .or_else(|| provider.command_output("python3", &args))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// `numpy-1.26.4.dist-info` -> (`numpy`, `1.26.4`).
+fn dist_info_name(dir: &str) -> Option<(String, String)> {
+    let stem = dir.strip_suffix(".dist-info")?;
+    let (name, version) = stem.rsplit_once('-')?;
+    Some((name.replace('_', "-").to_lowercase(), version.to_string()))
+}
+
+fn native_wheels<P: SystemProvider>(
+    provider: &P,
+    site: &str,
+) -> Vec<(String, String, Vec<String>)> {
+    let mut wheels = Vec::new();
+    for dir in provider.list_dir(site).unwrap_or_default() {
+        let Some((name, version)) = dist_info_name(&dir) else {
+            continue;
+        };
+        let Some(wheel) = provider.read_file(&format!("{}/{}/WHEEL", site, dir)) else {
+            continue;
+        };
+        let tags: Vec<String> = wheel
+            .lines()
+            .filter_map(|l| l.strip_prefix("Tag:"))
+            .map(|t| t.trim().to_string())
+            .collect();
+        wheels.push((name, version, tags));
+    }
+    wheels.sort();
+    wheels
+}
+
+fn find_node<'a>(nodes: &'a mut [Node], id: &str) -> Option<&'a mut Node> {
+    nodes.iter_mut().find(|n| n.id == id)
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let cpu = provider
+        .read_file("/proc/cpuinfo")
+        .map(|c| parse_cpuinfo(&c))
+        .unwrap_or_default();
+    let host_arch = find_node(nodes, "os")
+        .and_then(|n| n.metadata.get("arch"))
+        .and_then(|v| v.as_str())
+        .map(normalize_arch);
+    let microarch = match host_arch.as_deref() {
+        Some("x86_64") if !cpu.flags.is_empty() => Some(x86_64_level(&cpu.flags).to_string()),
+        Some(other) => Some(other.to_string()),
+        None => None,
+    };
+
+    if let Some(os) = find_node(nodes, "os") {
+        if let Some(vendor) = &cpu.vendor {
+            os.metadata.insert("cpu_vendor".into(), json!(vendor));
+        }
+        if let Some(model) = &cpu.model {
+            os.metadata.insert("cpu_model".into(), json!(model));
+        }
+        if let Some(level) = &microarch {
+            os.metadata.insert("cpu_microarch".into(), json!(level));
+        }
+        os.metadata.insert("cpu_flags".into(), json!(cpu.flags));
+    }
+
+    let Some(site) = site_packages(provider) else {
+        return;
+    };
+    let mut arch_mismatches: Vec<Value> = Vec::new();
+    let mut flag_mismatches: Vec<Value> = Vec::new();
+    for (name, version, tags) in native_wheels(provider, &site) {
+        let arches: Vec<String> = tags.iter().filter_map(|t| wheel_arch(t)).collect();
+        if let Some(host) = &host_arch {
+            if !arches.is_empty() && !arches.contains(host) {
+                arch_mismatches.push(json!({
+                    "package": name,
+                    "version": version,
+                    "tags": tags,
+                    "wheel_arch": arches[0],
+                }));
+            }
+        }
+        if cpu.flags.is_empty() {
+            continue;
+        }
+        for (package, flag, alternative) in WHEEL_FLAG_REQUIREMENTS {
+            if *package == name && !cpu.flags.iter().any(|f| f == flag) {
+                flag_mismatches.push(json!({
+                    "package": name,
+                    "version": version,
+                    "flag": flag,
+                    "alternative": alternative,
+                }));
+            }
+        }
+    }
+
+    if let Some(python) = find_node(nodes, "python") {
+        python.metadata.insert("site_packages".into(), json!(site));
+        python.metadata.insert(
+            "native_wheel_arch_mismatches".into(),
+            json!(arch_mismatches),
+        );
+        python.metadata.insert(
+            "native_wheel_flag_mismatches".into(),
+            json!(flag_mismatches),
+        );
+    }
+}
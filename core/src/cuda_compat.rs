@@ -0,0 +1,499 @@
+use crate::models::{Issue, Node, Severity, SystemState};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+const TABLE: &str = include_str!("../data/cuda_compat.json");
+
+/// pip packages whose CUDA build is checked against the driver and toolkit.
+pub const FRAMEWORK_PACKAGES: &[&str] = &[
+    "torch",
+    "tensorflow",
+    "tensorflow-gpu",
+    "jaxlib",
+    "jax-cuda11-plugin",
+    "jax-cuda12-plugin",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DriverMinimum {
+    pub cuda: String,
+    pub driver: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CudnnRange {
+    pub cudnn: String,
+    pub cuda_min: String,
+    pub cuda_max: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TorchDefault {
+    pub torch: String,
+    pub cuda: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TorchWheel {
+    pub tag: String,
+    pub cuda: String,
+    pub index_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TensorflowBuild {
+    pub tensorflow: String,
+    pub cuda: String,
+    pub cudnn: String,
+}
+
+/// The bundled driver/CUDA/cuDNN/framework compatibility table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompatTable {
+    pub version: String,
+    pub driver_minimums: Vec<DriverMinimum>,
+    pub cudnn: Vec<CudnnRange>,
+    pub torch_default_cuda: Vec<TorchDefault>,
+    pub torch_wheels: Vec<TorchWheel>,
+    pub tensorflow: Vec<TensorflowBuild>,
+}
+
+pub fn table() -> CompatTable {
+    serde_json::from_str(TABLE).expect("bundled table invariant: cuda_compat.json must parse")
+}
+
+/// Numeric components of a dotted version, ignoring any local or
+/// pre-release suffix on each component.
+pub fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split(['+', ' '])
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .map_while(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+fn at_least(version: &str, minimum: &str) -> bool {
+    version_parts(version) >= version_parts(minimum)
+}
+
+/// True when `version` falls under the `prefix` release line, e.g. `2.3.1`
+/// under `2.3`.
+fn in_line(version: &str, prefix: &str) -> bool {
+    let parts = version_parts(version);
+    let prefix = version_parts(prefix);
+    !prefix.is_empty() && parts.len() >= prefix.len() && parts[..prefix.len()] == prefix[..]
+}
+
+fn major(version: &str) -> Option<u64> {
+    version_parts(version).first().copied()
+}
+
+/// Extracts `12.2` from `nvcc --version` output.
+pub fn parse_nvcc_release(output: &str) -> Option<String> {
+    let re =
+        Regex::new(r"release (\d+\.\d+)").expect("regex construction invariant: constant pattern");
+    re.captures(output).map(|c| c[1].to_string())
+}
+
+/// CUDA version a torch wheel was built for: the `+cu121` local version, or
+/// the table default for plain PyPI wheels. CPU and ROCm builds return `None`.
+pub fn torch_cuda(table: &CompatTable, version: &str) -> Option<String> {
+    match version.split_once('+') {
+        Some((_, local)) => {
+            let digits = local.strip_prefix("cu")?;
+            if digits.len() < 3 || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let (major, minor) = digits.split_at(digits.len() - 1);
+            Some(format!("{}.{}", major, minor))
+        }
+        None => table
+            .torch_default_cuda
+            .iter()
+            .find(|d| in_line(version, &d.torch))
+            .map(|d| d.cuda.clone()),
+    }
+}
+
+/// CUDA major a JAX package targets, from the plugin name or the
+/// `+cuda12.cudnn89` local version of older jaxlib wheels.
+pub fn jax_cuda(package: &str, version: &str) -> Option<String> {
+    if let Some(major) = package
+        .strip_prefix("jax-cuda")
+        .and_then(|rest| rest.strip_suffix("-plugin"))
+    {
+        return Some(major.to_string());
+    }
+    let (_, local) = version.split_once('+')?;
+    let major: String = local
+        .strip_prefix("cuda")?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    (!major.is_empty()).then_some(major)
+}
+
+/// The highest CUDA version a driver release can run.
+pub fn driver_max_cuda(table: &CompatTable, driver: &str) -> Option<String> {
+    table
+        .driver_minimums
+        .iter()
+        .filter(|m| at_least(driver, &m.driver))
+        .max_by(|a, b| version_parts(&a.cuda).cmp(&version_parts(&b.cuda)))
+        .map(|m| m.cuda.clone())
+}
+
+fn minimum_driver<'a>(table: &'a CompatTable, cuda: &str) -> Option<&'a str> {
+    table
+        .driver_minimums
+        .iter()
+        .find(|m| in_line(cuda, &m.cuda))
+        .map(|m| m.driver.as_str())
+}
+
+fn cudnn_range<'a>(table: &'a CompatTable, cudnn: &str) -> Option<&'a CudnnRange> {
+    table
+        .cudnn
+        .iter()
+        .filter(|r| in_line(cudnn, &r.cudnn))
+        .max_by_key(|r| version_parts(&r.cudnn).len())
+}
+
+/// Newest torch wheel tag the driver can run.
+fn torch_wheel_for<'a>(table: &'a CompatTable, driver_cuda: &str) -> Option<&'a TorchWheel> {
+    table
+        .torch_wheels
+        .iter()
+        .filter(|w| driver_supports(driver_cuda, &w.cuda))
+        .max_by(|a, b| version_parts(&a.cuda).cmp(&version_parts(&b.cuda)))
+}
+
+fn torch_wheel_for_major(table: &CompatTable, cuda_major: u64) -> Option<&TorchWheel> {
+    table
+        .torch_wheels
+        .iter()
+        .filter(|w| major(&w.cuda) == Some(cuda_major))
+        .max_by(|a, b| version_parts(&a.cuda).cmp(&version_parts(&b.cuda)))
+}
+
+fn metadata_str<'a>(node: &'a Node, key: &str) -> Option<&'a str> {
+    node.metadata.get(key).and_then(|v| v.as_str())
+}
+
+/// Framework wheels bundle their CUDA runtime, which runs on any driver of
+/// the same major through CUDA minor version compatibility.
+fn driver_supports(driver_cuda: &str, needed: &str) -> bool {
+    major(driver_cuda) >= major(needed)
+}
+
+struct GpuFacts {
+    driver: Option<String>,
+    driver_cuda: Option<String>,
+    toolkit: Option<String>,
+    cudnn: Option<String>,
+}
+
+fn gpu_facts(state: &SystemState, table: &CompatTable) -> Option<GpuFacts> {
+    let gpu = state.nodes.iter().find(|n| n.id == "gpu")?;
+    let driver = state
+        .nodes
+        .iter()
+        .filter(|n| n.id.starts_with("gpu_nvidia"))
+        .find_map(|n| metadata_str(n, "driver_version"))
+        .map(|s| s.to_string());
+    let driver_cuda = metadata_str(gpu, "driver_cuda_version")
+        .map(|s| s.to_string())
+        .or_else(|| driver.as_deref().and_then(|d| driver_max_cuda(table, d)));
+    Some(GpuFacts {
+        driver,
+        driver_cuda,
+        toolkit: metadata_str(gpu, "cuda_version").and_then(parse_nvcc_release),
+        cudnn: metadata_str(gpu, "cudnn_version").map(|s| s.to_string()),
+    })
+}
+
+fn driver_label(facts: &GpuFacts, driver_cuda: &str) -> String {
+    match &facts.driver {
+        Some(driver) => format!("NVIDIA driver {} (CUDA <= {})", driver, driver_cuda),
+        None => format!("the NVIDIA driver (CUDA <= {})", driver_cuda),
+    }
+}
+
+fn driver_upgrade(table: &CompatTable, cuda: &str) -> String {
+    match minimum_driver(table, cuda) {
+        Some(min) => format!("Upgrade the NVIDIA driver to {} or newer", min),
+        None => "Upgrade the NVIDIA driver".into(),
+    }
+}
+
+fn check_toolkit(table: &CompatTable, facts: &GpuFacts, issues: &mut Vec<Issue>) {
+    let (Some(toolkit), Some(driver_cuda)) = (&facts.toolkit, &facts.driver_cuda) else {
+        return;
+    };
+    let required = minimum_driver(table, toolkit);
+    let compatible = match (&facts.driver, required) {
+        (Some(driver), Some(required)) => at_least(driver, required),
+        _ => at_least(driver_cuda, toolkit),
+    };
+    if compatible {
+        return;
+    }
+    let requirement = required
+        .map(|r| format!(" requires driver >= {}", r))
+        .unwrap_or_default();
+    issues.push(Issue {
+        code: "CUDA_VERSION_MISMATCH".into(),
+        severity: Severity::Warning,
+        title: "CUDA toolkit newer than driver".into(),
+        description: format!(
+            "CUDA toolkit {} (nvcc){} but {} cannot run it.",
+            toolkit,
+            requirement,
+            driver_label(facts, driver_cuda)
+        ),
+        suggestion: format!(
+            "{}, or install CUDA toolkit {} to match the driver.",
+            driver_upgrade(table, toolkit),
+            driver_cuda
+        ),
+    });
+}
+
+fn check_cudnn(table: &CompatTable, facts: &GpuFacts, issues: &mut Vec<Issue>) {
+    let (Some(cudnn), Some(toolkit)) = (&facts.cudnn, &facts.toolkit) else {
+        return;
+    };
+    let Some(range) = cudnn_range(table, cudnn) else {
+        return;
+    };
+    let toolkit_line: Vec<u64> = version_parts(toolkit).into_iter().take(2).collect();
+    if toolkit_line >= version_parts(&range.cuda_min)
+        && toolkit_line <= version_parts(&range.cuda_max)
+    {
+        return;
+    }
+    issues.push(Issue {
+        code: "CUDNN_CUDA_MISMATCH".into(),
+        severity: Severity::Warning,
+        title: "cuDNN incompatible with CUDA toolkit".into(),
+        description: format!(
+            "cuDNN {} supports CUDA {} to {}, but the CUDA toolkit (nvcc) is {}.",
+            cudnn, range.cuda_min, range.cuda_max, toolkit
+        ),
+        suggestion: format!(
+            "Install the cuDNN build for CUDA {} (for example libcudnn{}-cuda-{}).",
+            toolkit,
+            if major(toolkit) >= Some(12) { "9" } else { "8" },
+            major(toolkit).unwrap_or_default()
+        ),
+    });
+}
+
+fn check_torch(table: &CompatTable, facts: &GpuFacts, version: &str, issues: &mut Vec<Issue>) {
+    let Some(built_for) = torch_cuda(table, version) else {
+        return;
+    };
+    if let Some(driver_cuda) = &facts.driver_cuda {
+        if !driver_supports(driver_cuda, &built_for) {
+            let wheel = torch_wheel_for(table, driver_cuda);
+            let (code, suggestion) = match wheel {
+                Some(w) => (
+                    format!("TORCH_NEEDS_{}", w.tag.to_uppercase()),
+                    format!(
+                        "Reinstall torch from {} (CUDA {}), or {}.",
+                        w.index_url,
+                        w.cuda,
+                        driver_upgrade(table, &built_for).replacen("Upgrade", "upgrade", 1)
+                    ),
+                ),
+                None => (
+                    "TORCH_CUDA_MISMATCH".into(),
+                    format!("{}.", driver_upgrade(table, &built_for)),
+                ),
+            };
+            issues.push(Issue {
+                code,
+                severity: Severity::Critical,
+                title: "PyTorch wheel incompatible with driver".into(),
+                description: format!(
+                    "torch {} is built for CUDA {} but {} cannot run it.",
+                    version,
+                    built_for,
+                    driver_label(facts, driver_cuda)
+                ),
+                suggestion,
+            });
+            return;
+        }
+    }
+    if let (Some(toolkit), Some(torch_major)) = (&facts.toolkit, major(&built_for)) {
+        if major(toolkit) != Some(torch_major) {
+            let suggestion = match major(toolkit).and_then(|m| torch_wheel_for_major(table, m)) {
+                Some(w) => format!(
+                    "Reinstall torch from {} or install CUDA toolkit {}.x before building extensions.",
+                    w.index_url, torch_major
+                ),
+                None => format!(
+                    "Install CUDA toolkit {}.x before building torch extensions.",
+                    torch_major
+                ),
+            };
+            issues.push(Issue {
+                code: "TORCH_TOOLKIT_MISMATCH".into(),
+                severity: Severity::Warning,
+                title: "PyTorch and CUDA toolkit majors differ".into(),
+                description: format!(
+                    "torch {} is built for CUDA {} but the CUDA toolkit (nvcc) is {}; locally compiled CUDA extensions will not load.",
+                    version, built_for, toolkit
+                ),
+                suggestion,
+            });
+        }
+    }
+}
+
+fn check_tensorflow(
+    table: &CompatTable,
+    facts: &GpuFacts,
+    package: &str,
+    version: &str,
+    issues: &mut Vec<Issue>,
+) {
+    let Some(build) = table
+        .tensorflow
+        .iter()
+        .find(|b| in_line(version, &b.tensorflow))
+    else {
+        return;
+    };
+    if let Some(driver_cuda) = &facts.driver_cuda {
+        if !driver_supports(driver_cuda, &build.cuda) {
+            let fallback = table
+                .tensorflow
+                .iter()
+                .filter(|b| driver_supports(driver_cuda, &b.cuda))
+                .max_by(|a, b| version_parts(&a.tensorflow).cmp(&version_parts(&b.tensorflow)));
+            let suggestion = match fallback {
+                Some(b) => format!(
+                    "{}, or pin {}=={}.* (built for CUDA {}).",
+                    driver_upgrade(table, &build.cuda),
+                    package,
+                    b.tensorflow,
+                    b.cuda
+                ),
+                None => format!("{}.", driver_upgrade(table, &build.cuda)),
+            };
+            issues.push(Issue {
+                code: "TENSORFLOW_CUDA_MISMATCH".into(),
+                severity: Severity::Critical,
+                title: "TensorFlow incompatible with driver".into(),
+                description: format!(
+                    "{} {} is built for CUDA {} but {} cannot run it.",
+                    package,
+                    version,
+                    build.cuda,
+                    driver_label(facts, driver_cuda)
+                ),
+                suggestion,
+            });
+            return;
+        }
+    }
+    if let Some(cudnn) = &facts.cudnn {
+        if major(cudnn) != major(&build.cudnn) {
+            issues.push(Issue {
+                code: "TENSORFLOW_CUDNN_MISMATCH".into(),
+                severity: Severity::Warning,
+                title: "TensorFlow incompatible with cuDNN".into(),
+                description: format!(
+                    "{} {} is built against cuDNN {} but cuDNN {} is installed.",
+                    package, version, build.cudnn, cudnn
+                ),
+                suggestion: format!(
+                    "Install {}[and-cuda]=={} to use bundled CUDA libraries, or install cuDNN {}.",
+                    package, version, build.cudnn
+                ),
+            });
+        }
+    }
+}
+
+fn check_jax(
+    table: &CompatTable,
+    facts: &GpuFacts,
+    package: &str,
+    version: &str,
+    issues: &mut Vec<Issue>,
+) {
+    let (Some(needed), Some(driver_cuda)) = (jax_cuda(package, version), &facts.driver_cuda) else {
+        return;
+    };
+    if driver_supports(driver_cuda, &needed) {
+        return;
+    }
+    issues.push(Issue {
+        code: "JAX_CUDA_MISMATCH".into(),
+        severity: Severity::Critical,
+        title: "JAX incompatible with driver".into(),
+        description: format!(
+            "{} {} targets CUDA {} but {} cannot run it.",
+            package,
+            version,
+            needed,
+            driver_label(facts, driver_cuda)
+        ),
+        suggestion: format!("{}.", driver_upgrade(table, &format!("{}.0", needed))),
+    });
+}
+
+/// Checks the NVIDIA driver, CUDA toolkit, cuDNN and installed ML framework
+/// wheels against the bundled table.
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let table = table();
+    let mut issues = Vec::new();
+    let Some(facts) = gpu_facts(state, &table) else {
+        return issues;
+    };
+    check_toolkit(&table, &facts, &mut issues);
+    check_cudnn(&table, &facts, &mut issues);
+
+    let frameworks = state
+        .nodes
+        .iter()
+        .find(|n| n.id == "python")
+        .and_then(|n| n.metadata.get("ml_frameworks"))
+        .and_then(|v| v.as_object());
+    for (package, version) in frameworks.into_iter().flatten() {
+        let Some(version) = version.as_str() else {
+            continue;
+        };
+        match package.as_str() {
+            "torch" => check_torch(&table, &facts, version, &mut issues),
+            "tensorflow" | "tensorflow-gpu" => {
+                check_tensorflow(&table, &facts, package, version, &mut issues)
+            }
+            name if name.starts_with("jax") => {
+                check_jax(&table, &facts, package, version, &mut issues)
+            }
+            _ => {}
+        }
+    }
+    issues
+}
+
+/// Installed framework packages from a parsed `pip freeze`.
+pub fn framework_versions<'a>(
+    installed: impl IntoIterator<Item = (&'a String, &'a String)>,
+) -> serde_json::Map<String, Value> {
+    installed
+        .into_iter()
+        .filter(|(name, _)| FRAMEWORK_PACKAGES.contains(&name.as_str()))
+        .map(|(name, version)| (name.clone(), Value::String(version.clone())))
+        .collect()
+}
@@ -16,8 +16,8 @@ fn print_issue_overview() -> Result<(), String> {
     let fixes = fix::commands();
 
     println!("\n=== Scan Issues (fix integration) ===");
-    for issue in state.issues {
-        let fix_command = fixes.get(issue.code.as_str());
+    for issue in &state.issues {
+        let fix_command = fix::command_for(&fixes, &state, &issue.code);
         let fixable = if fix_command.is_some() {
             "fixable"
         } else {
@@ -42,11 +42,14 @@ fn collect_issues() -> Result<Vec<DoctorIssue>, String> {
     let fixes = fix::commands();
     Ok(state
         .issues
-        .into_iter()
-        .map(|issue| DoctorIssue {
-            code: issue.code.clone(),
-            fixable: fixes.contains_key(issue.code.as_str()),
-            fix_command: fixes.get(issue.code.as_str()).map(|c| c.to_string()),
+        .iter()
+        .map(|issue| {
+            let fix_command = fix::command_for(&fixes, &state, &issue.code);
+            DoctorIssue {
+                code: issue.code.clone(),
+                fixable: fix_command.is_some(),
+                fix_command,
+            }
         })
         .collect())
 }
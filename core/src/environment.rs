@@ -0,0 +1,246 @@
+use crate::models::{Node, Status, SystemState};
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+
+/// What the scan is running on, recorded on the os node as `environment`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvironmentKind {
+    BareMetal,
+    VirtualMachine,
+    Container,
+    Wsl,
+}
+
+impl EnvironmentKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EnvironmentKind::BareMetal => "bare_metal",
+            EnvironmentKind::VirtualMachine => "vm",
+            EnvironmentKind::Container => "container",
+            EnvironmentKind::Wsl => "wsl",
+        }
+    }
+
+    fn parse(raw: &str) -> Option<EnvironmentKind> {
+        match raw {
+            "bare_metal" => Some(EnvironmentKind::BareMetal),
+            "vm" => Some(EnvironmentKind::VirtualMachine),
+            "container" => Some(EnvironmentKind::Container),
+            "wsl" => Some(EnvironmentKind::Wsl),
+            _ => None,
+        }
+    }
+}
+
+/// The environment recorded in a scan, for oracle rules to condition on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Environment {
+    pub kind: EnvironmentKind,
+    pub container_runtime: Option<String>,
+    pub virtualization: Option<String>,
+}
+
+impl Environment {
+    /// Reads the os node; scans without environment metadata count as bare
+    /// metal, which is what every rule assumed before it was recorded.
+    pub fn of(state: &SystemState) -> Environment {
+        let os = state.nodes.iter().find(|n| n.id == "os");
+        let field = |key: &str| {
+            os.and_then(|n| n.metadata.get(key))
+                .and_then(|v| v.as_str())
+                .map(String::from)
+        };
+        Environment {
+            kind: field("environment")
+                .and_then(|k| EnvironmentKind::parse(&k))
+                .unwrap_or(EnvironmentKind::BareMetal),
+            container_runtime: field("container_runtime"),
+            virtualization: field("virtualization"),
+        }
+    }
+
+    pub fn in_container(&self) -> bool {
+        self.kind == EnvironmentKind::Container
+    }
+}
+
+/// `systemd-detect-virt` names that denote containers rather than VMs.
+const CONTAINER_VIRT: &[&str] = &[
+    "docker",
+    "podman",
+    "lxc",
+    "lxc-libvirt",
+    "systemd-nspawn",
+    "rkt",
+    "openvz",
+    "proot",
+    "pouch",
+    "container-other",
+];
+
+/// cgroup path fragments and the runtime they belong to.
+const CGROUP_MARKERS: &[(&str, &str)] = &[
+    ("kubepods", "kubernetes"),
+    ("/docker", "docker"),
+    ("docker-", "docker"),
+    ("libpod", "podman"),
+    ("/lxc", "lxc"),
+    ("containerd", "containerd"),
+];
+
+/// DMI vendor or product strings of common hypervisors.
+const DMI_HYPERVISORS: &[(&str, &str)] = &[
+    ("virtualbox", "oracle"),
+    ("vmware", "vmware"),
+    ("qemu", "qemu"),
+    ("kvm", "kvm"),
+    ("virtual machine", "microsoft"),
+    ("xen", "xen"),
+    ("parallels", "parallels"),
+    ("google compute engine", "kvm"),
+    ("amazon ec2", "amazon"),
+];
+
+/// Environment variables set by CI systems and devcontainer hosts.
+const CONTEXT_VARIABLES: &[(&str, &str)] = &[
+    ("CI", "ci"),
+    ("GITHUB_ACTIONS", "ci"),
+    ("GITLAB_CI", "ci"),
+    ("REMOTE_CONTAINERS", "devcontainer"),
+    ("DEVCONTAINER", "devcontainer"),
+    ("CODESPACES", "codespaces"),
+];
+
+fn container_from_environ(environ: &str) -> Option<String> {
+    environ
+        .split('\0')
+        .find_map(|entry| entry.strip_prefix("container="))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn container_from_cgroup(cgroup: &str) -> Option<&'static str> {
+    CGROUP_MARKERS
+        .iter()
+        .find(|(marker, _)| cgroup.lines().any(|l| l.contains(marker)))
+        .map(|(_, runtime)| *runtime)
+}
+
+/// Records the environment type and the evidence for it on the os node, and
+/// where the Docker daemon lives when the scan runs inside a container.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let mut signals: Vec<String> = Vec::new();
+    let mut runtime: Option<String> = None;
+    let mut virtualization: Option<String> = None;
+
+    if provider.file_exists("/.dockerenv") {
+        signals.push("/.dockerenv present".into());
+        runtime.get_or_insert_with(|| "docker".into());
+    }
+    if provider.file_exists("/run/.containerenv") {
+        signals.push("/run/.containerenv present".into());
+        runtime.get_or_insert_with(|| "podman".into());
+    }
+    if let Some(found) = provider
+        .read_file("/proc/1/environ")
+        .and_then(|e| container_from_environ(&e))
+    {
+        signals.push(format!("/proc/1/environ container={}", found));
+        runtime.get_or_insert(found);
+    }
+    if let Some(found) = provider
+        .read_file("/proc/1/cgroup")
+        .and_then(|c| container_from_cgroup(&c))
+    {
+        signals.push(format!("/proc/1/cgroup mentions {}", found));
+        runtime.get_or_insert_with(|| found.into());
+    }
+    if provider.env_var("KUBERNETES_SERVICE_HOST").is_some() {
+        signals.push("KUBERNETES_SERVICE_HOST set".into());
+        runtime = Some("kubernetes".into());
+    }
+
+    // Exits non-zero (and so yields nothing) when it finds no virtualization.
+    let detected = provider
+        .command_output("systemd-detect-virt", &[])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty() && s != "none");
+    let mut wsl = false;
+    if let Some(virt) = detected {
+        signals.push(format!("systemd-detect-virt: {}", virt));
+        if virt == "wsl" {
+            wsl = true;
+        } else if CONTAINER_VIRT.contains(&virt.as_str()) {
+            runtime.get_or_insert(virt);
+        } else {
+            virtualization = Some(virt);
+        }
+    }
+    if let Some(version) = provider.read_file("/proc/version") {
+        if version.to_lowercase().contains("microsoft") {
+            signals.push("/proc/version mentions Microsoft".into());
+            wsl = true;
+        }
+    }
+    if virtualization.is_none() && runtime.is_none() && !wsl {
+        let dmi = ["sys_vendor", "product_name"]
+            .iter()
+            .filter_map(|f| provider.read_file(&format!("/sys/class/dmi/id/{}", f)))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        if let Some((marker, name)) = DMI_HYPERVISORS.iter().find(|(m, _)| dmi.contains(m)) {
+            signals.push(format!("DMI identifies {}", marker));
+            virtualization = Some(name.to_string());
+        }
+    }
+
+    let kind = if runtime.is_some() {
+        EnvironmentKind::Container
+    } else if wsl {
+        EnvironmentKind::Wsl
+    } else if virtualization.is_some() {
+        EnvironmentKind::VirtualMachine
+    } else {
+        EnvironmentKind::BareMetal
+    };
+    let mut context: Vec<&str> = CONTEXT_VARIABLES
+        .iter()
+        .filter(|(var, _)| provider.env_var(var).is_some())
+        .map(|(_, label)| *label)
+        .collect();
+    context.dedup();
+
+    if kind == EnvironmentKind::Container {
+        if let Some(docker) = nodes
+            .iter_mut()
+            .find(|n| n.id == "docker" && n.status == Status::Active)
+        {
+            // A daemon started inside this container reports the container's
+            // hostname; a socket mounted from the host reports the host's.
+            let daemon = provider.command_output("docker", &["info", "--format", "{{.Name}}"]);
+            let hostname = provider.command_output("hostname", &[]);
+            if let (Some(daemon), Some(hostname)) = (daemon, hostname) {
+                let scope = if daemon.trim() == hostname.trim() {
+                    "container"
+                } else {
+                    "host"
+                };
+                docker.metadata.insert("daemon_scope".into(), json!(scope));
+            }
+        }
+    }
+
+    if let Some(os) = nodes.iter_mut().find(|n| n.id == "os") {
+        os.metadata
+            .insert("environment".into(), json!(kind.as_str()));
+        os.metadata
+            .insert("container_runtime".into(), json!(runtime));
+        os.metadata
+            .insert("virtualization".into(), json!(virtualization));
+        os.metadata
+            .insert("environment_context".into(), json!(context));
+        os.metadata
+            .insert("environment_signals".into(), json!(signals));
+    }
+}
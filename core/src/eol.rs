@@ -0,0 +1,332 @@
+use crate::models::{Issue, Severity, SystemState};
+use crate::oracle_config::EolSettings;
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const BUNDLED: &str = include_str!("../data/eol.json");
+
+/// Refreshed calendar written by `preflight refresh-eol`; takes precedence
+/// over the bundled copy.
+pub const LOCAL_CALENDAR: &str = ".preflight/eol.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EolCycle {
+    pub cycle: String,
+    /// `None` while the release line is still supported with no announced date.
+    pub eol: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EolCalendar {
+    /// Date the calendar was compiled; also the default scan date.
+    pub as_of: String,
+    pub products: BTreeMap<String, Vec<EolCycle>>,
+}
+
+/// A product in the calendar and where its version lives in the scan.
+struct Product {
+    key: &'static str,
+    node_id: &'static str,
+    metadata_key: &'static str,
+    code_prefix: &'static str,
+    label: &'static str,
+}
+
+const PRODUCTS: &[Product] = &[
+    Product {
+        key: "nodejs",
+        node_id: "nodejs",
+        metadata_key: "version",
+        code_prefix: "NODE",
+        label: "Node.js",
+    },
+    Product {
+        key: "python",
+        node_id: "python",
+        metadata_key: "version",
+        code_prefix: "PYTHON",
+        label: "Python",
+    },
+    Product {
+        key: "postgresql",
+        node_id: "postgres",
+        metadata_key: "version",
+        code_prefix: "POSTGRES",
+        label: "PostgreSQL",
+    },
+    Product {
+        key: "mysql",
+        node_id: "mysql",
+        metadata_key: "version",
+        code_prefix: "MYSQL",
+        label: "MySQL",
+    },
+    Product {
+        key: "redis",
+        node_id: "redis",
+        metadata_key: "version",
+        code_prefix: "REDIS",
+        label: "Redis",
+    },
+    Product {
+        key: "go",
+        node_id: "go",
+        metadata_key: "version",
+        code_prefix: "GO",
+        label: "Go",
+    },
+    Product {
+        key: "java",
+        node_id: "java",
+        metadata_key: "version",
+        code_prefix: "JAVA",
+        label: "Java",
+    },
+    Product {
+        key: "cuda",
+        node_id: "gpu",
+        metadata_key: "cuda_version",
+        code_prefix: "CUDA",
+        label: "CUDA",
+    },
+    Product {
+        key: "docker-engine",
+        node_id: "docker",
+        metadata_key: "docker_engine_version",
+        code_prefix: "DOCKER",
+        label: "Docker Engine",
+    },
+];
+
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw.get(..10)?, "%Y-%m-%d").ok()
+}
+
+impl EolCalendar {
+    pub fn bundled() -> EolCalendar {
+        EolCalendar::parse(BUNDLED).expect("bundled calendar invariant: eol.json must parse")
+    }
+
+    /// Parses a calendar and checks every date so a bad refresh is rejected
+    /// before it replaces the working copy.
+    pub fn parse(raw: &str) -> Result<EolCalendar, String> {
+        let calendar: EolCalendar =
+            serde_json::from_str(raw).map_err(|e| format!("Invalid EOL calendar: {e}"))?;
+        parse_date(&calendar.as_of)
+            .ok_or_else(|| format!("Invalid as_of date: {}", calendar.as_of))?;
+        for (product, cycles) in &calendar.products {
+            for cycle in cycles {
+                if let Some(date) = &cycle.eol {
+                    parse_date(date).ok_or_else(|| {
+                        format!("Invalid EOL date for {} {}: {}", product, cycle.cycle, date)
+                    })?;
+                }
+            }
+        }
+        Ok(calendar)
+    }
+
+    pub fn load() -> EolCalendar {
+        fs::read_to_string(LOCAL_CALENDAR)
+            .ok()
+            .and_then(|raw| EolCalendar::parse(&raw).ok())
+            .unwrap_or_else(EolCalendar::bundled)
+    }
+
+    /// Release lines of `product` still supported on the calendar's `as_of` date.
+    pub fn supported_cycles(&self, product: &str) -> Vec<&str> {
+        let Some(as_of) = parse_date(&self.as_of) else {
+            return Vec::new();
+        };
+        self.products
+            .get(product)
+            .map(|cycles| {
+                cycles
+                    .iter()
+                    .filter(|c| {
+                        c.eol
+                            .as_deref()
+                            .and_then(parse_date)
+                            .map(|eol| eol > as_of)
+                            .unwrap_or(true)
+                    })
+                    .map(|c| c.cycle.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Validates a calendar file and installs it as the local calendar.
+pub fn refresh(from: &Path) -> Result<String, String> {
+    let raw =
+        fs::read_to_string(from).map_err(|e| format!("Unable to read {}: {e}", from.display()))?;
+    let calendar = EolCalendar::parse(&raw)?;
+    fs::create_dir_all(".preflight").map_err(|e| e.to_string())?;
+    fs::write(LOCAL_CALENDAR, raw).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "EOL calendar as of {} installed with {} products",
+        calendar.as_of,
+        calendar.products.len()
+    ))
+}
+
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .filter_map(|p| p.parse::<u64>().ok())
+        .collect()
+}
+
+/// Pulls the product version out of raw `--version` output.
+fn extract_version(product: &str, raw: &str) -> Option<String> {
+    if product == "mysql" && raw.contains("MariaDB") {
+        return None;
+    }
+    // Pre-8.0 MySQL clients print "Ver 14.14 Distrib 5.7.44", and nvcc
+    // prints a copyright year before "release 12.2".
+    let raw = match product {
+        "mysql" => raw.split("Distrib").nth(1).unwrap_or(raw),
+        "cuda" => raw.split("release").nth(1).unwrap_or(raw),
+        _ => raw,
+    };
+    let re = Regex::new(r"\d+(\.\d+)*").expect("regex construction invariant: constant pattern");
+    let version = re.find(raw)?.as_str();
+    // Java 8 and earlier report themselves as 1.8.
+    match (product, version.strip_prefix("1.")) {
+        ("java", Some(rest)) if !rest.is_empty() => Some(rest.to_string()),
+        _ => Some(version.to_string()),
+    }
+}
+
+/// The release line `version` belongs to, preferring the most specific cycle.
+fn find_cycle<'a>(cycles: &'a [EolCycle], version: &str) -> Option<&'a EolCycle> {
+    let parts = version_parts(version);
+    cycles
+        .iter()
+        .filter(|c| {
+            let cycle = version_parts(&c.cycle);
+            !cycle.is_empty() && parts.len() >= cycle.len() && parts[..cycle.len()] == cycle[..]
+        })
+        .max_by_key(|c| version_parts(&c.cycle).len())
+}
+
+/// Newest cycle still supported on `date`.
+fn supported_cycle(cycles: &[EolCycle], date: NaiveDate) -> Option<&EolCycle> {
+    cycles
+        .iter()
+        .filter(|c| {
+            c.eol
+                .as_deref()
+                .and_then(parse_date)
+                .map(|eol| eol > date)
+                .unwrap_or(true)
+        })
+        .max_by(|a, b| version_parts(&a.cycle).cmp(&version_parts(&b.cycle)))
+}
+
+fn node_version(state: &SystemState, product: &Product) -> Option<String> {
+    let node = state.nodes.iter().find(|n| n.id == product.node_id)?;
+    let raw = node.metadata.get(product.metadata_key)?.as_str()?;
+    extract_version(product.key, raw)
+}
+
+/// Calendar product keys paired with the version recorded for them in the scan.
+pub fn installed_versions(state: &SystemState) -> Vec<(&'static str, String)> {
+    PRODUCTS
+        .iter()
+        .filter_map(|p| node_version(state, p).map(|v| (p.key, v)))
+        .collect()
+}
+
+/// Warning for scans that fall back to the calendar's `as_of` date when that
+/// date is more than `warn_days` behind `today`: lines that reached end of
+/// life since then are not reported.
+pub fn stale_calendar_warning(
+    calendar: &EolCalendar,
+    settings: &EolSettings,
+    today: NaiveDate,
+) -> Option<String> {
+    if settings.scan_date.is_some() {
+        return None;
+    }
+    let as_of = parse_date(&calendar.as_of)?;
+    let age = (today - as_of).num_days();
+    (age > settings.warn_days).then(|| {
+        format!(
+            "EOL calendar is dated {} ({} days ago) and end-of-life checks use that date; pass --scan-date or run `preflight refresh-eol` to check against today.",
+            calendar.as_of, age
+        )
+    })
+}
+
+pub fn evaluate(state: &SystemState, settings: &EolSettings) -> Vec<Issue> {
+    evaluate_with_calendar(state, settings, &EolCalendar::load())
+}
+
+pub fn evaluate_with_calendar(
+    state: &SystemState,
+    settings: &EolSettings,
+    calendar: &EolCalendar,
+) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let Some(scan_date) = settings
+        .scan_date
+        .as_deref()
+        .and_then(parse_date)
+        .or_else(|| parse_date(&calendar.as_of))
+    else {
+        return issues;
+    };
+
+    for product in PRODUCTS {
+        let Some(cycles) = calendar.products.get(product.key) else {
+            continue;
+        };
+        let Some(version) = node_version(state, product) else {
+            continue;
+        };
+        let Some(cycle) = find_cycle(cycles, &version) else {
+            continue;
+        };
+        let Some(eol) = cycle.eol.as_deref().and_then(parse_date) else {
+            continue;
+        };
+        let upgrade = match supported_cycle(cycles, scan_date) {
+            Some(target) if target.cycle != cycle.cycle => format!(
+                "Upgrade to a supported {} release such as {}.",
+                product.label, target.cycle
+            ),
+            _ => format!("Plan an upgrade to a supported {} release.", product.label),
+        };
+        let days_left = (eol - scan_date).num_days();
+        if days_left <= 0 {
+            issues.push(Issue {
+                code: format!("{}_EOL", product.code_prefix),
+                severity: Severity::Warning,
+                title: format!("{} {} is end of life", product.label, cycle.cycle),
+                description: format!(
+                    "{} {} ({}) reached end of life on {} (scan date {}, EOL calendar as of {}).",
+                    product.label, cycle.cycle, version, eol, scan_date, calendar.as_of
+                ),
+                suggestion: upgrade,
+            });
+        } else if days_left <= settings.warn_days {
+            issues.push(Issue {
+                code: format!("{}_EOL_SOON", product.code_prefix),
+                severity: Severity::Warning,
+                title: format!("{} {} nears end of life", product.label, cycle.cycle),
+                description: format!(
+                    "{} {} ({}) reaches end of life on {}, {} days after the scan date {} (EOL calendar as of {}).",
+                    product.label, cycle.cycle, version, eol, days_left, scan_date, calendar.as_of
+                ),
+                suggestion: upgrade,
+            });
+        }
+    }
+    issues
+}
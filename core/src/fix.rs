@@ -1,4 +1,5 @@
 use crate::models::SystemState;
+use crate::systemd;
 use crate::utils::json_envelope;
 use serde::Serialize;
 use serde_json::json;
@@ -37,6 +38,10 @@ pub fn commands() -> HashMap<&'static str, &'static str> {
         ),
         ("REDIS_PORT_BOUND", "sudo systemctl restart redis-server"),
         ("REDIS_INACTIVE", "sudo systemctl start redis-server"),
+        ("TERRAFORM_PROVIDERS_NOT_LOCKED", "terraform init"),
+        ("TERRAFORM_PROVIDER_UNSATISFIED", "terraform init -upgrade"),
+        ("OPENTOFU_PROVIDERS_NOT_LOCKED", "tofu init"),
+        ("OPENTOFU_PROVIDER_UNSATISFIED", "tofu init -upgrade"),
         (
             "REDIS_MEMORY_LOW",
             "redis-cli CONFIG SET maxmemory 268435456",
@@ -45,12 +50,44 @@ pub fn commands() -> HashMap<&'static str, &'static str> {
             "REDIS_CONFIG_MISSING",
             "sudo cp /etc/redis/redis.conf.default /etc/redis/redis.conf",
         ),
+        (
+            "SEC_DOCKER_SOCKET_WORLD_WRITABLE",
+            "sudo chmod 660 /var/run/docker.sock",
+        ),
+        (
+            "PERM_DOCKER_GROUP_MISSING",
+            "sudo usermod -aG docker $USER",
+        ),
+        (
+            "PERM_VIDEO_GROUP_MISSING",
+            "sudo usermod -aG video $USER",
+        ),
+        (
+            "PERM_RENDER_GROUP_MISSING",
+            "sudo usermod -aG render $USER",
+        ),
+        (
+            "PERM_KVM_GROUP_MISSING",
+            "sudo usermod -aG kvm $USER",
+        ),
+        (
+            "PERM_LIBVIRT_GROUP_MISSING",
+            "sudo usermod -aG libvirt $USER",
+        ),
+        (
+            "PERM_POSTGRES_GROUP_MISSING",
+            "sudo usermod -aG postgres $USER",
+        ),
         ("GPU_MISSING", "sudo apt-get install -y nvidia-driver-535"),
         (
             "CUDA_VERSION_MISMATCH",
             "sudo apt-get install -y cuda-toolkit",
         ),
         ("CUDNN_MISSING", "sudo apt-get install -y libcudnn8"),
+        (
+            "TORCH_NEEDS_CU118",
+            "pip install --force-reinstall torch --index-url https://download.pytorch.org/whl/cu118",
+        ),
         ("PYTHON_MULTIPLE_ENV", "conda deactivate && deactivate"),
         (
             "PYTHON_NO_ENV",
@@ -62,21 +99,63 @@ pub fn commands() -> HashMap<&'static str, &'static str> {
         ),
         ("NODE_PACKAGE_MISSING", "npm init -y"),
         ("NODE_LOCKFILE_DRIFT", "npm install"),
+        ("NODE_ABI_MISMATCH", "npm rebuild"),
+        (
+            "KERNEL_INOTIFY_WATCHES_LOW",
+            "echo fs.inotify.max_user_watches=524288 | sudo tee /etc/sysctl.d/60-inotify.conf && sudo sysctl --system",
+        ),
+        (
+            "KERNEL_MAX_MAP_COUNT_LOW",
+            "echo vm.max_map_count=262144 | sudo tee /etc/sysctl.d/60-max-map-count.conf && sudo sysctl --system",
+        ),
+        (
+            "KERNEL_OVERCOMMIT_DISABLED",
+            "echo vm.overcommit_memory=1 | sudo tee /etc/sysctl.d/60-overcommit.conf && sudo sysctl --system",
+        ),
+        (
+            "KERNEL_SOMAXCONN_LOW",
+            "echo net.core.somaxconn=511 | sudo tee /etc/sysctl.d/60-somaxconn.conf && sudo sysctl --system",
+        ),
+        (
+            "ULIMIT_NOFILE_LOW",
+            "printf '* soft nofile 65535\\n* hard nofile 65535\\n' | sudo tee /etc/security/limits.d/60-nofile.conf",
+        ),
+        ("GIT_BRANCH_BEHIND", "git pull --ff-only"),
+        ("GIT_BRANCH_DIVERGED", "git pull --rebase"),
+        (
+            "GIT_SUBMODULE_UNINITIALIZED",
+            "git submodule update --init --recursive",
+        ),
+        ("GIT_SUBMODULE_OUT_OF_DATE", "git submodule update --recursive"),
+        ("GIT_LFS_NOT_PULLED", "git lfs install && git lfs pull"),
     ])
 }
 
+/// Command for an issue: the fixed table entry, or one built from scan
+/// facts such as the recorded systemd unit.
+pub fn command_for(
+    fixes: &HashMap<&'static str, &'static str>,
+    state: &SystemState,
+    code: &str,
+) -> Option<String> {
+    fixes
+        .get(code)
+        .map(|c| c.to_string())
+        .or_else(|| systemd::fix_command(state, code))
+}
+
 pub fn run(json_output: bool) -> Result<(), String> {
     let state = load_state()?;
     let fixes = commands();
     let mut rendered: Vec<FixCommand> = Vec::new();
     println!("Suggested fixes ({} issues):", state.issues.len());
-    for issue in state.issues {
-        if let Some(cmd) = fixes.get(issue.code.as_str()) {
+    for issue in &state.issues {
+        if let Some(cmd) = command_for(&fixes, &state, &issue.code) {
+            println!("- {}: {}", issue.code, cmd);
             rendered.push(FixCommand {
                 code: issue.code.clone(),
-                command: cmd.to_string(),
+                command: cmd,
             });
-            println!("- {}: {}", issue.code, cmd);
         } else {
             rendered.push(FixCommand {
                 code: issue.code.clone(),
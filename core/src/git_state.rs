@@ -0,0 +1,226 @@
+use crate::models::{Node, NodeType, Status};
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const DEPENDENCY_MANIFESTS: &[&str] = &[
+    "package.json",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "requirements.txt",
+    "Pipfile",
+    "Pipfile.lock",
+    "pyproject.toml",
+    "poetry.lock",
+    "Cargo.toml",
+    "Cargo.lock",
+    "go.mod",
+    "go.sum",
+    "Gemfile",
+    "Gemfile.lock",
+];
+
+fn git<P: SystemProvider>(provider: &P, args: &[&str]) -> Option<String> {
+    provider
+        .command_output("git", args)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn parse_divergence(raw: &str) -> Option<(u64, u64)> {
+    let mut parts = raw.split_whitespace();
+    let ahead = parts.next()?.parse::<u64>().ok()?;
+    let behind = parts.next()?.parse::<u64>().ok()?;
+    Some((ahead, behind))
+}
+
+fn uncommitted_manifests(porcelain: &str) -> Vec<String> {
+    let mut manifests: Vec<String> = porcelain
+        .lines()
+        .filter_map(|line| {
+            // Porcelain lines are "XY path"; the leading status column may
+            // have been trimmed by the provider, so split on the first gap.
+            let (_, path) = line.trim_start().split_once(' ')?;
+            let path = path.trim();
+            let path = path.rsplit(" -> ").next().unwrap_or(path);
+            let file_name = path.rsplit('/').next().unwrap_or(path);
+            DEPENDENCY_MANIFESTS
+                .contains(&file_name)
+                .then(|| path.to_string())
+        })
+        .collect();
+    manifests.sort();
+    manifests.dedup();
+    manifests
+}
+
+/// Splits `git submodule status` output into (uninitialized, out of date) paths.
+fn submodule_state(status: &str) -> (Vec<String>, Vec<String>) {
+    let mut uninitialized = Vec::new();
+    let mut outdated = Vec::new();
+    for line in status.lines() {
+        let Some(marker) = line.chars().next() else {
+            continue;
+        };
+        let path = line[marker.len_utf8()..]
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or("")
+            .to_string();
+        match marker {
+            '-' => uninitialized.push(path),
+            '+' | 'U' => outdated.push(path),
+            _ => {}
+        }
+    }
+    (uninitialized, outdated)
+}
+
+/// `git lfs ls-files` marks files whose content is present with `*` and
+/// unsmudged pointers with `-`.
+fn lfs_pointers(ls_files: &str) -> Vec<String> {
+    ls_files
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let _oid = parts.next()?;
+            let marker = parts.next()?;
+            let path = parts.next()?;
+            (marker == "-").then(|| path.to_string())
+        })
+        .collect()
+}
+
+fn expected_hooks_path<P: SystemProvider>(provider: &P) -> Option<&'static str> {
+    [".husky", ".githooks"]
+        .into_iter()
+        .find(|dir| provider.file_exists(dir))
+}
+
+fn detect_hooks<P: SystemProvider>(
+    provider: &P,
+    git_dir: &str,
+    metadata: &mut BTreeMap<String, Value>,
+) {
+    let hooks_path = git(provider, &["config", "--get", "core.hooksPath"]);
+    let expected = expected_hooks_path(provider);
+    let pre_commit_config = provider.file_exists(".pre-commit-config.yaml");
+
+    let mut missing: Vec<String> = Vec::new();
+    match (&hooks_path, expected) {
+        (Some(path), _) if !provider.file_exists(path) => {
+            missing.push(format!(
+                "core.hooksPath points at missing directory {}",
+                path
+            ));
+        }
+        (None, Some(dir)) => {
+            missing.push(format!("{} exists but core.hooksPath is unset", dir));
+        }
+        (Some(path), Some(dir)) if !path.trim_end_matches('/').starts_with(dir) => {
+            missing.push(format!(
+                "core.hooksPath is {} but the repo ships {}",
+                path, dir
+            ));
+        }
+        _ => {}
+    }
+    if pre_commit_config && hooks_path.is_none() {
+        let hook = format!("{}/hooks/pre-commit", git_dir);
+        if !provider.file_exists(&hook) {
+            missing.push(
+                ".pre-commit-config.yaml exists but the pre-commit hook is not installed".into(),
+            );
+        }
+    }
+
+    metadata.insert("hooks_path".into(), json!(hooks_path));
+    if let Some(dir) = expected {
+        metadata.insert("expected_hooks_path".into(), json!(dir));
+    }
+    metadata.insert("pre_commit_config".into(), json!(pre_commit_config));
+    metadata.insert("hooks_missing".into(), json!(missing));
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let mut metadata = BTreeMap::new();
+    let inside_repo = git(provider, &["rev-parse", "--is-inside-work-tree"])
+        .map(|s| s == "true")
+        .unwrap_or(false);
+    metadata.insert("repository".into(), json!(inside_repo));
+
+    if inside_repo {
+        let git_dir = git(provider, &["rev-parse", "--git-dir"]).unwrap_or_else(|| ".git".into());
+
+        if let Some(branch) = git(provider, &["rev-parse", "--abbrev-ref", "HEAD"]) {
+            metadata.insert("branch".into(), json!(branch));
+        }
+        let upstream = git(
+            provider,
+            &["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"],
+        );
+        if let Some(upstream) = &upstream {
+            metadata.insert("upstream".into(), json!(upstream));
+            if let Some((ahead, behind)) = git(
+                provider,
+                &["rev-list", "--left-right", "--count", "HEAD...@{u}"],
+            )
+            .and_then(|out| parse_divergence(&out))
+            {
+                metadata.insert("ahead".into(), json!(ahead));
+                metadata.insert("behind".into(), json!(behind));
+            }
+        }
+
+        let porcelain = git(provider, &["status", "--porcelain"]).unwrap_or_default();
+        metadata.insert(
+            "uncommitted_manifests".into(),
+            json!(uncommitted_manifests(&porcelain)),
+        );
+
+        let (uninitialized, outdated) = if provider.file_exists(".gitmodules") {
+            submodule_state(&git(provider, &["submodule", "status"]).unwrap_or_default())
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        metadata.insert("submodules_uninitialized".into(), json!(uninitialized));
+        metadata.insert("submodules_outdated".into(), json!(outdated));
+
+        let lfs_tracked = provider
+            .read_file(".gitattributes")
+            .map(|c| c.contains("filter=lfs"))
+            .unwrap_or(false);
+        metadata.insert("lfs_tracked".into(), json!(lfs_tracked));
+        if lfs_tracked {
+            let lfs_installed = git(provider, &["lfs", "version"]).is_some();
+            metadata.insert("lfs_installed".into(), json!(lfs_installed));
+            let pointers = git(provider, &["lfs", "ls-files"])
+                .map(|out| lfs_pointers(&out))
+                .unwrap_or_default();
+            metadata.insert("lfs_pointers".into(), json!(pointers));
+        }
+
+        detect_hooks(provider, &git_dir, &mut metadata);
+
+        metadata.insert(
+            "user_email".into(),
+            json!(git(provider, &["config", "--get", "user.email"])),
+        );
+    }
+
+    let status = if inside_repo {
+        Status::Active
+    } else {
+        Status::Inactive
+    };
+
+    nodes.push(Node {
+        id: "git".into(),
+        node_type: NodeType::File,
+        label: "Git repository".into(),
+        status,
+        metadata,
+    });
+}
@@ -0,0 +1,343 @@
+use crate::cuda_compat;
+use crate::models::{Node, NodeType, Status};
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const NVIDIA_QUERY: &str = "--query-gpu=index,name,memory.total,memory.used,driver_version,compute_cap,utilization.gpu,pci.bus_id";
+
+/// A single GPU as reported by a vendor tool or the PCI bus.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpuDevice {
+    pub vendor: String,
+    pub index: usize,
+    pub model: String,
+    pub memory_total_mb: Option<u64>,
+    pub memory_used_mb: Option<u64>,
+    pub driver_version: Option<String>,
+    pub compute_capability: Option<String>,
+    pub utilization_percent: Option<u64>,
+    pub pci_bus_id: Option<String>,
+    pub driver_loaded: bool,
+}
+
+/// A display-class device from `lspci -mm -nn`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PciDisplayDevice {
+    pub slot: String,
+    pub class_code: String,
+    pub vendor_id: String,
+    pub vendor: String,
+    pub device: String,
+}
+
+fn field_value(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() || trimmed.starts_with('[') {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+/// Parses `nvidia-smi --query-gpu=... --format=csv,noheader,nounits` rows in
+/// the column order of `NVIDIA_QUERY`.
+pub fn parse_nvidia_csv(output: &str) -> Vec<GpuDevice> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split(',').map(|c| c.trim()).collect();
+            if cols.len() < 8 {
+                return None;
+            }
+            Some(GpuDevice {
+                vendor: "nvidia".into(),
+                index: cols[0].parse().ok()?,
+                model: cols[1].to_string(),
+                memory_total_mb: cols[2].parse().ok(),
+                memory_used_mb: cols[3].parse().ok(),
+                driver_version: field_value(cols[4]),
+                compute_capability: field_value(cols[5]),
+                utilization_percent: cols[6].parse().ok(),
+                pci_bus_id: field_value(cols[7]),
+                driver_loaded: true,
+            })
+        })
+        .collect()
+}
+
+fn json_text(card: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|k| card.get(*k).and_then(|v| v.as_str()))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn bytes_text_to_mb(raw: Option<String>) -> Option<u64> {
+    raw.and_then(|b| b.parse::<u64>().ok())
+        .map(|b| b / (1024 * 1024))
+}
+
+/// Parses `rocm-smi --json` output, where each `cardN` key holds one GPU.
+pub fn parse_rocm_json(output: &str) -> Vec<GpuDevice> {
+    let Ok(Value::Object(map)) = serde_json::from_str::<Value>(output) else {
+        return Vec::new();
+    };
+    let system_driver = map
+        .get("system")
+        .and_then(|s| json_text(s, &["Driver version"]));
+    let mut devices: Vec<GpuDevice> = map
+        .iter()
+        .filter_map(|(key, card)| {
+            let index = key.strip_prefix("card")?.parse::<usize>().ok()?;
+            let model = json_text(card, &["Card series", "Card SKU", "Card model"]);
+            let total = json_text(card, &["VRAM Total Memory (B)"]);
+            let used = json_text(card, &["VRAM Total Used Memory (B)"]);
+            let utilization = json_text(card, &["GPU use (%)"]);
+            Some(GpuDevice {
+                vendor: "amd".into(),
+                index,
+                model: model.unwrap_or_else(|| "AMD GPU".into()),
+                memory_total_mb: bytes_text_to_mb(total),
+                memory_used_mb: bytes_text_to_mb(used),
+                driver_version: json_text(card, &["Driver version"])
+                    .or_else(|| system_driver.clone()),
+                compute_capability: json_text(card, &["GFX Version"]),
+                utilization_percent: utilization.and_then(|u| u.parse().ok()),
+                pci_bus_id: json_text(card, &["PCI Bus"]),
+                driver_loaded: true,
+            })
+        })
+        .collect();
+    devices.sort_by_key(|d| d.index);
+    devices
+}
+
+/// Splits an `lspci -mm` line into its quoted fields.
+fn quoted_fields(line: &str) -> Vec<String> {
+    line.split('"')
+        .skip(1)
+        .step_by(2)
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Extracts the trailing `[xxxx]` code that `-nn` appends to a field.
+fn bracket_code(field: &str) -> (String, String) {
+    match field.rfind(" [") {
+        Some(pos) if field.ends_with(']') => (
+            field[..pos].trim().to_string(),
+            field[pos + 2..field.len() - 1].to_lowercase(),
+        ),
+        _ => (field.trim().to_string(), String::new()),
+    }
+}
+
+/// Returns display controllers (PCI class 03xx) from `lspci -mm -nn` output.
+pub fn parse_lspci_display(output: &str) -> Vec<PciDisplayDevice> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let slot = line.split_whitespace().next()?.to_string();
+            let fields = quoted_fields(line);
+            let (_, class_code) = bracket_code(fields.first()?);
+            if !class_code.starts_with("03") {
+                return None;
+            }
+            let (vendor, vendor_id) = bracket_code(fields.get(1)?);
+            let (device, _) = bracket_code(fields.get(2)?);
+            Some(PciDisplayDevice {
+                slot,
+                class_code,
+                vendor_id,
+                vendor,
+                device,
+            })
+        })
+        .collect()
+}
+
+fn pci_vendor(vendor_id: &str) -> Option<&'static str> {
+    match vendor_id {
+        "10de" => Some("nvidia"),
+        "1002" => Some("amd"),
+        "8086" => Some("intel"),
+        _ => None,
+    }
+}
+
+fn detect_cudnn<P: SystemProvider>(provider: &P) -> Option<String> {
+    [
+        "/usr/include/cudnn_version.h",
+        "/usr/local/cuda/include/cudnn_version.h",
+    ]
+    .iter()
+    .find_map(|p| {
+        provider.read_file(p).and_then(|contents| {
+            contents
+                .lines()
+                .find(|l| l.contains("CUDNN_MAJOR"))
+                .and_then(|major| {
+                    let major_num = major.split_whitespace().last()?.to_string();
+                    let minor = contents
+                        .lines()
+                        .find(|l| l.contains("CUDNN_MINOR"))
+                        .and_then(|l| l.split_whitespace().last())
+                        .unwrap_or("0");
+                    let patch = contents
+                        .lines()
+                        .find(|l| l.contains("CUDNN_PATCHLEVEL"))
+                        .and_then(|l| l.split_whitespace().last())
+                        .unwrap_or("0");
+                    Some(format!("{}.{}.{}", major_num, minor, patch))
+                })
+        })
+    })
+}
+
+/// The highest CUDA version the loaded driver supports, from the
+/// `nvidia-smi` banner.
+fn driver_cuda_version<P: SystemProvider>(provider: &P) -> Option<String> {
+    provider.command_output("nvidia-smi", &[]).and_then(|out| {
+        out.lines().find_map(|l| {
+            l.split("CUDA Version:")
+                .nth(1)
+                .and_then(|s| s.split_whitespace().next())
+                .map(|s| s.to_string())
+        })
+    })
+}
+
+/// Live load (memory in use, utilization) stays out of the node so a busy
+/// GPU does not change the scan fingerprint.
+fn device_node(device: &GpuDevice) -> Node {
+    let mut metadata = BTreeMap::new();
+    metadata.insert("vendor".into(), json!(device.vendor));
+    metadata.insert("index".into(), json!(device.index));
+    metadata.insert("model".into(), json!(device.model));
+    metadata.insert("driver_loaded".into(), json!(device.driver_loaded));
+    if let Some(v) = device.memory_total_mb {
+        metadata.insert("memory_total_mb".into(), json!(v));
+    }
+    if let Some(v) = &device.driver_version {
+        metadata.insert("driver_version".into(), json!(v));
+    }
+    if let Some(v) = &device.compute_capability {
+        metadata.insert("compute_capability".into(), json!(v));
+    }
+    if let Some(v) = &device.pci_bus_id {
+        metadata.insert("pci_bus_id".into(), json!(v));
+    }
+    Node {
+        id: format!("gpu_{}{}", device.vendor, device.index),
+        node_type: NodeType::Gpu,
+        label: device.model.clone(),
+        status: if device.driver_loaded {
+            Status::Active
+        } else {
+            Status::Inactive
+        },
+        metadata,
+    }
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let mut devices = provider
+        .command_output(
+            "nvidia-smi",
+            &[NVIDIA_QUERY, "--format=csv,noheader,nounits"],
+        )
+        .map(|out| parse_nvidia_csv(&out))
+        .unwrap_or_default();
+    devices.extend(
+        provider
+            .command_output(
+                "rocm-smi",
+                &[
+                    "--showproductname",
+                    "--showmeminfo",
+                    "vram",
+                    "--showuse",
+                    "--showdriverversion",
+                    "--json",
+                ],
+            )
+            .map(|out| parse_rocm_json(&out))
+            .unwrap_or_default(),
+    );
+
+    // PCI devices fill in vendors no management tool reported on. NVIDIA and
+    // AMD cards that only show up here have no working driver.
+    let pci = provider
+        .command_output("lspci", &["-mm", "-nn"])
+        .map(|out| parse_lspci_display(&out))
+        .unwrap_or_default();
+    for vendor in ["nvidia", "amd", "intel"] {
+        if devices.iter().any(|d| d.vendor == vendor) {
+            continue;
+        }
+        let found = pci
+            .iter()
+            .filter(|p| pci_vendor(&p.vendor_id) == Some(vendor));
+        for (index, p) in found.enumerate() {
+            devices.push(GpuDevice {
+                vendor: vendor.into(),
+                index,
+                model: p.device.clone(),
+                pci_bus_id: Some(p.slot.clone()),
+                driver_loaded: vendor == "intel",
+                ..GpuDevice::default()
+            });
+        }
+    }
+
+    let cuda_version = provider.command_output("nvcc", &["--version"]);
+    let cudnn_version = detect_cudnn(provider);
+    let driver_cuda = driver_cuda_version(provider);
+
+    let vendor_present = |vendor: &str| devices.iter().any(|d| d.vendor == vendor);
+    let mut metadata = BTreeMap::new();
+    metadata.insert("device_count".into(), json!(devices.len()));
+    metadata.insert(
+        "devices".into(),
+        json!(devices
+            .iter()
+            .map(|d| format!("gpu_{}{}", d.vendor, d.index))
+            .collect::<Vec<_>>()),
+    );
+    metadata.insert(
+        "nvidia_gpu_detected".into(),
+        json!(vendor_present("nvidia")),
+    );
+    metadata.insert("amd_gpu_detected".into(), json!(vendor_present("amd")));
+    metadata.insert("intel_gpu_detected".into(), json!(vendor_present("intel")));
+    if let Some(v) = &driver_cuda {
+        metadata.insert("driver_cuda_version".into(), json!(v));
+    }
+    if let Some(cuda) = &cuda_version {
+        metadata.insert("cuda_version".into(), json!(cuda));
+    }
+    if let Some(cudnn) = &cudnn_version {
+        metadata.insert("cudnn_version".into(), json!(cudnn));
+    }
+    metadata.insert(
+        "compat_table_version".into(),
+        json!(cuda_compat::table().version),
+    );
+
+    let status = if devices.is_empty() {
+        Status::Inactive
+    } else {
+        Status::Active
+    };
+
+    nodes.extend(devices.iter().map(device_node));
+    nodes.push(Node {
+        id: "gpu".into(),
+        node_type: NodeType::Gpu,
+        label: "GPU".into(),
+        status,
+        metadata,
+    });
+}
@@ -1,3 +1,4 @@
+use crate::connections::Connection;
 use crate::models::{Edge, Node, Relation, Status, SystemState};
 use std::collections::HashMap;
 
@@ -102,6 +103,23 @@ pub fn derive_edges(state: &mut SystemState) {
         });
     }
 
+    // -------------------------------
+    // GPU devices → GPU
+    // -------------------------------
+    let devices: Vec<String> = state
+        .nodes
+        .iter()
+        .filter(|n| n.id.starts_with("gpu_"))
+        .map(|n| n.id.clone())
+        .collect();
+    for device in devices {
+        state.edges.push(Edge {
+            from: device,
+            to: "gpu".into(),
+            relation: Relation::REQUIRES,
+        });
+    }
+
     // -------------------------------
     // Docker Images → Docker
     // -------------------------------
@@ -112,6 +130,20 @@ pub fn derive_edges(state: &mut SystemState) {
             relation: Relation::REQUIRES,
         });
     }
+    // Dockerfiles and the dev container are built by the daemon.
+    let images: Vec<String> = state
+        .nodes
+        .iter()
+        .filter(|n| n.id.starts_with("dockerfile:") || n.id == "devcontainer")
+        .map(|n| n.id.clone())
+        .collect();
+    for image in images {
+        state.edges.push(Edge {
+            from: image,
+            to: "docker".into(),
+            relation: Relation::REQUIRES,
+        });
+    }
 
     // -------------------------------
     // Python → OS
@@ -124,6 +156,92 @@ pub fn derive_edges(state: &mut SystemState) {
         });
     }
 
+    // -------------------------------
+    // Web servers and applications → listen and upstream ports
+    // -------------------------------
+    let mut port_edges = Vec::new();
+    for node in &state.nodes {
+        for (key, relation) in [
+            ("listen_ports", Relation::BINDS),
+            ("upstream_ports", Relation::REQUIRES),
+        ] {
+            let ports = node
+                .metadata
+                .get(key)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|p| p.as_u64());
+            for port in ports {
+                let to = format!("port{}", port);
+                if state.nodes.iter().any(|n| n.id == to) {
+                    port_edges.push(Edge {
+                        from: node.id.clone(),
+                        to,
+                        relation: relation.clone(),
+                    });
+                }
+            }
+        }
+    }
+    state.edges.extend(port_edges);
+
+    // -------------------------------
+    // Applications → runtimes and services they need
+    // -------------------------------
+    let mut required = Vec::new();
+    for node in &state.nodes {
+        let targets = node
+            .metadata
+            .get("requires")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t.as_str());
+        for target in targets {
+            if state.nodes.iter().any(|n| n.id == target) {
+                required.push(Edge {
+                    from: node.id.clone(),
+                    to: target.to_string(),
+                    relation: Relation::REQUIRES,
+                });
+            }
+        }
+    }
+    state.edges.extend(required);
+
+    // -------------------------------
+    // Connection strings → services they resolve to
+    // -------------------------------
+    let connections: Vec<Connection> = state
+        .nodes
+        .iter()
+        .find(|n| n.id == "os")
+        .and_then(|os| os.metadata.get("connections"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    for connection in connections {
+        let Some(target) = connection.node else {
+            continue;
+        };
+        if !state.nodes.iter().any(|n| n.id == target) {
+            continue;
+        }
+        for consumer in connection.consumers {
+            let exists = state
+                .edges
+                .iter()
+                .any(|e| e.from == consumer && e.to == target && e.relation == Relation::REQUIRES);
+            if !exists {
+                state.edges.push(Edge {
+                    from: consumer,
+                    to: target.clone(),
+                    relation: Relation::REQUIRES,
+                });
+            }
+        }
+    }
+
     //-------------------------------------------
     // Node-level relationship: Python ↔ Docker Images
     //-------------------------------------------
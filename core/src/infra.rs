@@ -0,0 +1,550 @@
+use crate::models::{Issue, Node, NodeType, Severity, Status, SystemState};
+use crate::system_provider::SystemProvider;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+struct InfraTool {
+    id: &'static str,
+    label: &'static str,
+    command: &'static str,
+    args: &'static [&'static str],
+}
+
+const TOOLS: &[InfraTool] = &[
+    InfraTool {
+        id: "terraform",
+        label: "Terraform",
+        command: "terraform",
+        args: &["version"],
+    },
+    InfraTool {
+        id: "opentofu",
+        label: "OpenTofu",
+        command: "tofu",
+        args: &["version"],
+    },
+    InfraTool {
+        id: "kubectl",
+        label: "kubectl",
+        command: "kubectl",
+        args: &["version", "--client", "-o", "json"],
+    },
+    InfraTool {
+        id: "helm",
+        label: "Helm",
+        command: "helm",
+        args: &["version", "--short"],
+    },
+    InfraTool {
+        id: "kind",
+        label: "kind",
+        command: "kind",
+        args: &["version"],
+    },
+    InfraTool {
+        id: "minikube",
+        label: "minikube",
+        command: "minikube",
+        args: &["version", "--short"],
+    },
+    InfraTool {
+        id: "aws",
+        label: "AWS CLI",
+        command: "aws",
+        args: &["--version"],
+    },
+    InfraTool {
+        id: "gcloud",
+        label: "Google Cloud CLI",
+        command: "gcloud",
+        args: &["--version"],
+    },
+    InfraTool {
+        id: "az",
+        label: "Azure CLI",
+        command: "az",
+        args: &["version", "-o", "json"],
+    },
+];
+
+const LOCK_FILE: &str = ".terraform.lock.hcl";
+
+/// Context names that usually point at production clusters.
+const PRODUCTION_CONTEXT: &str = r"(?i)(^|[-_./:@])(prod|production|prd|live)([-_./:@]|$)";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProviderRequirement {
+    /// Registry address without the host, e.g. `hashicorp/aws`.
+    pub source: String,
+    pub constraint: Option<String>,
+    /// `file:line` of the constraint.
+    pub origin: String,
+    pub locked_version: Option<String>,
+}
+
+fn extract_version(output: &str) -> Option<String> {
+    Regex::new(r"v?(\d+\.\d+(?:\.\d+)?)")
+        .expect("regex construction invariant: constant pattern")
+        .captures(output)
+        .map(|c| c[1].to_string())
+}
+
+fn numeric(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+fn compare(a: &[u64], b: &[u64]) -> Ordering {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Checks a version against a Terraform constraint such as
+/// `>= 1.5, < 2.0` or `~> 5.0`; `None` when either cannot be parsed.
+pub fn constraint_satisfied(constraint: &str, version: &str) -> Option<bool> {
+    let have = numeric(version);
+    if have.is_empty() {
+        return None;
+    }
+    let mut satisfied = true;
+    for part in constraint
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        let (op, rest) = ["~>", ">=", "<=", "!=", ">", "<", "="]
+            .iter()
+            .find_map(|op| part.strip_prefix(op).map(|rest| (*op, rest)))
+            .unwrap_or(("=", part));
+        let want = numeric(rest);
+        if want.is_empty() {
+            return None;
+        }
+        let order = compare(&have, &want);
+        satisfied &= match op {
+            // `~> 1.5.7` allows 1.5.x from 1.5.7; `~> 5.0` allows 5.x.
+            "~>" => {
+                let mut upper = want.clone();
+                if upper.len() > 1 {
+                    upper.pop();
+                }
+                *upper.last_mut().expect("non-empty version") += 1;
+                order.is_ge() && compare(&have, &upper).is_lt()
+            }
+            ">=" => order.is_ge(),
+            "<=" => order.is_le(),
+            "!=" => order.is_ne(),
+            ">" => order.is_gt(),
+            "<" => order.is_lt(),
+            _ => order.is_eq(),
+        };
+    }
+    Some(satisfied)
+}
+
+/// `hashicorp/aws` from `registry.terraform.io/hashicorp/aws` or `aws`.
+fn provider_address(source: &str, name: &str) -> String {
+    let parts: Vec<&str> = source.split('/').filter(|p| !p.is_empty()).collect();
+    match parts.as_slice() {
+        [.., namespace, kind] => format!("{}/{}", namespace, kind).to_lowercase(),
+        _ => format!("hashicorp/{}", name).to_lowercase(),
+    }
+}
+
+fn line_of(contents: &str, offset: usize) -> usize {
+    contents[..offset].matches('\n').count() + 1
+}
+
+/// The body of the first `keyword {` block in `contents`, with the offset
+/// it starts at.
+fn block_body<'a>(contents: &'a str, keyword: &str) -> Option<(usize, &'a str)> {
+    let start = contents.find(keyword)?;
+    let open = start + contents[start..].find('{')? + 1;
+    let mut depth = 1;
+    for (i, c) in contents[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((open, &contents[open..open + i]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `required_version` and `required_providers` from the root module's
+/// `*.tf` files.
+fn terraform_requirements(
+    files: &[(String, String)],
+) -> (Option<(String, String)>, Vec<ProviderRequirement>) {
+    let required_version = Regex::new(r#"required_version\s*=\s*"([^"]*)""#)
+        .expect("regex construction invariant: constant pattern");
+    let object_entry = Regex::new(r#"(?s)([A-Za-z0-9_-]+)\s*=\s*\{(.*?)\}"#)
+        .expect("regex construction invariant: constant pattern");
+    let legacy_entry = Regex::new(r#"(?m)^\s*([A-Za-z0-9_-]+)\s*=\s*"([^"]*)""#)
+        .expect("regex construction invariant: constant pattern");
+    let attribute = Regex::new(r#"(source|version)\s*=\s*"([^"]*)""#)
+        .expect("regex construction invariant: constant pattern");
+
+    let mut version = None;
+    let mut providers = Vec::new();
+    for (path, contents) in files {
+        if let Some(c) = required_version.captures(contents) {
+            let at = c.get(0).map(|m| m.start()).unwrap_or(0);
+            version.get_or_insert((
+                c[1].to_string(),
+                format!("{}:{}", path, line_of(contents, at)),
+            ));
+        }
+        let Some((offset, body)) = block_body(contents, "required_providers") else {
+            continue;
+        };
+        for c in object_entry.captures_iter(body) {
+            let mut source = None;
+            let mut constraint = None;
+            for a in attribute.captures_iter(&c[2]) {
+                match &a[1] {
+                    "source" => source = Some(a[2].to_string()),
+                    _ => constraint = Some(a[2].to_string()),
+                }
+            }
+            let at = offset + c.get(0).map(|m| m.start()).unwrap_or(0);
+            providers.push(ProviderRequirement {
+                source: provider_address(source.as_deref().unwrap_or(""), &c[1]),
+                constraint,
+                origin: format!("{}:{}", path, line_of(contents, at)),
+                locked_version: None,
+            });
+        }
+        // Terraform 0.12 style `aws = "~> 2.0"`.
+        let remainder = object_entry.replace_all(body, "");
+        for c in legacy_entry.captures_iter(&remainder) {
+            providers.push(ProviderRequirement {
+                source: provider_address("", &c[1]),
+                constraint: Some(c[2].to_string()),
+                origin: path.clone(),
+                locked_version: None,
+            });
+        }
+    }
+    (version, providers)
+}
+
+/// Locked provider versions from `.terraform.lock.hcl`.
+pub fn locked_providers(contents: &str) -> BTreeMap<String, String> {
+    let block = Regex::new(r#"(?ms)^provider\s+"([^"]+)"\s*\{(.*?)^\}"#)
+        .expect("regex construction invariant: constant pattern");
+    let version = Regex::new(r#"(?m)^\s*version\s*=\s*"([^"]*)""#)
+        .expect("regex construction invariant: constant pattern");
+    block
+        .captures_iter(contents)
+        .filter_map(|c| {
+            let locked = version.captures(&c[2])?[1].to_string();
+            Some((provider_address(&c[1], ""), locked))
+        })
+        .collect()
+}
+
+fn detect_terraform<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let mut names: Vec<String> = provider
+        .list_dir(".")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| f.ends_with(".tf"))
+        .collect();
+    names.sort();
+    let files: Vec<(String, String)> = names
+        .into_iter()
+        .filter_map(|name| provider.read_file(&name).map(|c| (name, c)))
+        .collect();
+    if files.is_empty() {
+        return;
+    }
+    let (required_version, mut providers) = terraform_requirements(&files);
+    let lock = provider.read_file(LOCK_FILE).map(|c| locked_providers(&c));
+    for requirement in &mut providers {
+        requirement.locked_version = lock
+            .as_ref()
+            .and_then(|l| l.get(&requirement.source))
+            .cloned();
+    }
+    let project = json!({
+        "required_version": required_version.as_ref().map(|(c, _)| c),
+        "required_version_origin": required_version.as_ref().map(|(_, o)| o),
+        "providers": providers,
+        "lock_file": lock.is_some(),
+    });
+
+    let installed = ["terraform", "opentofu"]
+        .iter()
+        .find(|id| nodes.iter().any(|n| n.id == **id));
+    match installed {
+        Some(id) => {
+            if let Some(node) = nodes.iter_mut().find(|n| n.id == *id) {
+                node.metadata.insert("project".into(), project);
+            }
+        }
+        None => {
+            let mut metadata = BTreeMap::new();
+            metadata.insert("project".into(), project);
+            nodes.push(Node {
+                id: "terraform".into(),
+                node_type: NodeType::Runtime,
+                label: "Terraform".into(),
+                status: Status::Inactive,
+                metadata,
+            });
+        }
+    }
+}
+
+/// Current kubeconfig context and, when the cluster answers, its version.
+fn detect_kube_context<P: SystemProvider>(provider: &P, node: &mut Node) {
+    let Some(context) = provider
+        .command_output("kubectl", &["config", "current-context"])
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+    else {
+        return;
+    };
+    let server = provider
+        .command_output(
+            "kubectl",
+            &["version", "-o", "json", "--request-timeout=5s"],
+        )
+        .and_then(|out| serde_json::from_str::<Value>(&out).ok())
+        .and_then(|v| {
+            v.get("serverVersion")?
+                .get("gitVersion")?
+                .as_str()
+                .map(|s| s.trim_start_matches('v').to_string())
+        });
+    node.metadata.insert("context".into(), json!(context));
+    node.metadata
+        .insert("server_reachable".into(), json!(server.is_some()));
+    node.metadata.insert("server_version".into(), json!(server));
+}
+
+/// Adds runtime nodes for installed infrastructure CLIs, checks Terraform
+/// or OpenTofu against the project's constraints, and records kubectl's
+/// current context and cluster version.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    for tool in TOOLS {
+        let Some(output) = provider.command_output(tool.command, tool.args) else {
+            continue;
+        };
+        let version = if tool.id == "kubectl" {
+            serde_json::from_str::<Value>(&output)
+                .ok()
+                .and_then(|v| {
+                    v.get("clientVersion")?
+                        .get("gitVersion")?
+                        .as_str()
+                        .map(|s| s.trim_start_matches('v').to_string())
+                })
+                .or_else(|| extract_version(&output))
+        } else {
+            extract_version(&output)
+        };
+        let mut node = Node {
+            id: tool.id.into(),
+            node_type: NodeType::Runtime,
+            label: tool.label.into(),
+            status: Status::Active,
+            metadata: BTreeMap::new(),
+        };
+        node.metadata.insert("version".into(), json!(version));
+        if tool.id == "kubectl" {
+            detect_kube_context(provider, &mut node);
+        }
+        nodes.push(node);
+    }
+    detect_terraform(provider, nodes);
+}
+
+fn minor_of(version: &str) -> Option<(u64, u64)> {
+    match numeric(version).as_slice() {
+        [major, minor, ..] => Some((*major, *minor)),
+        _ => None,
+    }
+}
+
+fn evaluate_terraform(node: &Node, issues: &mut Vec<Issue>) {
+    let Some(project) = node.metadata.get("project") else {
+        return;
+    };
+    let prefix = node.id.to_uppercase();
+    let command = if node.id == "opentofu" {
+        "tofu"
+    } else {
+        "terraform"
+    };
+    let str_field = |key: &str| project.get(key).and_then(|v| v.as_str());
+    let version = node.metadata.get("version").and_then(|v| v.as_str());
+
+    if node.status != Status::Active {
+        issues.push(Issue {
+            code: format!("{}_MISSING", prefix),
+            severity: Severity::Critical,
+            title: "Terraform is not installed".into(),
+            description: format!(
+                "This directory has Terraform configuration{} but neither terraform nor tofu is on PATH.",
+                str_field("required_version")
+                    .map(|c| format!(" requiring {}", c))
+                    .unwrap_or_default()
+            ),
+            suggestion: "Install Terraform or OpenTofu (for example with tfenv, mise, or your package manager).".into(),
+        });
+    }
+    if let (Some(constraint), Some(version)) = (str_field("required_version"), version) {
+        if constraint_satisfied(constraint, version) == Some(false) {
+            issues.push(Issue {
+                code: format!("{}_VERSION_UNSATISFIED", prefix),
+                severity: Severity::Critical,
+                title: format!("{} {} does not satisfy {}", node.label, version, constraint),
+                description: format!(
+                    "{} requires {} {} but {} is installed; `{} init` will refuse to run.",
+                    str_field("required_version_origin").unwrap_or("required_version"),
+                    node.label,
+                    constraint,
+                    version,
+                    command
+                ),
+                suggestion: format!(
+                    "Install a {} release matching {} (for example with tfenv or mise).",
+                    node.label, constraint
+                ),
+            });
+        }
+    }
+
+    let providers: Vec<ProviderRequirement> = project
+        .get("providers")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let mut unlocked = Vec::new();
+    let mut unsatisfied = Vec::new();
+    for p in &providers {
+        match (&p.locked_version, &p.constraint) {
+            (None, _) => unlocked.push(format!("{} ({})", p.source, p.origin)),
+            (Some(locked), Some(constraint))
+                if constraint_satisfied(constraint, locked) == Some(false) =>
+            {
+                unsatisfied.push(format!(
+                    "{} is locked at {} but {} requires {}",
+                    p.source, locked, p.origin, constraint
+                ))
+            }
+            _ => {}
+        }
+    }
+    if !unlocked.is_empty() {
+        issues.push(Issue {
+            code: format!("{}_PROVIDERS_NOT_LOCKED", prefix),
+            severity: Severity::Warning,
+            title: format!("{} providers missing from {}", node.label, LOCK_FILE),
+            description: format!(
+                "{} has no entry for {}, so provider versions are not pinned.",
+                LOCK_FILE,
+                unlocked.join(", ")
+            ),
+            suggestion: format!("Run `{} init` and commit {}.", command, LOCK_FILE),
+        });
+    }
+    if !unsatisfied.is_empty() {
+        issues.push(Issue {
+            code: format!("{}_PROVIDER_UNSATISFIED", prefix),
+            severity: Severity::Warning,
+            title: format!("Locked {} providers violate their constraints", node.label),
+            description: format!("{}.", unsatisfied.join("; ")),
+            suggestion: format!("Run `{} init -upgrade` and commit {}.", command, LOCK_FILE),
+        });
+    }
+}
+
+fn evaluate_kubectl(node: &Node, issues: &mut Vec<Issue>) {
+    let field = |key: &str| node.metadata.get(key).and_then(|v| v.as_str());
+    let Some(context) = field("context") else {
+        return;
+    };
+    let production =
+        Regex::new(PRODUCTION_CONTEXT).expect("regex construction invariant: constant pattern");
+    if production.is_match(context) {
+        issues.push(Issue {
+            code: "KUBE_CONTEXT_PRODUCTION".into(),
+            severity: Severity::Warning,
+            title: format!("kubectl context \"{}\" looks like production", context),
+            description: format!(
+                "The current kubeconfig context is \"{}\", so kubectl and helm commands run during these checks or fixes would act on what looks like a production cluster.",
+                context
+            ),
+            suggestion: "Switch to a development context with `kubectl config use-context <name>` before continuing.".into(),
+        });
+    }
+    if node
+        .metadata
+        .get("server_reachable")
+        .and_then(|v| v.as_bool())
+        == Some(false)
+    {
+        issues.push(Issue {
+            code: "KUBE_CONTEXT_UNREACHABLE".into(),
+            severity: Severity::Warning,
+            title: format!("Cluster for context \"{}\" is unreachable", context),
+            description: format!(
+                "kubectl could not reach the API server of context \"{}\" within 5 seconds.",
+                context
+            ),
+            suggestion: "Start the local cluster (for example `kind create cluster` or `minikube start`), or check VPN and credentials.".into(),
+        });
+    }
+    let (Some(client), Some(server)) = (field("version"), field("server_version")) else {
+        return;
+    };
+    if let (Some((cmaj, cmin)), Some((smaj, smin))) = (minor_of(client), minor_of(server)) {
+        // kubectl supports one minor version of skew in either direction.
+        if cmaj != smaj || cmin.abs_diff(smin) > 1 {
+            issues.push(Issue {
+                code: "KUBECTL_VERSION_SKEW".into(),
+                severity: Severity::Warning,
+                title: format!("kubectl {} is too far from the cluster's {}", client, server),
+                description: format!(
+                    "kubectl {} talks to Kubernetes {} through context \"{}\"; only one minor version of skew is supported.",
+                    client, server, context
+                ),
+                suggestion: format!(
+                    "Install kubectl {}.{} to match the cluster.",
+                    smaj, smin
+                ),
+            });
+        }
+    }
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for node in &state.nodes {
+        match node.id.as_str() {
+            "terraform" | "opentofu" => evaluate_terraform(node, &mut issues),
+            "kubectl" => evaluate_kubectl(node, &mut issues),
+            _ => {}
+        }
+    }
+    issues
+}
@@ -0,0 +1,193 @@
+use crate::models::{Node, NodeType, Status};
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const NOFILE_SETTING: &str = "nofile";
+
+/// A kernel or ulimit setting that a detected workload needs raised.
+pub struct TuningRule {
+    pub consumer: &'static str,
+    pub setting: &'static str,
+    pub code: &'static str,
+    pub minimum: u64,
+    /// When set the value must equal `minimum` rather than exceed it.
+    pub exact: bool,
+}
+
+pub const RULES: &[TuningRule] = &[
+    TuningRule {
+        consumer: "nodejs",
+        setting: "fs.inotify.max_user_watches",
+        code: "KERNEL_INOTIFY_WATCHES_LOW",
+        minimum: 524288,
+        exact: false,
+    },
+    TuningRule {
+        consumer: "nodejs",
+        setting: NOFILE_SETTING,
+        code: "ULIMIT_NOFILE_LOW",
+        minimum: 8192,
+        exact: false,
+    },
+    TuningRule {
+        consumer: "elasticsearch",
+        setting: "vm.max_map_count",
+        code: "KERNEL_MAX_MAP_COUNT_LOW",
+        minimum: 262144,
+        exact: false,
+    },
+    TuningRule {
+        consumer: "elasticsearch",
+        setting: NOFILE_SETTING,
+        code: "ULIMIT_NOFILE_LOW",
+        minimum: 65535,
+        exact: false,
+    },
+    TuningRule {
+        consumer: "redis",
+        setting: "vm.overcommit_memory",
+        code: "KERNEL_OVERCOMMIT_DISABLED",
+        minimum: 1,
+        exact: true,
+    },
+    TuningRule {
+        consumer: "redis",
+        setting: "net.core.somaxconn",
+        code: "KERNEL_SOMAXCONN_LOW",
+        minimum: 511,
+        exact: false,
+    },
+];
+
+fn sysctl_path(setting: &str) -> String {
+    format!("/proc/sys/{}", setting.replace('.', "/"))
+}
+
+fn read_sysctl<P: SystemProvider>(provider: &P, setting: &str) -> Option<u64> {
+    provider
+        .read_file(&sysctl_path(setting))
+        .and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+/// Returns the (soft, hard) open file limits from a `/proc/<pid>/limits` table.
+/// `unlimited` is reported as `u64::MAX`.
+pub fn parse_nofile_limits(contents: &str) -> Option<(u64, u64)> {
+    let line = contents.lines().find(|l| l.starts_with("Max open files"))?;
+    let mut values = line
+        .trim_start_matches("Max open files")
+        .split_whitespace()
+        .map(|v| {
+            if v == "unlimited" {
+                Some(u64::MAX)
+            } else {
+                v.parse::<u64>().ok()
+            }
+        });
+    Some((values.next()??, values.next()??))
+}
+
+fn detect_consumers<P: SystemProvider>(provider: &P, nodes: &[Node]) -> Vec<&'static str> {
+    let mut consumers = Vec::new();
+    let node_active = |id: &str| {
+        nodes
+            .iter()
+            .any(|n| n.id == id && n.status == Status::Active)
+    };
+
+    let node_project = nodes.iter().any(|n| {
+        n.id == "nodejs"
+            && n.status == Status::Active
+            && n.metadata
+                .get("package_json_present")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+    });
+    if node_project {
+        consumers.push("nodejs");
+    }
+
+    let compose_mentions_elasticsearch = nodes
+        .iter()
+        .find(|n| n.id == "docker")
+        .and_then(|n| n.metadata.get("compose_file"))
+        .and_then(|v| v.as_str())
+        .and_then(|path| provider.read_file(path))
+        .map(|contents| contents.to_lowercase().contains("elasticsearch"))
+        .unwrap_or(false);
+    if compose_mentions_elasticsearch || provider.file_exists("/usr/share/elasticsearch") {
+        consumers.push("elasticsearch");
+    }
+
+    if node_active("redis") {
+        consumers.push("redis");
+    }
+
+    consumers
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let consumers = detect_consumers(provider, nodes);
+    let nofile = provider
+        .read_file("/proc/self/limits")
+        .and_then(|c| parse_nofile_limits(&c));
+
+    let mut settings = BTreeMap::new();
+    for rule in RULES {
+        if rule.setting == NOFILE_SETTING || settings.contains_key(rule.setting) {
+            continue;
+        }
+        if let Some(value) = read_sysctl(provider, rule.setting) {
+            settings.insert(rule.setting.to_string(), json!(value));
+        }
+    }
+
+    let mut violations: Vec<Value> = Vec::new();
+    for rule in RULES.iter().filter(|r| consumers.contains(&r.consumer)) {
+        let actual = if rule.setting == NOFILE_SETTING {
+            nofile.map(|(soft, _)| soft)
+        } else {
+            settings.get(rule.setting).and_then(|v| v.as_u64())
+        };
+        if let Some(actual) = actual {
+            let violated = if rule.exact {
+                actual != rule.minimum
+            } else {
+                actual < rule.minimum
+            };
+            if violated {
+                violations.push(json!({
+                    "code": rule.code,
+                    "consumer": rule.consumer,
+                    "setting": rule.setting,
+                    "required": rule.minimum,
+                    "actual": actual,
+                }));
+            }
+        }
+    }
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert("sysctl".into(), json!(settings));
+    if let Some((soft, hard)) = nofile {
+        metadata.insert("nofile_soft".into(), json!(soft));
+        metadata.insert("nofile_hard".into(), json!(hard));
+    }
+    metadata.insert("consumers".into(), json!(consumers));
+    metadata.insert("tuning_violations".into(), json!(violations));
+
+    let status = if settings.is_empty() && nofile.is_none() {
+        Status::Inactive
+    } else {
+        Status::Active
+    };
+
+    nodes.push(Node {
+        id: "kernel".into(),
+        node_type: NodeType::Os,
+        label: "Kernel limits".into(),
+        status,
+        metadata,
+    });
+}
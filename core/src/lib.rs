@@ -1,27 +1,50 @@
+pub mod advisories;
 pub mod analyze;
+pub mod applications;
+pub mod build_prereqs;
 pub mod command_ast;
+pub mod connections;
+pub mod containers;
+pub mod cpu_features;
+pub mod cuda_compat;
 pub mod deps;
 pub mod doctor;
+pub mod environment;
+pub mod eol;
 pub mod exporter;
 pub mod fix;
+pub mod git_state;
+pub mod gpu;
 pub mod graph;
 pub mod history;
+pub mod infra;
 pub mod json_diff;
+pub mod kernel_tuning;
 pub mod models;
+pub mod node_abi;
 pub mod oracle;
+pub mod oracle_config;
+pub mod path_scan;
+pub mod permissions;
 pub mod proposed_state;
+pub mod proxy;
 pub mod remote;
 pub mod risk;
 pub mod risk_config;
 pub mod scanner;
 pub mod schema;
+pub mod secrets;
 pub mod security;
 pub mod server;
 pub mod share;
 pub mod snapshot;
 pub mod spec;
 pub mod system_provider;
+pub mod systemd;
+pub mod tls;
 pub mod tokenizer;
 pub mod utils;
 pub mod validate;
+pub mod version_pins;
 pub mod watch;
+pub mod webserver;
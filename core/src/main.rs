@@ -1,33 +1,56 @@
 mod graph;
 mod models;
 mod oracle;
+mod oracle_config;
+mod path_scan;
 mod scanner;
 mod schema;
 mod server;
 mod utils;
 mod validate;
+mod version_pins;
 
+mod advisories;
 mod analyze;
+mod applications;
+mod build_prereqs;
 mod command_ast;
 mod config;
+mod connections;
+mod containers;
+mod cpu_features;
+mod cuda_compat;
 mod deps;
 mod doctor;
+mod environment;
+mod eol;
 mod exporter;
 mod fix;
+mod git_state;
+mod gpu;
 mod history;
+mod infra;
 mod json_diff;
+mod kernel_tuning;
+mod node_abi;
+mod permissions;
 mod proposed_state;
+mod proxy;
 mod remote;
 mod risk;
 mod risk_config;
+mod secrets;
 mod security;
 mod share;
 mod snapshot;
 mod spec;
 mod system_provider;
+mod systemd;
+mod tls;
 mod tokenizer;
 mod updater;
 mod watch;
+mod webserver;
 
 use clap::{Parser, Subcommand};
 use serde_json::json;
@@ -47,6 +70,9 @@ enum Commands {
     Scan {
         #[arg(long)]
         remote: Option<String>,
+        /// Date (YYYY-MM-DD) to check end-of-life windows against.
+        #[arg(long)]
+        scan_date: Option<String>,
     },
     Simulate {
         command: String,
@@ -77,6 +103,15 @@ enum Commands {
         #[arg(long)]
         out: String,
     },
+    RefreshEol {
+        #[arg(long)]
+        from: PathBuf,
+    },
+    ImportAdvisories {
+        /// Directory of OSV JSON records.
+        #[arg(long)]
+        from: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
@@ -85,13 +120,29 @@ enum SnapshotCommand {
     Restore { name: String },
 }
 
-fn scan_command(remote: Option<String>, json_output: bool) -> Result<models::SystemState, String> {
+fn scan_command(
+    remote: Option<String>,
+    scan_date: Option<String>,
+    json_output: bool,
+) -> Result<models::SystemState, String> {
+    let mut config = oracle_config::OracleConfig::load();
+    if scan_date.is_some() {
+        config.tls.scan_date = scan_date.clone();
+        config.eol.scan_date = scan_date;
+    }
+    if let Some(warning) = eol::stale_calendar_warning(
+        &eol::EolCalendar::load(),
+        &config.eol,
+        chrono::Utc::now().date_naive(),
+    ) {
+        eprintln!("Warning: {}", warning);
+    }
     let mut state = if let Some(target) = remote {
         remote::remote_scan(&target)?
     } else {
         let mut local_state = scanner::perform_scan();
         graph::derive_edges(&mut local_state);
-        local_state.issues = oracle::evaluate(&local_state);
+        local_state.issues = oracle::evaluate_with_config(&local_state, &config);
         local_state.refresh_fingerprint();
         local_state.assert_contract();
         schema::validate_against_contract(&local_state)
@@ -199,8 +250,8 @@ async fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Scan { remote } => {
-            if let Err(e) = scan_command(remote, cli.json) {
+        Commands::Scan { remote, scan_date } => {
+            if let Err(e) = scan_command(remote, scan_date, cli.json) {
                 eprintln!("Scan failed: {}", e);
                 std::process::exit(1);
             }
@@ -318,5 +369,19 @@ async fn main() {
                 std::process::exit(1);
             }
         }
+        Commands::RefreshEol { from } => match eol::refresh(&from) {
+            Ok(summary) => println!("{}", summary),
+            Err(e) => {
+                eprintln!("EOL refresh failed: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::ImportAdvisories { from } => match advisories::import(&from) {
+            Ok(summary) => println!("{}", summary),
+            Err(e) => {
+                eprintln!("Advisory import failed: {}", e);
+                std::process::exit(1);
+            }
+        },
     }
 }
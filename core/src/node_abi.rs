@@ -0,0 +1,157 @@
+use crate::models::{Node, Status};
+use crate::system_provider::SystemProvider;
+use regex::Regex;
+use serde_json::json;
+use serde_json::Value;
+
+/// Packages in `node_modules/.package-lock.json` that ran an install script,
+/// which is how npm marks addons compiled or downloaded at install time.
+pub fn install_script_packages(hidden_lockfile: &str) -> Vec<(String, String)> {
+    let Ok(lock) = serde_json::from_str::<Value>(hidden_lockfile) else {
+        return Vec::new();
+    };
+    let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) else {
+        return Vec::new();
+    };
+    packages
+        .iter()
+        .filter(|(_, entry)| {
+            entry
+                .get("hasInstallScript")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+        })
+        .filter_map(|(path, _)| {
+            let name = path.rsplit("node_modules/").next()?;
+            (!name.is_empty()).then(|| (name.to_string(), path.clone()))
+        })
+        .collect()
+}
+
+/// The `node_module_version` node-gyp recorded when it built the addon.
+pub fn gypi_module_version(config_gypi: &str) -> Option<String> {
+    let re = Regex::new(r#""node_module_version"\s*:\s*(\d+)"#)
+        .expect("regex construction invariant: constant pattern");
+    re.captures(config_gypi).map(|c| c[1].to_string())
+}
+
+/// ABI numbers embedded in prebuilt binary names: node-pre-gyp's
+/// `node-v115-linux-x64` directories and prebuildify's `node.abi115.node`.
+pub fn binary_name_abi(name: &str) -> Option<String> {
+    let re =
+        Regex::new(r"(?:node-v|abi)(\d+)").expect("regex construction invariant: constant pattern");
+    re.captures(name).map(|c| c[1].to_string())
+}
+
+/// N-API addons are ABI-stable across Node releases and never need a rebuild
+/// after an upgrade.
+fn uses_napi(package_json: &Value) -> bool {
+    let depends_on_addon_api = ["dependencies", "devDependencies"].iter().any(|key| {
+        package_json
+            .get(*key)
+            .and_then(|d| d.get("node-addon-api"))
+            .is_some()
+    });
+    let declares_napi = package_json
+        .get("binary")
+        .and_then(|b| b.get("napi_versions"))
+        .is_some();
+    depends_on_addon_api || declares_napi
+}
+
+fn built_abis<P: SystemProvider>(provider: &P, dir: &str) -> (Vec<String>, Vec<&'static str>) {
+    let mut abis = Vec::new();
+    let mut sources = Vec::new();
+
+    if let Some(abi) = provider
+        .read_file(&format!("{}/build/config.gypi", dir))
+        .and_then(|c| gypi_module_version(&c))
+    {
+        abis.push(abi);
+        sources.push("build/config.gypi");
+    }
+    if let Some(entries) = provider.list_dir(&format!("{}/lib/binding", dir)) {
+        let found: Vec<String> = entries.iter().filter_map(|e| binary_name_abi(e)).collect();
+        if !found.is_empty() {
+            abis.extend(found);
+            sources.push("lib/binding");
+        }
+    }
+    let prebuild_root = format!("{}/prebuilds", dir);
+    for platform in provider.list_dir(&prebuild_root).unwrap_or_default() {
+        let files = provider
+            .list_dir(&format!("{}/{}", prebuild_root, platform))
+            .unwrap_or_default();
+        let found: Vec<String> = files.iter().filter_map(|f| binary_name_abi(f)).collect();
+        if !found.is_empty() {
+            abis.extend(found);
+            if !sources.contains(&"prebuilds") {
+                sources.push("prebuilds");
+            }
+        }
+    }
+
+    abis.sort();
+    abis.dedup();
+    (abis, sources)
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let Some(nodejs) = nodes
+        .iter_mut()
+        .find(|n| n.id == "nodejs" && n.status == Status::Active)
+    else {
+        return;
+    };
+    let Some(running_abi) = provider
+        .command_output("node", &["-p", "process.versions.modules"])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+    else {
+        return;
+    };
+    nodejs
+        .metadata
+        .insert("node_abi".into(), json!(running_abi));
+
+    let candidates = provider
+        .read_file("node_modules/.package-lock.json")
+        .map(|c| install_script_packages(&c))
+        .unwrap_or_default();
+
+    let mut addons: Vec<Value> = Vec::new();
+    let mut mismatches: Vec<Value> = Vec::new();
+    for (name, dir) in candidates {
+        let napi = provider
+            .read_file(&format!("{}/package.json", dir))
+            .and_then(|c| serde_json::from_str::<Value>(&c).ok())
+            .map(|pkg| uses_napi(&pkg))
+            .unwrap_or(false);
+        let (abis, sources) = built_abis(provider, &dir);
+        if abis.is_empty() && !napi {
+            continue;
+        }
+        addons.push(json!({
+            "package": name,
+            "path": dir,
+            "abi": abis,
+            "sources": sources,
+            "napi": napi,
+        }));
+        if !napi && !abis.contains(&running_abi) {
+            mismatches.push(json!({
+                "package": name,
+                "path": dir,
+                "built_abi": abis,
+                "running_abi": running_abi,
+            }));
+        }
+    }
+
+    nodejs
+        .metadata
+        .insert("native_addons".into(), json!(addons));
+    nodejs
+        .metadata
+        .insert("native_addon_abi_mismatches".into(), json!(mismatches));
+}
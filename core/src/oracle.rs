@@ -1,9 +1,26 @@
+use crate::advisories;
 use crate::command_ast::parse_command;
+use crate::connections;
+use crate::containers;
+use crate::cuda_compat;
+use crate::environment::{Environment, EnvironmentKind};
+use crate::eol;
+use crate::infra;
 use crate::json_diff::diff_states;
-use crate::models::{Issue, Severity, Status, SystemState};
+use crate::kernel_tuning::NOFILE_SETTING;
+use crate::models::{Issue, Node, Severity, Status, SystemState};
+use crate::oracle_config::{HostThresholds, OracleConfig};
+use crate::permissions;
 use crate::proposed_state::{apply_predicted_changes, clone_state};
+use crate::proxy;
+use crate::secrets;
+use crate::security;
+use crate::systemd;
+use crate::tls;
+use crate::webserver;
 use serde_json::json;
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 pub struct SimulationResult {
     pub issues: Vec<Issue>,
@@ -34,12 +51,667 @@ fn required_api_for_compose(compose_version: &str) -> Option<(u64, u64)> {
     }
 }
 
+fn evaluate_host(
+    node: &Node,
+    thresholds: &HostThresholds,
+    compose_present: bool,
+    issues: &mut Vec<Issue>,
+) {
+    let metadata_u64 = |key: &str| node.metadata.get(key).and_then(|v| v.as_u64());
+
+    let min_memory = if compose_present {
+        thresholds
+            .compose_min_available_memory_mb
+            .max(thresholds.min_available_memory_mb)
+    } else {
+        thresholds.min_available_memory_mb
+    };
+    if let Some(available) = metadata_u64("memory_available_mb") {
+        if available < min_memory {
+            let context = if compose_present {
+                " for the compose stack"
+            } else {
+                ""
+            };
+            issues.push(Issue {
+                code: "HOST_MEMORY_LOW".into(),
+                severity: Severity::Warning,
+                title: "Available memory low".into(),
+                description: format!(
+                    "{} MB of memory available but at least {} MB is required{}.",
+                    available, min_memory, context
+                ),
+                suggestion: "Close memory-heavy applications or raise the host memory allocation."
+                    .into(),
+            });
+        }
+    }
+
+    let low_disks: Vec<String> = [
+        ("project_disk_free_mb", "project directory"),
+        ("tmp_disk_free_mb", "/tmp"),
+    ]
+    .iter()
+    .filter_map(|(key, label)| {
+        let free = metadata_u64(key)?;
+        (free < thresholds.min_free_disk_mb).then(|| format!("{} ({} MB free)", label, free))
+    })
+    .collect();
+    if !low_disks.is_empty() {
+        issues.push(Issue {
+            code: "HOST_DISK_LOW".into(),
+            severity: Severity::Warning,
+            title: "Free disk space low".into(),
+            description: format!(
+                "Less than {} MB free on: {}.",
+                thresholds.min_free_disk_mb,
+                low_disks.join(", ")
+            ),
+            suggestion: "Free disk space by pruning Docker data, caches, or build artifacts."
+                .into(),
+        });
+    }
+
+    let cpu_count = metadata_u64("cpu_count").unwrap_or(0);
+    let load_one = node
+        .metadata
+        .get("load_average")
+        .and_then(|v| v.as_array())
+        .and_then(|a| a.first())
+        .and_then(|v| v.as_f64());
+    if let Some(load) = load_one {
+        if cpu_count > 0 && load / cpu_count as f64 > thresholds.max_load_per_cpu {
+            issues.push(Issue {
+                code: "HOST_LOAD_HIGH".into(),
+                severity: Severity::Warning,
+                title: "Host load high".into(),
+                description: format!(
+                    "1-minute load average {:.2} exceeds {:.1} per CPU across {} CPUs.",
+                    load, thresholds.max_load_per_cpu, cpu_count
+                ),
+                suggestion: "Wait for background jobs to finish or stop busy processes before running workloads.".into(),
+            });
+        }
+    }
+}
+
+fn evaluate_kernel_tuning(node: &Node, environment: &Environment, issues: &mut Vec<Issue>) {
+    let violations = node
+        .metadata
+        .get("tuning_violations")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let mut by_code: BTreeMap<String, Vec<&Value>> = BTreeMap::new();
+    for violation in &violations {
+        if let Some(code) = violation.get("code").and_then(|v| v.as_str()) {
+            by_code.entry(code.to_string()).or_default().push(violation);
+        }
+    }
+
+    for (code, entries) in by_code {
+        let setting = entries[0]
+            .get("setting")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let actual = entries[0]
+            .get("actual")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let required = entries
+            .iter()
+            .filter_map(|e| e.get("required").and_then(|v| v.as_u64()))
+            .max()
+            .unwrap_or(0);
+        let consumers: Vec<&str> = entries
+            .iter()
+            .filter_map(|e| e.get("consumer").and_then(|v| v.as_str()))
+            .collect();
+        let (title, suggestion) = if setting == NOFILE_SETTING {
+            (
+                "Open file limit too low".to_string(),
+                format!(
+                    "Raise the nofile limit to {} in /etc/security/limits.conf (e.g. `* soft nofile {}`) and start a new login session.",
+                    required, required
+                ),
+            )
+        } else if environment.in_container() {
+            // vm.* and most net.core.* sysctls are not namespaced, so only the
+            // host (or the runtime's --sysctl flag for namespaced ones) can set them.
+            (
+                format!("{} below workload requirement", setting),
+                format!(
+                    "Set it on the container host with `sudo sysctl -w {}={}` and persist it in /etc/sysctl.d/; it cannot be changed from inside the container.",
+                    setting, required
+                ),
+            )
+        } else {
+            (
+                format!("{} below workload requirement", setting),
+                format!(
+                    "Run `sudo sysctl -w {}={}` and persist it in /etc/sysctl.d/.",
+                    setting, required
+                ),
+            )
+        };
+        issues.push(Issue {
+            code,
+            severity: Severity::Warning,
+            title,
+            description: format!(
+                "{} is {} but {} requires {}.",
+                setting,
+                actual,
+                consumers.join(", "),
+                required
+            ),
+            suggestion,
+        });
+    }
+}
+
+fn string_list(node: &Node, key: &str) -> Vec<String> {
+    node.metadata
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn evaluate_git(node: &Node, issues: &mut Vec<Issue>) {
+    let ahead = node
+        .metadata
+        .get("ahead")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let behind = node
+        .metadata
+        .get("behind")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let upstream = node
+        .metadata
+        .get("upstream")
+        .and_then(|v| v.as_str())
+        .unwrap_or("upstream");
+    if behind > 0 && ahead > 0 {
+        issues.push(Issue {
+            code: "GIT_BRANCH_DIVERGED".into(),
+            severity: Severity::Warning,
+            title: "Branch diverged from upstream".into(),
+            description: format!(
+                "The current branch is {} commit(s) ahead of and {} commit(s) behind {}.",
+                ahead, behind, upstream
+            ),
+            suggestion: "Rebase onto or merge the upstream branch before continuing.".into(),
+        });
+    } else if behind > 0 {
+        issues.push(Issue {
+            code: "GIT_BRANCH_BEHIND".into(),
+            severity: Severity::Warning,
+            title: "Branch behind upstream".into(),
+            description: format!(
+                "The current branch is {} commit(s) behind {}.",
+                behind, upstream
+            ),
+            suggestion: "Fast-forward the branch to pick up upstream changes.".into(),
+        });
+    }
+
+    let manifests = string_list(node, "uncommitted_manifests");
+    if !manifests.is_empty() {
+        issues.push(Issue {
+            code: "GIT_MANIFEST_UNCOMMITTED".into(),
+            severity: Severity::Warning,
+            title: "Uncommitted dependency manifest changes".into(),
+            description: format!(
+                "Dependency manifests have uncommitted changes: {}.",
+                manifests.join(", ")
+            ),
+            suggestion:
+                "Commit or discard manifest changes so installs match what teammates and CI see."
+                    .into(),
+        });
+    }
+
+    let uninitialized = string_list(node, "submodules_uninitialized");
+    if !uninitialized.is_empty() {
+        issues.push(Issue {
+            code: "GIT_SUBMODULE_UNINITIALIZED".into(),
+            severity: Severity::Critical,
+            title: "Git submodules not initialized".into(),
+            description: format!(
+                "Submodules have not been checked out: {}.",
+                uninitialized.join(", ")
+            ),
+            suggestion: "Run git submodule update --init --recursive.".into(),
+        });
+    }
+    let outdated = string_list(node, "submodules_outdated");
+    if !outdated.is_empty() {
+        issues.push(Issue {
+            code: "GIT_SUBMODULE_OUT_OF_DATE".into(),
+            severity: Severity::Warning,
+            title: "Git submodules out of date".into(),
+            description: format!(
+                "Submodules are not at the commit recorded by the superproject: {}.",
+                outdated.join(", ")
+            ),
+            suggestion: "Run git submodule update --recursive.".into(),
+        });
+    }
+
+    let lfs_tracked = node
+        .metadata
+        .get("lfs_tracked")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let lfs_installed = node
+        .metadata
+        .get("lfs_installed")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let pointers = string_list(node, "lfs_pointers");
+    if lfs_tracked && (!lfs_installed || !pointers.is_empty()) {
+        let description = if lfs_installed {
+            format!(
+                "Git LFS files are still pointer stubs: {}.",
+                pointers.join(", ")
+            )
+        } else {
+            ".gitattributes tracks files with Git LFS but git-lfs is not installed.".to_string()
+        };
+        issues.push(Issue {
+            code: "GIT_LFS_NOT_PULLED".into(),
+            severity: Severity::Critical,
+            title: "Git LFS content missing".into(),
+            description,
+            suggestion: "Install git-lfs, then run git lfs install && git lfs pull.".into(),
+        });
+    }
+
+    let hooks_missing = string_list(node, "hooks_missing");
+    if !hooks_missing.is_empty() {
+        let suggestion = match node
+            .metadata
+            .get("expected_hooks_path")
+            .and_then(|v| v.as_str())
+        {
+            Some(dir) => format!("Run git config core.hooksPath {}.", dir),
+            None => "Install the repository hooks, e.g. with pre-commit install.".into(),
+        };
+        issues.push(Issue {
+            code: "GIT_HOOKS_MISSING".into(),
+            severity: Severity::Warning,
+            title: "Repository git hooks not installed".into(),
+            description: format!("{}.", hooks_missing.join("; ")),
+            suggestion,
+        });
+    }
+
+    if node
+        .metadata
+        .get("user_email")
+        .map(|v| v.is_null())
+        .unwrap_or(false)
+    {
+        issues.push(Issue {
+            code: "GIT_USER_EMAIL_UNSET".into(),
+            severity: Severity::Warning,
+            title: "Git user.email not configured".into(),
+            description:
+                "git config user.email is unset, so commits will fail or be misattributed.".into(),
+            suggestion: "Set git config --global user.email to your work address.".into(),
+        });
+    }
+}
+
+fn evaluate_node_abi(node: &Node, issues: &mut Vec<Issue>) {
+    let mismatches = node
+        .metadata
+        .get("native_addon_abi_mismatches")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    if mismatches.is_empty() {
+        return;
+    }
+    let running = node
+        .metadata
+        .get("node_abi")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let version = node
+        .metadata
+        .get("version")
+        .and_then(|v| v.as_str())
+        .unwrap_or("the running Node");
+    let mut packages = Vec::new();
+    let mut details = Vec::new();
+    for entry in &mismatches {
+        let package = entry.get("package").and_then(|v| v.as_str()).unwrap_or("");
+        let built: Vec<&str> = entry
+            .get("built_abi")
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        details.push(format!("{} (built for ABI {})", package, built.join("/")));
+        packages.push(package.to_string());
+    }
+    issues.push(Issue {
+        code: "NODE_ABI_MISMATCH".into(),
+        severity: Severity::Critical,
+        title: "Native addons built for another Node version".into(),
+        description: format!(
+            "{} uses NODE_MODULE_VERSION {} but these addons were built for a different ABI: {}.",
+            version,
+            running,
+            details.join(", ")
+        ),
+        suggestion: format!("Rebuild them with npm rebuild {}.", packages.join(" ")),
+    });
+}
+
+fn evaluate_build_prerequisites(node: &Node, code: &str, issues: &mut Vec<Issue>) {
+    let findings = node
+        .metadata
+        .get("build_prerequisites_missing")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let mut details = Vec::new();
+    let mut apt_packages: Vec<String> = Vec::new();
+    for finding in &findings {
+        let package = finding
+            .get("package")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let missing: Vec<&Value> = finding
+            .get("missing")
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().collect())
+            .unwrap_or_default();
+        let names: Vec<&str> = missing
+            .iter()
+            .filter_map(|m| m.get("name").and_then(|v| v.as_str()))
+            .collect();
+        for apt in missing
+            .iter()
+            .filter_map(|m| m.get("apt_package").and_then(|v| v.as_str()))
+        {
+            if !apt_packages.iter().any(|a| a == apt) {
+                apt_packages.push(apt.to_string());
+            }
+        }
+        details.push(format!("{} needs {}", package, names.join(", ")));
+    }
+    if details.is_empty() {
+        return;
+    }
+    apt_packages.sort();
+    issues.push(Issue {
+        code: code.into(),
+        severity: Severity::Warning,
+        title: "Native build prerequisites missing".into(),
+        description: format!(
+            "Packages that compile during install are missing system dependencies: {}.",
+            details.join("; ")
+        ),
+        suggestion: format!(
+            "Install the build dependencies before installing packages: sudo apt-get install -y {}",
+            apt_packages.join(" ")
+        ),
+    });
+}
+
+fn evaluate_native_wheels(node: &Node, issues: &mut Vec<Issue>) {
+    let entries = |key: &str| {
+        node.metadata
+            .get(key)
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let field = |entry: &Value, key: &str| {
+        entry
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+
+    let arch_mismatches: Vec<String> = entries("native_wheel_arch_mismatches")
+        .iter()
+        .map(|e| {
+            format!(
+                "{} {} ({})",
+                field(e, "package"),
+                field(e, "version"),
+                field(e, "wheel_arch")
+            )
+        })
+        .collect();
+    if !arch_mismatches.is_empty() {
+        issues.push(Issue {
+            code: "WHEEL_ARCH_MISMATCH".into(),
+            severity: Severity::Critical,
+            title: "Native wheels built for another architecture".into(),
+            description: format!(
+                "Installed wheels target a different CPU architecture than this host: {}.",
+                arch_mismatches.join(", ")
+            ),
+            suggestion: "Reinstall these packages with pip install --force-reinstall --no-cache-dir so pip selects wheels for this host.".into(),
+        });
+    }
+
+    for entry in entries("native_wheel_flag_mismatches") {
+        let flag = field(&entry, "flag");
+        issues.push(Issue {
+            code: "WHEEL_CPU_FLAG_MISSING".into(),
+            severity: Severity::Critical,
+            title: "CPU lacks instructions a wheel requires".into(),
+            description: format!(
+                "{} {} is compiled with {} but the CPU does not report the {} flag; it will crash with an illegal instruction.",
+                field(&entry, "package"),
+                field(&entry, "version"),
+                flag.to_uppercase(),
+                flag
+            ),
+            suggestion: format!("Use {} instead.", field(&entry, "alternative")),
+        });
+    }
+}
+
+fn evaluate_path(node: &Node, issues: &mut Vec<Issue>) {
+    let Some(tools) = node.metadata.get("tools").and_then(|v| v.as_object()) else {
+        return;
+    };
+    let describe = |candidate: &Value| {
+        let path = candidate.get("path").and_then(|v| v.as_str()).unwrap_or("");
+        let source = candidate
+            .get("source")
+            .and_then(|v| v.as_str())
+            .unwrap_or("system");
+        format!("{} ({})", path, source)
+    };
+
+    let mut details = Vec::new();
+    for (tool, entry) in tools {
+        let shadowed = entry
+            .get("shadowed")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        if let (Some(winner), false) = (entry.get("winner"), shadowed.is_empty()) {
+            let losers: Vec<String> = shadowed.iter().map(describe).collect();
+            details.push(format!(
+                "{}: {} shadows {}",
+                tool,
+                describe(winner),
+                losers.join(", ")
+            ));
+        }
+    }
+    if !details.is_empty() {
+        issues.push(Issue {
+            code: "PATH_TOOL_SHADOWED".into(),
+            severity: Severity::Warning,
+            title: "Toolchain shadowed on PATH".into(),
+            description: format!(
+                "Earlier PATH entries hide other installs: {}.",
+                details.join("; ")
+            ),
+            suggestion: "Reorder PATH so the intended install (e.g. the version manager shim directory) comes first.".into(),
+        });
+    }
+
+    let winner = |tool: &str| {
+        let winner = tools.get(tool).and_then(|t| t.get("winner"))?;
+        let text = |key: &str| winner.get(key).and_then(|v| v.as_str());
+        Some((text("path")?, text("resolved")?))
+    };
+    if let (Some((python_path, python)), Some((python3_path, python3))) =
+        (winner("python"), winner("python3"))
+    {
+        if python != python3 {
+            issues.push(Issue {
+                code: "PYTHON_PATH_MISMATCH".into(),
+                severity: Severity::Warning,
+                title: "python and python3 resolve to different installs".into(),
+                description: format!(
+                    "python ({}) resolves to {} but python3 ({}) resolves to {}.",
+                    python_path, python, python3_path, python3
+                ),
+                suggestion: "Point python at the same interpreter as python3, e.g. via a virtual environment or update-alternatives.".into(),
+            });
+        }
+    }
+}
+
+fn pin_install_command(manager: &str, version: &str) -> String {
+    match manager {
+        "asdf" => "asdf install".into(),
+        "mise" => "mise install".into(),
+        "nvm" => "nvm install".into(),
+        "sdkman" => "sdk env install".into(),
+        other => format!("{} install {}", other, version),
+    }
+}
+
+fn evaluate_version_pins(state: &SystemState, issues: &mut Vec<Issue>) {
+    let mut manager_missing = Vec::new();
+    let mut not_installed = Vec::new();
+    let mut install_commands: Vec<String> = Vec::new();
+    let mut not_active = Vec::new();
+    let mut shim_missing = false;
+
+    for node in &state.nodes {
+        let Some(pins) = node.metadata.get("version_pins").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        for pin in pins {
+            let text = |key: &str| pin.get(key).and_then(|v| v.as_str()).unwrap_or("");
+            let flag = |key: &str| pin.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+            let label = format!("{} {} ({})", text("tool"), text("pinned"), text("source"));
+            if !flag("manager_available") {
+                manager_missing.push(format!("{} needs {}", label, text("manager")));
+            } else if !flag("installed") {
+                not_installed.push(label);
+                let command = pin_install_command(text("manager"), text("pinned"));
+                if !install_commands.contains(&command) {
+                    install_commands.push(command);
+                }
+            } else if !flag("active") {
+                let active = pin
+                    .get("active_version")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("none");
+                let mut detail = format!("{} but {} is active", label, active);
+                if !flag("shim_on_path") {
+                    detail.push_str(&format!("; {} shims are not on PATH", text("manager")));
+                    shim_missing = true;
+                }
+                not_active.push(detail);
+            }
+        }
+    }
+
+    if !manager_missing.is_empty() {
+        issues.push(Issue {
+            code: "VERSION_PIN_MANAGER_MISSING".into(),
+            severity: Severity::Warning,
+            title: "Version manager not installed".into(),
+            description: format!(
+                "Pinned runtimes expect a version manager that was not found: {}.",
+                manager_missing.join(", ")
+            ),
+            suggestion: "Install the version manager named by the pin file, or install the pinned runtime another way.".into(),
+        });
+    }
+    if !not_installed.is_empty() {
+        issues.push(Issue {
+            code: "VERSION_PIN_NOT_INSTALLED".into(),
+            severity: Severity::Warning,
+            title: "Pinned runtime version not installed".into(),
+            description: format!(
+                "Pinned versions are not installed: {}.",
+                not_installed.join(", ")
+            ),
+            suggestion: format!("Run {}.", install_commands.join(" && ")),
+        });
+    }
+    if !not_active.is_empty() {
+        let suggestion = if shim_missing {
+            "Add the version manager's shims to PATH (e.g. via its shell init hook) and open a new shell."
+        } else {
+            "Switch to the pinned version in this shell (e.g. nvm use, pyenv shell, asdf shell)."
+        };
+        issues.push(Issue {
+            code: "VERSION_PIN_NOT_ACTIVE".into(),
+            severity: Severity::Warning,
+            title: "Pinned runtime version not active".into(),
+            description: format!(
+                "Installed pinned versions are not the ones in use: {}.",
+                not_active.join(", ")
+            ),
+            suggestion: suggestion.into(),
+        });
+    }
+}
+
 pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    evaluate_with_config(state, &OracleConfig::load())
+}
+
+pub fn evaluate_with_config(state: &SystemState, config: &OracleConfig) -> Vec<Issue> {
     // unchanged from Drop 2 — left intact intentionally
     let mut issues = Vec::new();
+    let environment = Environment::of(state);
+    let compose_present = state
+        .nodes
+        .iter()
+        .any(|n| n.id == "docker" && n.metadata.contains_key("compose_file"));
 
     for node in &state.nodes {
-        if node.id == "docker" && node.status != crate::models::Status::Active {
+        if node.id == "docker"
+            && node.status != crate::models::Status::Active
+            && environment.in_container()
+        {
+            issues.push(Issue {
+                code: "DOCKER_INACTIVE_IN_CONTAINER".into(),
+                severity: Severity::Warning,
+                title: "No Docker daemon reachable from this container".into(),
+                description: format!(
+                    "Docker was unreachable from inside this {} container, where there is no host service to start.",
+                    environment.container_runtime.as_deref().unwrap_or("unknown")
+                ),
+                suggestion: "Mount the host socket (`-v /var/run/docker.sock:/var/run/docker.sock`) or enable the docker-in-docker devcontainer feature.".into(),
+            });
+        } else if node.id == "docker" && node.status != crate::models::Status::Active {
             issues.push(Issue {
                 code: "DOCKER_INACTIVE".into(),
                 severity: Severity::Warning,
@@ -105,6 +777,10 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
         }
 
         match node.id.as_str() {
+            "os" => evaluate_host(node, &config.host, compose_present, &mut issues),
+            "kernel" => evaluate_kernel_tuning(node, &environment, &mut issues),
+            "git" if node.status == Status::Active => evaluate_git(node, &mut issues),
+            "path" => evaluate_path(node, &mut issues),
             "python" => {
                 let env_flags = [
                     node.metadata
@@ -225,11 +901,20 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                         .and_then(|v| v.as_str());
                     if let (Some(a), Some(b)) = (v, v3) {
                         if a != b {
+                            let path = node.metadata.get("python_path").and_then(|v| v.as_str());
+                            let path3 = node.metadata.get("python3_path").and_then(|v| v.as_str());
+                            let description = match (path, path3) {
+                                (Some(p), Some(p3)) => format!(
+                                    "python reports {} from {} but python3 reports {} from {}.",
+                                    a, p, b, p3
+                                ),
+                                _ => "python and python3 report different versions.".into(),
+                            };
                             issues.push(Issue {
                                 code: "PYTHON_VERSION_DRIFT".into(),
                                 severity: Severity::Warning,
                                 title: "Python version drift".into(),
-                                description: "python and python3 report different versions.".into(),
+                                description,
                                 suggestion:
                                     "Align python and python3 to the same version or adjust PATH."
                                         .into(),
@@ -237,6 +922,8 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                         }
                     }
                 }
+                evaluate_native_wheels(node, &mut issues);
+                evaluate_build_prerequisites(node, "PYTHON_BUILD_DEPS_MISSING", &mut issues);
             }
             "nodejs" if node.status != Status::Active => {
                 issues.push(Issue {
@@ -248,6 +935,8 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                 });
             }
             "nodejs" => {
+                evaluate_build_prerequisites(node, "NODE_BUILD_DEPS_MISSING", &mut issues);
+                evaluate_node_abi(node, &mut issues);
                 let has_package_json = node
                     .metadata
                     .get("package_json_present")
@@ -332,11 +1021,10 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                     .get("port_bound")
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false);
-                let process_count = node
+                let instance_count = node
                     .metadata
-                    .get("processes")
-                    .and_then(|v| v.as_array())
-                    .map(|a| a.len())
+                    .get("instance_count")
+                    .and_then(|v| v.as_u64())
                     .unwrap_or(0);
                 let installed_versions = node
                     .metadata
@@ -353,12 +1041,15 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                         suggestion: "Stop the conflicting PostgreSQL instance or update the port configuration.".into(),
                     });
                 }
-                if process_count > 1 {
+                if instance_count > 1 {
                     issues.push(Issue {
                         code: "POSTGRES_MULTI_INSTANCE".into(),
                         severity: Severity::Warning,
-                        title: "Multiple PostgreSQL processes".into(),
-                        description: "More than one PostgreSQL process detected.".into(),
+                        title: "Multiple PostgreSQL instances".into(),
+                        description: format!(
+                            "{} PostgreSQL server processes are running independently.",
+                            instance_count
+                        ),
                         suggestion: "Consolidate to a single instance or ensure intentional multi-instance setup.".into(),
                     });
                 }
@@ -462,20 +1153,33 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                     });
                 }
             }
+            "gpu" if node.status != Status::Active && environment.in_container() => {
+                issues.push(Issue {
+                    code: "GPU_NOT_PASSED_THROUGH".into(),
+                    severity: Severity::Warning,
+                    title: "No GPU passed through to the container".into(),
+                    description: format!(
+                        "No GPU is visible inside this {} container; the host may still have one.",
+                        environment.container_runtime.as_deref().unwrap_or("unknown")
+                    ),
+                    suggestion: "Start the container with `--gpus all` (NVIDIA Container Toolkit) or a GPU device request in compose or devcontainer.json.".into(),
+                });
+            }
             "gpu" if node.status != Status::Active => {
+                let suggestion = match environment.kind {
+                    EnvironmentKind::Wsl => "Install the Windows NVIDIA driver with WSL support; do not install a Linux driver inside WSL.",
+                    EnvironmentKind::VirtualMachine => "Attach a GPU to this VM through PCI passthrough or vGPU, then install its driver in the guest.",
+                    _ => "Install GPU drivers or ensure the GPU is accessible to this environment.",
+                };
                 issues.push(Issue {
                     code: "GPU_MISSING".into(),
                     severity: Severity::Warning,
                     title: "GPU unavailable".into(),
                     description: "No GPU was detected via nvidia-smi.".into(),
-                    suggestion:
-                        "Install GPU drivers or ensure the GPU is accessible to this environment."
-                            .into(),
+                    suggestion: suggestion.into(),
                 });
             }
             "gpu" => {
-                let cuda_version = node.metadata.get("cuda_version").and_then(|v| v.as_str());
-                let nvidia_smi = node.metadata.get("nvidia_smi").and_then(|v| v.as_str());
                 let cudnn_version = node.metadata.get("cudnn_version").and_then(|v| v.as_str());
                 let amd_gpu = node
                     .metadata
@@ -492,7 +1196,7 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                         code: "GPU_AMD_DETECTED".into(),
                         severity: Severity::Warning,
                         title: "AMD GPU detected".into(),
-                        description: "An AMD GPU was detected via lspci.".into(),
+                        description: "An AMD GPU was detected via rocm-smi or lspci.".into(),
                         suggestion: "Ensure AMD drivers and ROCm are installed if required.".into(),
                     });
                 }
@@ -505,32 +1209,6 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                         suggestion: "Install appropriate Intel graphics drivers if needed.".into(),
                     });
                 }
-                if let (Some(cuda), Some(smi)) = (cuda_version, nvidia_smi) {
-                    if let Some(smi_cuda) = smi.lines().find_map(|l| {
-                        if l.contains("CUDA Version") {
-                            l.split("CUDA Version:").nth(1).map(|s| {
-                                s.trim().split_whitespace().next().unwrap_or("").to_string()
-                            })
-                        } else {
-                            None
-                        }
-                    }) {
-                        if !smi_cuda.is_empty() && !cuda.contains(&smi_cuda) {
-                            issues.push(Issue {
-                                code: "CUDA_VERSION_MISMATCH".into(),
-                                severity: Severity::Warning,
-                                title: "CUDA version mismatch".into(),
-                                description: format!(
-                                    "nvcc reports {} but nvidia-smi shows {}.",
-                                    cuda, smi_cuda
-                                ),
-                                suggestion:
-                                    "Align installed CUDA toolkit with driver-supported version."
-                                        .into(),
-                            });
-                        }
-                    }
-                }
                 if cudnn_version.is_none() && node.status == Status::Active {
                     issues.push(Issue {
                         code: "CUDNN_MISSING".into(),
@@ -541,10 +1219,55 @@ pub fn evaluate(state: &SystemState) -> Vec<Issue> {
                     });
                 }
             }
+            id if id.starts_with("gpu_") => {
+                let driver_loaded = node
+                    .metadata
+                    .get("driver_loaded")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+                if !driver_loaded {
+                    let bus = node
+                        .metadata
+                        .get("pci_bus_id")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown");
+                    issues.push(Issue {
+                        code: "GPU_DRIVER_MISSING".into(),
+                        severity: Severity::Warning,
+                        title: "GPU driver not loaded".into(),
+                        description: format!(
+                            "{} at PCI {} is present but its vendor tool reported no devices.",
+                            node.label, bus
+                        ),
+                        suggestion: "Install and load the vendor driver (NVIDIA driver or ROCm) for this GPU.".into(),
+                    });
+                }
+            }
             _ => {}
         }
     }
 
+    evaluate_version_pins(state, &mut issues);
+    issues.extend(cuda_compat::evaluate(state));
+    issues.extend(eol::evaluate(state, &config.eol));
+    issues.extend(advisories::evaluate(state));
+    issues.extend(security::evaluate(state));
+    issues.extend(secrets::evaluate(state));
+    issues.extend(permissions::evaluate(state));
+    issues.extend(proxy::evaluate(state));
+    issues.extend(tls::evaluate(state, config));
+    issues.extend(webserver::evaluate(state));
+    issues.extend(infra::evaluate(state));
+    issues.extend(containers::evaluate(state));
+    issues.extend(connections::evaluate(state));
+    let unit_issues = systemd::evaluate(state);
+    let replaced: Vec<String> = unit_issues
+        .iter()
+        .filter_map(systemd::replaced_code)
+        .collect();
+    issues.retain(|i| !replaced.contains(&i.code));
+    issues.extend(unit_issues);
+
     issues
 }
 
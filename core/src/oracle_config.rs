@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use std::fs;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct OracleConfig {
+    pub host: HostThresholds,
+    pub eol: EolSettings,
+    pub tls: TlsSettings,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HostThresholds {
+    pub min_available_memory_mb: u64,
+    pub compose_min_available_memory_mb: u64,
+    pub min_free_disk_mb: u64,
+    pub max_load_per_cpu: f64,
+}
+
+impl Default for HostThresholds {
+    fn default() -> Self {
+        HostThresholds {
+            min_available_memory_mb: 2048,
+            compose_min_available_memory_mb: 8192,
+            min_free_disk_mb: 5120,
+            max_load_per_cpu: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct EolSettings {
+    /// `YYYY-MM-DD` date support windows are measured against. When unset the
+    /// calendar's own `as_of` date is used so repeated scans stay identical.
+    pub scan_date: Option<String>,
+    pub warn_days: i64,
+}
+
+impl Default for EolSettings {
+    fn default() -> Self {
+        EolSettings {
+            scan_date: None,
+            warn_days: 180,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TlsSettings {
+    /// `YYYY-MM-DD` date certificate expiry is measured against. Unlike the
+    /// EOL scan date this defaults to today, so pinning the EOL calendar never
+    /// hides expired certificates.
+    pub scan_date: Option<String>,
+    /// Certificates expiring within this many days of the scan date raise
+    /// `TLS_CERT_EXPIRING`.
+    pub warn_days: i64,
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        TlsSettings {
+            scan_date: None,
+            warn_days: 30,
+        }
+    }
+}
+
+impl OracleConfig {
+    pub fn load() -> OracleConfig {
+        let path = ".preflight/oracle.toml";
+        if let Ok(toml_str) = fs::read_to_string(path) {
+            toml::from_str(&toml_str).unwrap_or_default()
+        } else {
+            OracleConfig::default()
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::models::{Node, NodeType, Status};
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const TOOLS: &[&str] = &[
+    "python", "python3", "node", "npm", "docker", "psql", "go", "java",
+];
+
+/// Path fragments that identify which installer owns an executable.
+const INSTALL_SOURCES: &[(&str, &str)] = &[
+    ("/.nvm/", "nvm"),
+    ("/.volta/", "volta"),
+    ("/fnm/", "fnm"),
+    ("/.pyenv/", "pyenv"),
+    ("/.asdf/", "asdf"),
+    ("/mise/", "mise"),
+    ("/.rbenv/", "rbenv"),
+    ("/.sdkman/", "sdkman"),
+    ("conda", "conda"),
+    ("/.venv/", "venv"),
+    ("/venv/", "venv"),
+    ("/.virtualenvs/", "venv"),
+    ("/homebrew/", "homebrew"),
+    ("/linuxbrew/", "homebrew"),
+    ("/snap/", "snap"),
+    ("/usr/local/", "local"),
+];
+
+pub fn install_source(path: &str) -> &'static str {
+    INSTALL_SOURCES
+        .iter()
+        .find(|(fragment, _)| path.contains(fragment))
+        .map(|(_, source)| *source)
+        .unwrap_or("system")
+}
+
+pub fn path_entries<P: SystemProvider>(provider: &P) -> Vec<String> {
+    let mut seen = Vec::new();
+    for dir in provider.env_var("PATH").unwrap_or_default().split(':') {
+        let dir = dir.trim_end_matches('/');
+        if !dir.is_empty() && !seen.iter().any(|d| d == dir) {
+            seen.push(dir.to_string());
+        }
+    }
+    seen
+}
+
+fn tool_candidates<P: SystemProvider>(provider: &P, dirs: &[String], tool: &str) -> Vec<Value> {
+    dirs.iter()
+        .map(|dir| format!("{}/{}", dir, tool))
+        .filter(|path| provider.file_exists(path))
+        .map(|path| {
+            // A venv's `bin/python` links to the interpreter it was made from;
+            // the entry on PATH is what says which environment is active.
+            let resolved = provider.resolve_path(&path).unwrap_or_else(|| path.clone());
+            json!({
+                "source": install_source(&path),
+                "path": path,
+                "resolved": resolved,
+            })
+        })
+        .collect()
+}
+
+fn field<'a>(candidate: &'a Value, key: &str) -> &'a str {
+    candidate.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let dirs = path_entries(provider);
+    let mut tools = BTreeMap::new();
+
+    for tool in TOOLS {
+        let candidates = tool_candidates(provider, &dirs, tool);
+        let Some(winner) = candidates.first() else {
+            continue;
+        };
+        let winner_resolved = field(winner, "resolved");
+        let mut shadowed: Vec<Value> = Vec::new();
+        for candidate in candidates.iter().skip(1) {
+            let resolved = field(candidate, "resolved");
+            if resolved != winner_resolved
+                && !shadowed.iter().any(|s| field(s, "resolved") == resolved)
+            {
+                shadowed.push(candidate.clone());
+            }
+        }
+        tools.insert(
+            tool.to_string(),
+            json!({
+                "winner": winner.clone(),
+                "candidates": candidates.clone(),
+                "shadowed": shadowed,
+            }),
+        );
+    }
+
+    let winner_path = |tool: &str| {
+        tools
+            .get(tool)
+            .and_then(|t| t.get("winner"))
+            .map(|w| field(w, "path").to_string())
+    };
+    if let Some(python) = nodes.iter_mut().find(|n| n.id == "python") {
+        if let Some(path) = winner_path("python") {
+            python.metadata.insert("python_path".into(), json!(path));
+        }
+        if let Some(path) = winner_path("python3") {
+            python.metadata.insert("python3_path".into(), json!(path));
+        }
+    }
+    if let Some(nodejs) = nodes.iter_mut().find(|n| n.id == "nodejs") {
+        if let Some(path) = winner_path("node") {
+            nodejs.metadata.insert("node_path".into(), json!(path));
+        }
+    }
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert("entries".into(), json!(dirs));
+    metadata.insert("tools".into(), json!(tools));
+
+    let status = if dirs.is_empty() {
+        Status::Inactive
+    } else {
+        Status::Active
+    };
+
+    nodes.push(Node {
+        id: "path".into(),
+        node_type: NodeType::File,
+        label: "PATH toolchains".into(),
+        status,
+        metadata,
+    });
+}
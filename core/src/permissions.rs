@@ -0,0 +1,327 @@
+use crate::models::{Issue, Node, Severity, SystemState};
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Access a tool needs on a socket, device or directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// Sockets and device nodes are opened read-write.
+    ReadWrite,
+    /// Creating sockets or lock files in a directory.
+    WriteDir,
+}
+
+impl Access {
+    fn bits(self) -> u32 {
+        match self {
+            Access::ReadWrite => 0o6,
+            Access::WriteDir => 0o3,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Access::ReadWrite => "read-write",
+            Access::WriteDir => "write",
+        }
+    }
+}
+
+const FIXED_RESOURCES: &[(&str, &str, Access)] = &[
+    (
+        "/var/run/docker.sock",
+        "Docker daemon socket",
+        Access::ReadWrite,
+    ),
+    ("/dev/kfd", "ROCm compute device", Access::ReadWrite),
+    ("/dev/kvm", "KVM virtualization", Access::ReadWrite),
+    (
+        "/var/run/libvirt/libvirt-sock",
+        "libvirt daemon socket",
+        Access::ReadWrite,
+    ),
+    (
+        "/var/run/postgresql",
+        "PostgreSQL socket directory",
+        Access::WriteDir,
+    ),
+];
+
+/// Groups that are safe to join for device or socket access. Access that
+/// only `root` (or another system group) grants needs sudo instead.
+const GRANTING_GROUPS: &[&str] = &["docker", "video", "render", "kvm", "libvirt", "postgres"];
+
+struct Identity {
+    user: String,
+    uid: u32,
+    gid: u32,
+    groups: Vec<String>,
+    /// Groups in the account database, which include memberships added
+    /// since the current session started.
+    configured_groups: Vec<String>,
+}
+
+fn id_output<P: SystemProvider>(provider: &P, args: &[&str]) -> Option<String> {
+    provider
+        .command_output("id", args)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn identity<P: SystemProvider>(provider: &P) -> Option<Identity> {
+    let user = id_output(provider, &["-un"])?;
+    let uid = id_output(provider, &["-u"])?.parse().ok()?;
+    let gid = id_output(provider, &["-g"])?.parse().ok()?;
+    let split = |s: String| -> Vec<String> { s.split_whitespace().map(String::from).collect() };
+    let groups = id_output(provider, &["-Gn"]).map(split).unwrap_or_default();
+    let configured_groups = id_output(provider, &["-Gn", &user])
+        .map(split)
+        .unwrap_or_else(|| groups.clone());
+    Some(Identity {
+        user,
+        uid,
+        gid,
+        groups,
+        configured_groups,
+    })
+}
+
+/// GPU device nodes present on this host.
+fn device_resources<P: SystemProvider>(provider: &P) -> Vec<(String, &'static str, Access)> {
+    let mut devices = Vec::new();
+    let mut nvidia: Vec<String> = provider
+        .list_dir("/dev")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|d| d.starts_with("nvidia") && d != "nvidia-caps")
+        .collect();
+    nvidia.sort();
+    for name in nvidia {
+        devices.push((
+            format!("/dev/{}", name),
+            "NVIDIA GPU device",
+            Access::ReadWrite,
+        ));
+    }
+    let mut render: Vec<String> = provider
+        .list_dir("/dev/dri")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|d| d.starts_with("renderD"))
+        .collect();
+    render.sort();
+    for name in render {
+        devices.push((
+            format!("/dev/dri/{}", name),
+            "GPU render node",
+            Access::ReadWrite,
+        ));
+    }
+    devices
+}
+
+/// `stat -c '%a %U %G'` output as (mode, owner, group).
+pub fn parse_stat(raw: &str) -> Option<(u32, String, String)> {
+    let mut parts = raw.split_whitespace();
+    let mode = u32::from_str_radix(parts.next()?, 8).ok()? & 0o777;
+    Some((mode, parts.next()?.to_string(), parts.next()?.to_string()))
+}
+
+fn check_resource<P: SystemProvider>(
+    provider: &P,
+    who: &Identity,
+    path: &str,
+    purpose: &str,
+    access: Access,
+) -> Option<Value> {
+    if !provider.file_exists(path) {
+        return None;
+    }
+    let (mode, owner, group) =
+        parse_stat(&provider.command_output("stat", &["-L", "-c", "%a %U %G", path])?)?;
+    let need = access.bits();
+    let granted = |shift: u32| (mode >> shift) & need == need;
+    let in_group = who.groups.contains(&group);
+    let allowed =
+        who.uid == 0 || (owner == who.user && granted(6)) || (in_group && granted(3)) || granted(0);
+    // Joining the owning group only helps when its bits grant access and it
+    // is a group meant for delegation.
+    let via_group = !allowed && granted(3) && GRANTING_GROUPS.contains(&group.as_str());
+    let pending_relogin = via_group && who.configured_groups.contains(&group);
+    Some(json!({
+        "path": path,
+        "purpose": purpose,
+        "access": access.label(),
+        "mode": format!("{:o}", mode),
+        "owner": owner,
+        "group": group,
+        "allowed": allowed,
+        "needs_group": via_group.then(|| group.clone()),
+        "pending_relogin": pending_relogin,
+        "sudo_required": !allowed,
+    }))
+}
+
+/// Records the current user's uid, gid and groups on the os node, and
+/// whether they can use the Docker socket, GPU devices and service sockets
+/// without sudo.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let Some(who) = identity(provider) else {
+        return;
+    };
+    let mut resources: Vec<(String, &str, Access)> = FIXED_RESOURCES
+        .iter()
+        .map(|(path, purpose, access)| (path.to_string(), *purpose, *access))
+        .collect();
+    resources.extend(device_resources(provider));
+    let checks: Vec<Value> = resources
+        .iter()
+        .filter_map(|(path, purpose, access)| {
+            check_resource(provider, &who, path, purpose, *access)
+        })
+        .collect();
+    // `sudo -n true` prints nothing on success, so echo a marker.
+    let passwordless_sudo = who.uid != 0
+        && provider
+            .command_output("sh", &["-c", "sudo -n true 2>/dev/null && echo ok"])
+            .is_some_and(|out| out.trim() == "ok");
+
+    if let Some(os) = nodes.iter_mut().find(|n| n.id == "os") {
+        os.metadata.insert("user".into(), json!(who.user));
+        os.metadata.insert("uid".into(), json!(who.uid));
+        os.metadata.insert("gid".into(), json!(who.gid));
+        os.metadata.insert("groups".into(), json!(who.groups));
+        os.metadata
+            .insert("passwordless_sudo".into(), json!(passwordless_sudo));
+        os.metadata
+            .insert("resource_permissions".into(), json!(checks));
+    }
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let Some(os) = state.nodes.iter().find(|n| n.id == "os") else {
+        return issues;
+    };
+    let user = os
+        .metadata
+        .get("user")
+        .and_then(|v| v.as_str())
+        .unwrap_or("the current user");
+    let checks: Vec<&Value> = os
+        .metadata
+        .get("resource_permissions")
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().collect())
+        .unwrap_or_default();
+    let sudo_note = if os
+        .metadata
+        .get("passwordless_sudo")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        "passwordless sudo is available"
+    } else {
+        "sudo will prompt for a password or is unavailable"
+    };
+
+    let mut missing_groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut relogin_groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut root_only: Vec<String> = Vec::new();
+    for check in checks {
+        if check
+            .get("allowed")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        let described = format!(
+            "{} ({}, mode {} {}:{}, needs {})",
+            str_field(check, "path"),
+            str_field(check, "purpose"),
+            str_field(check, "mode"),
+            str_field(check, "owner"),
+            str_field(check, "group"),
+            str_field(check, "access"),
+        );
+        match check.get("needs_group").and_then(|v| v.as_str()) {
+            Some(group)
+                if check
+                    .get("pending_relogin")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false) =>
+            {
+                relogin_groups
+                    .entry(group.into())
+                    .or_default()
+                    .push(described)
+            }
+            Some(group) => missing_groups
+                .entry(group.into())
+                .or_default()
+                .push(described),
+            None => root_only.push(described),
+        }
+    }
+
+    for (group, resources) in missing_groups {
+        issues.push(Issue {
+            code: format!("PERM_{}_GROUP_MISSING", group.to_uppercase()),
+            severity: if group == "docker" {
+                Severity::Critical
+            } else {
+                Severity::Warning
+            },
+            title: format!("{} is not in the {} group", user, group),
+            description: format!(
+                "Without sudo, {} cannot use {}; {}.",
+                user,
+                resources.join(", "),
+                sudo_note
+            ),
+            suggestion: format!(
+                "Run `sudo usermod -aG {} $USER`, then log out and back in.",
+                group
+            ),
+        });
+    }
+    for (group, resources) in relogin_groups {
+        issues.push(Issue {
+            code: "PERM_GROUP_RELOGIN".into(),
+            severity: Severity::Warning,
+            title: format!("{} group membership is not active yet", group),
+            description: format!(
+                "{} was added to {} but this session predates it, so {} still needs sudo.",
+                user,
+                group,
+                resources.join(", ")
+            ),
+            suggestion: format!(
+                "Log out and back in, or run `newgrp {}` in this shell.",
+                group
+            ),
+        });
+    }
+    if !root_only.is_empty() {
+        issues.push(Issue {
+            code: "PERM_SUDO_REQUIRED".into(),
+            severity: Severity::Warning,
+            title: "Some sockets and devices need sudo".into(),
+            description: format!(
+                "No delegating group grants {} access to {}; {}.",
+                user,
+                root_only.join(", "),
+                sudo_note
+            ),
+            suggestion: "Run the affected commands with sudo, or change the owning group and mode (for example `sudo chgrp docker` and `sudo chmod 660`).".into(),
+        });
+    }
+    issues
+}
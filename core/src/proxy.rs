@@ -0,0 +1,556 @@
+use crate::models::{Issue, Node, Severity, Status, SystemState};
+use crate::system_provider::{SystemProvider, TcpProbe};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const DOCKER_DROP_IN_DIR: &str = "/etc/systemd/system/docker.service.d";
+/// Overall budget for resolving and connecting to one registry.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Proxy settings as one tool sees them. Credentials in proxy URLs are
+/// masked before they are recorded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProxySource {
+    /// `environment`, `npm`, `pip`, `docker_daemon`, `docker_client` or `git`.
+    pub tool: String,
+    /// Where the settings were read from.
+    pub location: String,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+impl ProxySource {
+    fn new(tool: &str, location: &str) -> ProxySource {
+        ProxySource {
+            tool: tool.into(),
+            location: location.into(),
+            ..ProxySource::default()
+        }
+    }
+
+    fn proxy_for(&self, scheme: &str) -> Option<&str> {
+        match scheme {
+            "https" => self.https_proxy.as_deref(),
+            _ => self.http_proxy.as_deref(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.http_proxy.is_none() && self.https_proxy.is_none() && self.no_proxy.is_none()
+    }
+}
+
+/// A package registry named in tool configuration and whether it can be
+/// reached from here.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistryCheck {
+    pub tool: String,
+    pub location: String,
+    pub url: String,
+    pub host: String,
+    pub port: u16,
+    /// Requests go through a configured proxy rather than straight out.
+    pub via_proxy: bool,
+    pub resolves: bool,
+    /// Only probed for direct connections; `None` when proxied or
+    /// unresolvable.
+    pub reachable: Option<bool>,
+}
+
+/// Tool, config location and URL of a configured registry.
+type RegistryRef = (String, String, String);
+
+/// Scheme, host and port of a URL; proxies are often written without a
+/// scheme, which curl and pip treat as `http`.
+pub fn split_url(url: &str) -> Option<(String, String, u16)> {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_lowercase(), rest),
+        None => ("http".to_string(), url),
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, hp)| hp);
+    let default_port = if scheme == "https" { 443 } else { 80 };
+    let (host, port) = match host_port.strip_prefix('[') {
+        // Bracketed IPv6 literal, optionally followed by `:port`.
+        Some(v6) => {
+            let (host, rest) = v6.split_once(']')?;
+            let port = match rest.strip_prefix(':') {
+                Some(port) => port.parse().ok()?,
+                None => default_port,
+            };
+            (host, port)
+        }
+        None => match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (host_port, default_port),
+        },
+    };
+    (!host.is_empty()).then(|| (scheme, host.to_lowercase(), port))
+}
+
+/// Masks `user:password@` in a URL.
+pub fn redact_credentials(url: &str) -> String {
+    let (prefix, rest) = url.split_once("://").unwrap_or(("", url));
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    match rest[..authority_end].rsplit_once('@') {
+        Some((_, host)) => {
+            let scheme = if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("{}://", prefix)
+            };
+            format!("{}****@{}{}", scheme, host, &rest[authority_end..])
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Proxies compare equal when they point at the same host and port,
+/// regardless of scheme spelling, credentials or a trailing slash.
+fn same_proxy(a: &str, b: &str) -> bool {
+    match (split_url(a), split_url(b)) {
+        (Some((_, ha, pa)), Some((_, hb, pb))) => ha == hb && pa == pb,
+        _ => a.trim_end_matches('/') == b.trim_end_matches('/'),
+    }
+}
+
+fn no_proxy_entries(no_proxy: &str) -> impl Iterator<Item = &str> {
+    no_proxy
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+}
+
+/// Whether `host` bypasses the proxy under curl-style `NO_PROXY` rules.
+pub fn bypasses_proxy(host: &str, no_proxy: &str) -> bool {
+    no_proxy_entries(no_proxy).any(|entry| {
+        let entry = entry.split(':').next().unwrap_or(entry).to_lowercase();
+        let domain = entry.trim_start_matches('*').trim_start_matches('.');
+        entry == "*" || host == domain || host.ends_with(&format!(".{}", domain))
+    })
+}
+
+fn covers_localhost(no_proxy: &str) -> bool {
+    bypasses_proxy("localhost", no_proxy) && bypasses_proxy("127.0.0.1", no_proxy)
+}
+
+fn home_path<P: SystemProvider>(provider: &P, relative: &str) -> Option<String> {
+    provider
+        .env_var("HOME")
+        .map(|home| format!("{}/{}", home.trim_end_matches('/'), relative))
+}
+
+fn first_env<P: SystemProvider>(provider: &P, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|n| provider.env_var(n))
+        .filter(|v| !v.trim().is_empty())
+}
+
+fn environment<P: SystemProvider>(provider: &P) -> ProxySource {
+    let mut source = ProxySource::new("environment", "environment");
+    source.http_proxy = first_env(provider, &["HTTP_PROXY", "http_proxy"]);
+    source.https_proxy = first_env(provider, &["HTTPS_PROXY", "https_proxy"]);
+    source.no_proxy = first_env(provider, &["NO_PROXY", "no_proxy"]);
+    source
+}
+
+/// `key = value` pairs, skipping comments and INI section headers.
+fn assignments(contents: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    contents.lines().filter_map(|line| {
+        let line = line.trim();
+        if line.starts_with(['#', ';', '[']) {
+            return None;
+        }
+        let (key, value) = line.split_once('=')?;
+        Some((
+            key.trim().to_lowercase(),
+            value.trim().trim_matches('"').to_string(),
+        ))
+    })
+}
+
+fn read_npmrc(location: &str, contents: &str, registries: &mut Vec<RegistryRef>) -> ProxySource {
+    let mut source = ProxySource::new("npm", location);
+    for (key, value) in assignments(contents) {
+        match key.as_str() {
+            "proxy" | "http-proxy" => source.http_proxy = Some(value),
+            "https-proxy" => source.https_proxy = Some(value),
+            "noproxy" => source.no_proxy = Some(value),
+            k if k == "registry" || k.ends_with(":registry") => {
+                registries.push(("npm".into(), location.into(), value))
+            }
+            _ => {}
+        }
+    }
+    source
+}
+
+fn read_pip_conf(location: &str, contents: &str, registries: &mut Vec<RegistryRef>) -> ProxySource {
+    let mut source = ProxySource::new("pip", location);
+    for (key, value) in assignments(contents) {
+        match key.as_str() {
+            // pip sends both schemes through its single proxy setting.
+            "proxy" => {
+                source.http_proxy = Some(value.clone());
+                source.https_proxy = Some(value);
+            }
+            "index-url" | "extra-index-url" => {
+                for url in value.split_whitespace() {
+                    registries.push(("pip".into(), location.into(), url.into()));
+                }
+            }
+            _ => {}
+        }
+    }
+    source
+}
+
+fn read_docker_daemon<P: SystemProvider>(
+    provider: &P,
+    registries: &mut Vec<RegistryRef>,
+) -> Option<ProxySource> {
+    let mut source = ProxySource::new("docker_daemon", "/etc/docker/daemon.json");
+    let mut found = false;
+    if let Some(config) = provider
+        .read_file("/etc/docker/daemon.json")
+        .and_then(|c| serde_json::from_str::<Value>(&c).ok())
+    {
+        found = true;
+        let proxies = config.get("proxies");
+        let field = |key: &str| {
+            proxies
+                .and_then(|p| p.get(key))
+                .and_then(|v| v.as_str())
+                .map(String::from)
+        };
+        source.http_proxy = field("http-proxy");
+        source.https_proxy = field("https-proxy");
+        source.no_proxy = field("no-proxy");
+        for mirror in config
+            .get("registry-mirrors")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+        {
+            registries.push((
+                "docker_daemon".into(),
+                "/etc/docker/daemon.json".into(),
+                mirror.into(),
+            ));
+        }
+    }
+
+    let assignment = Regex::new(r#"(?i)\b(https?_proxy|no_proxy)=([^"\s]+)"#)
+        .expect("regex construction invariant: constant pattern");
+    let mut drop_ins: Vec<String> = provider
+        .list_dir(DOCKER_DROP_IN_DIR)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| f.ends_with(".conf"))
+        .collect();
+    drop_ins.sort();
+    for name in drop_ins {
+        let path = format!("{}/{}", DOCKER_DROP_IN_DIR, name);
+        let Some(contents) = provider.read_file(&path) else {
+            continue;
+        };
+        for line in contents
+            .lines()
+            .filter(|l| l.trim().starts_with("Environment="))
+        {
+            for captures in assignment.captures_iter(line) {
+                let value = Some(captures[2].to_string());
+                let slot = match captures[1].to_lowercase().as_str() {
+                    "http_proxy" => &mut source.http_proxy,
+                    "https_proxy" => &mut source.https_proxy,
+                    _ => &mut source.no_proxy,
+                };
+                if slot.is_none() {
+                    *slot = value;
+                    source.location = path.clone();
+                }
+                found = true;
+            }
+        }
+    }
+    found.then_some(source)
+}
+
+fn read_docker_client(location: &str, contents: &str) -> Option<ProxySource> {
+    let config: Value = serde_json::from_str(contents).ok()?;
+    let proxies = config.get("proxies")?.get("default")?;
+    let field = |key: &str| proxies.get(key).and_then(|v| v.as_str()).map(String::from);
+    let mut source = ProxySource::new("docker_client", location);
+    source.http_proxy = field("httpProxy");
+    source.https_proxy = field("httpsProxy");
+    source.no_proxy = field("noProxy");
+    Some(source)
+}
+
+fn read_git<P: SystemProvider>(provider: &P) -> ProxySource {
+    let get = |key: &str| {
+        provider
+            .command_output("git", &["config", "--get", key])
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let mut source = ProxySource::new("git", "git config");
+    source.http_proxy = get("http.proxy");
+    // git uses http.proxy for https URLs unless https.proxy overrides it.
+    source.https_proxy = get("https.proxy").or_else(|| source.http_proxy.clone());
+    source
+}
+
+/// Resolves the registry host and, for direct connections, opens a TCP
+/// connection to it through the provider, so remote scans probe from the
+/// remote host.
+fn check_registry<P: SystemProvider>(
+    provider: &P,
+    (tool, location, url): RegistryRef,
+    env: &ProxySource,
+) -> Option<RegistryCheck> {
+    let (scheme, host, port) = split_url(&url)?;
+    let via_proxy = env.proxy_for(&scheme).is_some()
+        && !env
+            .no_proxy
+            .as_deref()
+            .is_some_and(|np| bypasses_proxy(&host, np));
+    let probe = provider.probe_tcp(&host, port, !via_proxy, PROBE_TIMEOUT)?;
+    let reachable = match probe {
+        TcpProbe::Reachable => Some(true),
+        TcpProbe::Unreachable => Some(false),
+        TcpProbe::Unresolved | TcpProbe::Resolved => None,
+    };
+    Some(RegistryCheck {
+        tool,
+        location,
+        url: redact_credentials(&url),
+        host,
+        port,
+        via_proxy,
+        resolves: probe != TcpProbe::Unresolved,
+        reachable,
+    })
+}
+
+/// Collects proxy settings from the environment, npm, pip, Docker and git,
+/// and checks that the package registries they configure can be reached.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let env = environment(provider);
+    let mut sources = vec![env.clone()];
+    let mut registries: Vec<RegistryRef> = Vec::new();
+
+    let mut npmrcs: Vec<(String, String)> = Vec::new();
+    if let Some(path) = home_path(provider, ".npmrc") {
+        npmrcs.push(("~/.npmrc".into(), path));
+    }
+    npmrcs.push((".npmrc".into(), ".npmrc".into()));
+    for (location, path) in npmrcs {
+        if let Some(contents) = provider.read_file(&path) {
+            sources.push(read_npmrc(&location, &contents, &mut registries));
+        }
+    }
+
+    let mut pip_confs: Vec<String> = vec!["/etc/pip.conf".into()];
+    pip_confs.extend(home_path(provider, ".config/pip/pip.conf"));
+    pip_confs.extend(home_path(provider, ".pip/pip.conf"));
+    pip_confs.extend(provider.env_var("PIP_CONFIG_FILE"));
+    for path in pip_confs {
+        if let Some(contents) = provider.read_file(&path) {
+            sources.push(read_pip_conf(&path, &contents, &mut registries));
+        }
+    }
+
+    sources.extend(read_docker_daemon(provider, &mut registries));
+    if let Some(path) = home_path(provider, ".docker/config.json") {
+        if let Some(contents) = provider.read_file(&path) {
+            sources.extend(read_docker_client("~/.docker/config.json", &contents));
+        }
+    }
+    sources.push(read_git(provider));
+
+    for source in &mut sources {
+        source.http_proxy = source.http_proxy.as_deref().map(redact_credentials);
+        source.https_proxy = source.https_proxy.as_deref().map(redact_credentials);
+    }
+    sources.retain(|s| s.tool == "environment" || !s.is_empty());
+
+    let checks: Vec<RegistryCheck> = registries
+        .into_iter()
+        .filter_map(|registry| check_registry(provider, registry, &env))
+        .collect();
+
+    if let Some(os) = nodes.iter_mut().find(|n| n.id == "os") {
+        os.metadata.insert("proxy_sources".into(), json!(sources));
+        os.metadata.insert("registries".into(), json!(checks));
+    }
+}
+
+fn describe(source: &ProxySource) -> String {
+    if source.location == source.tool {
+        source.tool.clone()
+    } else {
+        format!("{} ({})", source.tool, source.location)
+    }
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let Some(os) = state.nodes.iter().find(|n| n.id == "os") else {
+        return issues;
+    };
+    let sources: Vec<ProxySource> = os
+        .metadata
+        .get("proxy_sources")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let registries: Vec<RegistryCheck> = os
+        .metadata
+        .get("registries")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let env = sources
+        .iter()
+        .find(|s| s.tool == "environment")
+        .cloned()
+        .unwrap_or_default();
+    let docker_active = state
+        .nodes
+        .iter()
+        .any(|n| n.id == "docker" && n.status == Status::Active);
+
+    let env_proxy = env.http_proxy.as_ref().or(env.https_proxy.as_ref());
+    let mut mismatches = Vec::new();
+    for source in sources.iter().filter(|s| s.tool != "environment") {
+        if env_proxy.is_none() {
+            if let Some(tool) = source.https_proxy.as_ref().or(source.http_proxy.as_ref()) {
+                mismatches.push(format!(
+                    "{} uses {} but the environment sets no proxy",
+                    describe(source),
+                    tool
+                ));
+            }
+            continue;
+        }
+        for scheme in ["http", "https"] {
+            if let (Some(tool), Some(wanted)) = (source.proxy_for(scheme), env.proxy_for(scheme)) {
+                if !same_proxy(tool, wanted) {
+                    mismatches.push(format!(
+                        "{} uses {} for {} but the environment has {}",
+                        describe(source),
+                        tool,
+                        scheme,
+                        wanted
+                    ));
+                }
+            }
+        }
+    }
+    if !mismatches.is_empty() {
+        issues.push(Issue {
+            code: "PROXY_MISMATCH".into(),
+            severity: Severity::Warning,
+            title: "Proxy settings disagree between tools".into(),
+            description: format!("{}.", mismatches.join("; ")),
+            suggestion: "Point every tool at the same proxy as HTTP_PROXY/HTTPS_PROXY, or remove the tool-specific setting so it inherits the environment.".into(),
+        });
+    }
+
+    let daemon = sources.iter().find(|s| s.tool == "docker_daemon");
+    if let Some(proxy) = env_proxy {
+        if docker_active && daemon.is_none_or(|d| d.http_proxy.is_none() && d.https_proxy.is_none())
+        {
+            issues.push(Issue {
+                code: "PROXY_DOCKER_DAEMON_UNSET".into(),
+                severity: Severity::Warning,
+                title: "Docker daemon does not use the proxy".into(),
+                description: format!(
+                    "The environment routes traffic through {} but the Docker daemon does not read shell variables, so image pulls go direct.",
+                    proxy
+                ),
+                suggestion: format!(
+                    "Add `Environment=\"HTTP_PROXY={0}\" \"HTTPS_PROXY={0}\" \"NO_PROXY=localhost,127.0.0.1\"` to {1}/http-proxy.conf, then run `sudo systemctl daemon-reload && sudo systemctl restart docker`.",
+                    proxy, DOCKER_DROP_IN_DIR
+                ),
+            });
+        }
+    }
+
+    let missing_localhost: Vec<String> = sources
+        .iter()
+        .filter(|s| s.http_proxy.is_some() || s.https_proxy.is_some())
+        .filter(|s| {
+            matches!(
+                s.tool.as_str(),
+                "environment" | "docker_daemon" | "docker_client"
+            )
+        })
+        .filter(|s| !s.no_proxy.as_deref().is_some_and(covers_localhost))
+        .map(|s| {
+            format!(
+                "{} (NO_PROXY={})",
+                describe(s),
+                s.no_proxy.as_deref().unwrap_or("unset")
+            )
+        })
+        .collect();
+    if !missing_localhost.is_empty() {
+        issues.push(Issue {
+            code: "PROXY_NO_PROXY_LOCALHOST".into(),
+            severity: Severity::Warning,
+            title: "localhost is sent through the proxy".into(),
+            description: format!(
+                "A proxy is configured but NO_PROXY does not exempt localhost and 127.0.0.1 for {}, so local services and health checks go to the proxy.",
+                missing_localhost.join(", ")
+            ),
+            suggestion: "Add `localhost,127.0.0.1,::1` to NO_PROXY (and to the Docker proxy settings).".into(),
+        });
+    }
+
+    for registry in &registries {
+        let origin = format!(
+            "{} registry {} from {}",
+            registry.tool, registry.url, registry.location
+        );
+        if !registry.resolves {
+            issues.push(Issue {
+                code: "REGISTRY_UNRESOLVABLE".into(),
+                severity: if registry.via_proxy {
+                    Severity::Warning
+                } else {
+                    Severity::Critical
+                },
+                title: format!("Registry host {} does not resolve", registry.host),
+                description: if registry.via_proxy {
+                    format!(
+                        "{} does not resolve locally; installs only work if the proxy resolves it.",
+                        origin
+                    )
+                } else {
+                    format!("{} does not resolve, so installs will fail.", origin)
+                },
+                suggestion: format!(
+                    "Check the hostname in {} and your DNS or VPN connection.",
+                    registry.location
+                ),
+            });
+        } else if registry.reachable == Some(false) {
+            issues.push(Issue {
+                code: "REGISTRY_UNREACHABLE".into(),
+                severity: Severity::Critical,
+                title: format!("Registry {}:{} is unreachable", registry.host, registry.port),
+                description: format!(
+                    "{} resolves but refused or timed out a direct connection on port {}.",
+                    origin, registry.port
+                ),
+                suggestion: "Check that the registry is up, or configure HTTP_PROXY/HTTPS_PROXY if it is only reachable through a proxy.".into(),
+            });
+        }
+    }
+    issues
+}
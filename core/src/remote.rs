@@ -3,8 +3,9 @@ use crate::models::SystemState;
 use crate::oracle;
 use crate::scanner;
 use crate::schema;
-use crate::system_provider::SystemProvider;
+use crate::system_provider::{ProcessInfo, SystemProvider, TcpProbe};
 use crate::utils;
+use std::collections::HashMap;
 use std::process::Command;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -44,6 +45,70 @@ impl SshSystemProvider {
     }
 }
 
+/// Process table, executable links and listening sockets in one round trip.
+/// Command lines are not collected, matching the local provider.
+const PROCESS_SCRIPT: &str = "ps -eo pid=,ppid=,user=,comm=; echo '--exe--'; \
+    for p in /proc/[0-9]*; do e=$(readlink \"$p/exe\" 2>/dev/null) && echo \"${p#/proc/} $e\"; done; \
+    echo '--ss--'; ss -ltnpH 2>/dev/null || true";
+
+/// Parses the output of [`PROCESS_SCRIPT`]. Sockets owned by other users
+/// only show up when the remote login is privileged.
+fn parse_processes(output: &str) -> Vec<ProcessInfo> {
+    let (table, rest) = output.split_once("--exe--").unwrap_or((output, ""));
+    let (exes, sockets) = rest.split_once("--ss--").unwrap_or((rest, ""));
+
+    let exes: HashMap<u32, String> = exes
+        .lines()
+        .filter_map(|line| {
+            let (pid, exe) = line.trim().split_once(' ')?;
+            Some((pid.parse().ok()?, exe.trim().to_string()))
+        })
+        .collect();
+    let mut ports: HashMap<u32, Vec<u16>> = HashMap::new();
+    for line in sockets.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let Some(port) = fields
+            .get(3)
+            .and_then(|local| local.rsplit(':').next())
+            .and_then(|p| p.parse::<u16>().ok())
+        else {
+            continue;
+        };
+        for owner in line.split("pid=").skip(1) {
+            let digits: String = owner.chars().take_while(|c| c.is_ascii_digit()).collect();
+            if let Ok(pid) = digits.parse() {
+                let held = ports.entry(pid).or_default();
+                if !held.contains(&port) {
+                    held.push(port);
+                }
+            }
+        }
+    }
+
+    let mut processes: Vec<ProcessInfo> = table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid: u32 = fields.next()?.parse().ok()?;
+            let parent_pid = fields.next()?.parse().ok().filter(|p| *p != 0);
+            let user = fields.next()?.to_string();
+            let name = fields.collect::<Vec<_>>().join(" ");
+            let mut listening_ports = ports.remove(&pid).unwrap_or_default();
+            listening_ports.sort_unstable();
+            Some(ProcessInfo {
+                pid,
+                parent_pid,
+                name,
+                exe: exes.get(&pid).cloned(),
+                user: Some(user),
+                listening_ports,
+            })
+        })
+        .collect();
+    processes.sort_by_key(|p| p.pid);
+    processes
+}
+
 impl SystemProvider for SshSystemProvider {
     fn file_exists(&self, path: &str) -> bool {
         self.ssh_command(&format!("test -e '{}' && echo ok", path))
@@ -78,6 +143,66 @@ impl SystemProvider for SshSystemProvider {
                     .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
             })
     }
+
+    fn disk_available(&self, path: &str) -> Option<u64> {
+        self.ssh_command(&format!("df -Pk '{}'", path))
+            .and_then(|out| {
+                out.lines()
+                    .nth(1)
+                    .and_then(|l| l.split_whitespace().nth(3))
+                    .and_then(|kb| kb.parse::<u64>().ok())
+                    .map(|kb| kb * 1024)
+            })
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.ssh_command(&format!("printenv {}", name))
+    }
+
+    fn resolve_path(&self, path: &str) -> Option<String> {
+        self.ssh_command(&format!("readlink -f '{}'", path))
+    }
+
+    fn processes(&self) -> Vec<ProcessInfo> {
+        self.ssh_command(PROCESS_SCRIPT)
+            .map(|out| parse_processes(&out))
+            .unwrap_or_default()
+    }
+
+    fn probe_tcp(
+        &self,
+        host: &str,
+        port: u16,
+        connect: bool,
+        timeout: Duration,
+    ) -> Option<TcpProbe> {
+        // The host is spliced into a shell command, so only plain names and
+        // addresses are probed.
+        let safe = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '_');
+        if host.is_empty() || !host.chars().all(safe) {
+            return None;
+        }
+        let secs = timeout.as_secs().max(1);
+        let probe = if connect {
+            format!(
+                "timeout {} bash -c '</dev/tcp/{}/{}' 2>/dev/null && echo reachable || echo unreachable",
+                secs, host, port
+            )
+        } else {
+            "echo resolved".to_string()
+        };
+        let script = format!(
+            "if timeout {} getent ahosts {} >/dev/null; then {}; else echo unresolved; fi",
+            secs, host, probe
+        );
+        match self.ssh_command(&script)?.trim() {
+            "unresolved" => Some(TcpProbe::Unresolved),
+            "resolved" => Some(TcpProbe::Resolved),
+            "reachable" => Some(TcpProbe::Reachable),
+            "unreachable" => Some(TcpProbe::Unreachable),
+            _ => None,
+        }
+    }
 }
 
 pub fn remote_scan(remote: &str) -> Result<SystemState, String> {
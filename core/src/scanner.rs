@@ -1,9 +1,31 @@
+use crate::advisories;
+use crate::applications;
+use crate::build_prereqs;
+use crate::connections;
+use crate::containers;
+use crate::cpu_features;
+use crate::cuda_compat;
+use crate::environment;
+use crate::git_state;
+use crate::gpu;
+use crate::infra;
+use crate::kernel_tuning;
 use crate::models::{Node, NodeType, Status, SystemState, DETERMINISTIC_TIMESTAMP};
-use crate::system_provider::{RealSystemProvider, SystemProvider};
+use crate::node_abi;
+use crate::path_scan;
+use crate::permissions;
+use crate::proxy;
+use crate::secrets;
+use crate::security;
+use crate::system_provider::{ProcessInfo, RealSystemProvider, SystemProvider};
+use crate::systemd;
+use crate::tls;
+use crate::version_pins;
+use crate::webserver;
 use semver::{Version, VersionReq};
 use serde_json::json;
 use serde_json::Value;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 
 fn version_satisfies(requirement: &str, actual: &str) -> bool {
@@ -45,7 +67,7 @@ fn parse_requirement_line(line: &str) -> Option<(String, String)> {
     Some((trimmed.to_lowercase(), "*".into()))
 }
 
-fn check_port<P: SystemProvider>(provider: &P, port: u16) -> Status {
+pub fn check_port<P: SystemProvider>(provider: &P, port: u16) -> Status {
     let probe = format!(
         "ss -ltn sport = :{0} || (netstat -ltn 2>/dev/null | grep :{0})",
         port
@@ -61,6 +83,24 @@ fn check_port<P: SystemProvider>(provider: &P, port: u16) -> Status {
     }
 }
 
+/// Installed pip packages, lower-cased, from the first `pip freeze` that runs.
+pub fn pip_packages<P: SystemProvider>(provider: &P) -> BTreeMap<String, String> {
+    let pip_freeze = provider
+        .command_output("python", &["-m", "pip", "freeze"])
+        .or_else(|| provider.command_output("python3", &["-m", "pip", "freeze"]))
+        .or_else(|| provider.command_output("pip", &["freeze"]))
+        .or_else(|| provider.command_output("pip3", &["freeze"]));
+    let mut installed = BTreeMap::new();
+    if let Some(output) = pip_freeze {
+        for line in output.lines() {
+            if let Some((name, ver)) = line.split_once("==") {
+                installed.insert(name.trim().to_lowercase(), ver.trim().to_string());
+            }
+        }
+    }
+    installed
+}
+
 fn detect_python<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     let version = provider.command_output("python", &["--version"]);
     let version3 = provider.command_output("python3", &["--version"]);
@@ -82,19 +122,7 @@ fn detect_python<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
         }
     }
 
-    let pip_freeze = provider
-        .command_output("python", &["-m", "pip", "freeze"])
-        .or_else(|| provider.command_output("python3", &["-m", "pip", "freeze"]))
-        .or_else(|| provider.command_output("pip", &["freeze"]))
-        .or_else(|| provider.command_output("pip3", &["freeze"]));
-    let mut installed = BTreeMap::new();
-    if let Some(output) = pip_freeze {
-        for line in output.lines() {
-            if let Some((name, ver)) = line.split_once("==") {
-                installed.insert(name.trim().to_lowercase(), ver.trim().to_string());
-            }
-        }
-    }
+    let installed = pip_packages(provider);
 
     let mut missing_packages: Vec<Value> = Vec::new();
     let mut version_drifts: Vec<Value> = Vec::new();
@@ -160,6 +188,10 @@ fn detect_python<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     );
     metadata.insert("python_requirements_drift".into(), json!(version_drifts));
     metadata.insert("python_lockfile_drift".into(), json!(lockfile_drift));
+    metadata.insert(
+        "ml_frameworks".into(),
+        json!(cuda_compat::framework_versions(&installed)),
+    );
 
     let status = if version.is_some() {
         Status::Active
@@ -223,6 +255,13 @@ fn detect_docker<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
             metadata.insert("docker_api_version".into(), json!(api));
         }
     }
+    if let Some(engine) =
+        provider.command_output("docker", &["version", "--format", "{{.Server.Version}}"])
+    {
+        if !engine.is_empty() {
+            metadata.insert("docker_engine_version".into(), json!(engine));
+        }
+    }
 
     let status = if docker_ok {
         Status::Active
@@ -340,16 +379,48 @@ fn detect_nodejs<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     });
 }
 
+/// Records what the process table says about a service: how many top-level
+/// instances run one of `executables`, their paths, users and listening
+/// ports. PIDs, command lines and the raw process count (one backend per
+/// client connection) are left out so the facts stay stable between scans
+/// and never carry passwords.
+fn record_processes<P: SystemProvider>(
+    provider: &P,
+    executables: &[&str],
+    metadata: &mut BTreeMap<String, Value>,
+) {
+    let matched: Vec<ProcessInfo> = provider
+        .processes()
+        .into_iter()
+        .filter(|p| p.runs_any(executables))
+        .collect();
+    // Worker processes are forked from the server, so an instance is a
+    // matching process whose parent is not itself a match.
+    let instances = matched
+        .iter()
+        .filter(|p| {
+            p.parent_pid
+                .is_none_or(|parent| !matched.iter().any(|m| m.pid == parent))
+        })
+        .count();
+    let exes: BTreeSet<&str> = matched
+        .iter()
+        .map(|p| p.exe.as_deref().unwrap_or(p.name.as_str()))
+        .collect();
+    let users: BTreeSet<&str> = matched.iter().filter_map(|p| p.user.as_deref()).collect();
+    let ports: BTreeSet<u16> = matched
+        .iter()
+        .flat_map(|p| p.listening_ports.iter().copied())
+        .collect();
+    metadata.insert("instance_count".into(), json!(instances));
+    metadata.insert("executables".into(), json!(exes));
+    metadata.insert("process_users".into(), json!(users));
+    metadata.insert("listening_ports".into(), json!(ports));
+}
+
 fn detect_postgres<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     let port_status = check_port(provider, 5432);
     let version = provider.command_output("psql", &["--version"]);
-    let pg_processes = provider
-        .command_output("ps", &["aux"])
-        .unwrap_or_default()
-        .lines()
-        .filter(|l| l.contains("postgres"))
-        .map(|l| l.to_string())
-        .collect::<Vec<_>>();
     let versions = provider.list_dir("/usr/lib/postgresql").unwrap_or_default();
     let mut metadata = BTreeMap::new();
     metadata.insert("port".into(), json!(5432));
@@ -360,7 +431,7 @@ fn detect_postgres<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     if let Some(v) = &version {
         metadata.insert("version".into(), json!(v));
     }
-    metadata.insert("processes".into(), json!(pg_processes));
+    record_processes(provider, &["postgres", "postmaster"], &mut metadata);
     metadata.insert("installed_versions".into(), json!(versions));
 
     let status = if version.is_some() || matches!(port_status, Status::Active) {
@@ -383,13 +454,6 @@ fn detect_mysql<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     let version = provider
         .command_output("mysql", &["--version"])
         .or_else(|| provider.command_output("mysqld", &["--version"]));
-    let processes = provider
-        .command_output("ps", &["aux"])
-        .unwrap_or_default()
-        .lines()
-        .filter(|l| l.to_lowercase().contains("mysql"))
-        .map(|l| l.to_string())
-        .collect::<Vec<_>>();
     let mut metadata = BTreeMap::new();
     metadata.insert("port".into(), json!(3306));
     metadata.insert(
@@ -399,7 +463,11 @@ fn detect_mysql<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     if let Some(v) = &version {
         metadata.insert("version".into(), json!(v));
     }
-    metadata.insert("processes".into(), json!(processes));
+    record_processes(
+        provider,
+        &["mysqld", "mariadbd", "mysqld_safe"],
+        &mut metadata,
+    );
 
     let status = if version.is_some() || matches!(port_status, Status::Active) {
         Status::Active
@@ -462,90 +530,149 @@ fn detect_redis<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     });
 }
 
-fn detect_gpu<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
-    let gpu_info = provider.command_output("nvidia-smi", &[]);
-    let lspci_output = provider.command_output("lspci", &[]);
-    let cuda_version = provider.command_output("nvcc", &["--version"]);
-    let cudnn_version = [
-        "/usr/include/cudnn_version.h",
-        "/usr/local/cuda/include/cudnn_version.h",
-    ]
-    .iter()
-    .find_map(|p| {
-        provider.read_file(p).and_then(|contents| {
-            contents
-                .lines()
-                .find(|l| l.contains("CUDNN_MAJOR"))
-                .and_then(|major| {
-                    let major_num = major.split_whitespace().last()?.to_string();
-                    let minor = contents
-                        .lines()
-                        .find(|l| l.contains("CUDNN_MINOR"))
-                        .and_then(|l| l.split_whitespace().last())
-                        .unwrap_or("0");
-                    let patch = contents
-                        .lines()
-                        .find(|l| l.contains("CUDNN_PATCHLEVEL"))
-                        .and_then(|l| l.split_whitespace().last())
-                        .unwrap_or("0");
-                    Some(format!("{}.{}.{}", major_num, minor, patch))
-                })
+/// Adds Go and Java runtime nodes for toolchains on PATH so EOL checks do not
+/// depend on a project pinning them. `java -version` writes to stderr.
+fn detect_toolchains<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let toolchains = [
+        ("go", "Go", "go", &["version"]),
+        ("java", "Java", "java", &["-version"]),
+    ];
+    for (id, label, command, args) in toolchains {
+        let Some(output) = provider.command_output(command, args) else {
+            continue;
+        };
+        let Some(version) = output.lines().map(str::trim).find(|l| !l.is_empty()) else {
+            continue;
+        };
+        let mut metadata = BTreeMap::new();
+        metadata.insert("version".into(), json!(version));
+        nodes.push(Node {
+            id: id.into(),
+            node_type: NodeType::Runtime,
+            label: label.into(),
+            status: Status::Active,
+            metadata,
+        });
+    }
+}
+
+fn parse_os_release(contents: &str) -> BTreeMap<String, String> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            Some((
+                key.trim().to_string(),
+                value
+                    .trim()
+                    .trim_matches('"')
+                    .trim_matches('\'')
+                    .to_string(),
+            ))
         })
-    });
+        .collect()
+}
+
+fn bytes_to_mb(bytes: u64) -> u64 {
+    bytes / (1024 * 1024)
+}
+
+/// Granularity for fluctuating memory and disk readings, so that a rescan of an
+/// unchanged host keeps the same fingerprint.
+const VOLATILE_BUCKET_MB: u64 = 512;
+
+fn bucketed_mb(bytes: u64) -> u64 {
+    bytes_to_mb(bytes) / VOLATILE_BUCKET_MB * VOLATILE_BUCKET_MB
+}
+
+fn detect_host<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
     let mut metadata = BTreeMap::new();
-    if let Some(info) = &gpu_info {
-        metadata.insert("nvidia_smi".into(), json!(info));
+    let resources = provider.host_resources();
+
+    let kernel = resources
+        .as_ref()
+        .and_then(|r| r.kernel_version.clone())
+        .or_else(|| provider.command_output("uname", &["-r"]));
+    let arch = resources
+        .as_ref()
+        .and_then(|r| r.arch.clone())
+        .or_else(|| provider.command_output("uname", &["-m"]));
+    if let Some(k) = &kernel {
+        metadata.insert("kernel_version".into(), json!(k));
     }
-    if let Some(info) = &lspci_output {
-        let lower = info.to_lowercase();
-        metadata.insert("amd_gpu_detected".into(), json!(lower.contains("amd")));
-        let intel_graphics = lower.contains("intel") && lower.contains("graphics");
-        metadata.insert("intel_gpu_detected".into(), json!(intel_graphics));
+    if let Some(a) = &arch {
+        metadata.insert("arch".into(), json!(a));
     }
-    if let Some(cuda) = &cuda_version {
-        metadata.insert("cuda_version".into(), json!(cuda));
+
+    if let Some(contents) = provider.read_file("/etc/os-release") {
+        let release = parse_os_release(&contents);
+        if let Some(name) = release.get("PRETTY_NAME").or_else(|| release.get("NAME")) {
+            metadata.insert("distro".into(), json!(name));
+        }
+        if let Some(id) = release.get("ID") {
+            metadata.insert("distro_id".into(), json!(id));
+        }
+        if let Some(version) = release.get("VERSION_ID") {
+            metadata.insert("distro_version".into(), json!(version));
+        }
     }
-    if let Some(cudnn) = &cudnn_version {
-        metadata.insert("cudnn_version".into(), json!(cudnn));
+
+    if let Some(r) = &resources {
+        metadata.insert("cpu_count".into(), json!(r.cpu_count));
+        metadata.insert("memory_total_mb".into(), json!(bytes_to_mb(r.total_memory)));
+        metadata.insert(
+            "memory_available_mb".into(),
+            json!(bucketed_mb(r.available_memory)),
+        );
+        metadata.insert("swap_total_mb".into(), json!(bytes_to_mb(r.total_swap)));
+        metadata.insert("swap_free_mb".into(), json!(bucketed_mb(r.free_swap)));
+        // Half-step load buckets keep idle jitter out of the fingerprint.
+        let load: Vec<f64> = r
+            .load_average
+            .iter()
+            .map(|l| (l * 2.0).round() / 2.0)
+            .collect();
+        metadata.insert("load_average".into(), json!(load));
     }
 
-    let amd_detected = metadata
-        .get("amd_gpu_detected")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let intel_detected = metadata
-        .get("intel_gpu_detected")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
+    if let Some(free) = provider.disk_available(".") {
+        metadata.insert("project_disk_free_mb".into(), json!(bucketed_mb(free)));
+    }
+    if let Some(free) = provider.disk_available("/tmp") {
+        metadata.insert("tmp_disk_free_mb".into(), json!(bucketed_mb(free)));
+    }
 
-    let status = if gpu_info.is_some() || amd_detected || intel_detected {
-        Status::Active
-    } else {
-        Status::Inactive
-    };
+    nodes.push(Node {
+        id: "os".into(),
+        node_type: NodeType::Os,
+        label: std::env::consts::OS.into(),
+        status: Status::Active,
+        metadata,
+    });
+}
+
+/// Adds a `port{N}` node for `port` unless the scan already has one.
+pub fn ensure_port_node<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>, port: u16) {
+    let id = format!("port{}", port);
+    if nodes.iter().any(|n| n.id == id) {
+        return;
+    }
+    let mut metadata = BTreeMap::new();
+    metadata.insert("protocol".into(), json!("tcp"));
+    metadata.insert("port".into(), json!(port));
 
     nodes.push(Node {
-        id: "gpu".into(),
-        node_type: NodeType::Gpu,
-        label: "GPU".into(),
-        status,
+        id,
+        node_type: NodeType::Port,
+        label: format!("Port {}", port),
+        status: check_port(provider, port),
         metadata,
     });
 }
 
 fn detect_ports<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
-    for port in [3000u16, 5173, 8000, 8080].iter() {
-        let mut metadata = BTreeMap::new();
-        metadata.insert("protocol".into(), json!("tcp"));
-        metadata.insert("port".into(), json!(port));
-
-        nodes.push(Node {
-            id: format!("port{}", port),
-            node_type: NodeType::Port,
-            label: format!("Port {}", port),
-            status: check_port(provider, *port),
-            metadata,
-        });
+    for port in [3000u16, 5173, 8000, 8080] {
+        ensure_port_node(provider, nodes, port);
     }
 }
 
@@ -556,22 +683,38 @@ pub fn perform_scan() -> SystemState {
 
 pub fn perform_scan_with_provider<P: SystemProvider>(provider: &P) -> SystemState {
     let timestamp = DETERMINISTIC_TIMESTAMP.to_string();
-    let mut nodes = vec![Node {
-        id: "os".into(),
-        node_type: NodeType::Os,
-        label: std::env::consts::OS.into(),
-        status: Status::Active,
-        metadata: BTreeMap::new(),
-    }];
+    let mut nodes = Vec::new();
 
+    detect_host(provider, &mut nodes);
     detect_docker(provider, &mut nodes);
     detect_python(provider, &mut nodes);
     detect_nodejs(provider, &mut nodes);
     detect_postgres(provider, &mut nodes);
     detect_mysql(provider, &mut nodes);
     detect_redis(provider, &mut nodes);
-    detect_gpu(provider, &mut nodes);
+    detect_toolchains(provider, &mut nodes);
+    infra::detect(provider, &mut nodes);
+    environment::detect(provider, &mut nodes);
+    systemd::detect(provider, &mut nodes);
+    gpu::detect(provider, &mut nodes);
+    cpu_features::detect(provider, &mut nodes);
     detect_ports(provider, &mut nodes);
+    webserver::detect(provider, &mut nodes);
+    kernel_tuning::detect(provider, &mut nodes);
+    git_state::detect(provider, &mut nodes);
+    path_scan::detect(provider, &mut nodes);
+    version_pins::detect(provider, &mut nodes);
+    containers::detect(provider, &mut nodes);
+    applications::detect(provider, &mut nodes);
+    connections::detect(provider, &mut nodes);
+    build_prereqs::detect(provider, &mut nodes);
+    node_abi::detect(provider, &mut nodes);
+    advisories::detect(provider, &mut nodes);
+    security::detect(provider, &mut nodes);
+    secrets::detect(provider, &mut nodes);
+    permissions::detect(provider, &mut nodes);
+    proxy::detect(provider, &mut nodes);
+    tls::detect(provider, &mut nodes);
 
     SystemState::new(nodes, Vec::new(), Vec::new(), timestamp)
 }
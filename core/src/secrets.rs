@@ -0,0 +1,255 @@
+use crate::models::{Issue, Node, Severity, SystemState};
+use crate::system_provider::SystemProvider;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Provider-issued credentials recognisable by their format.
+const PROVIDER_PATTERNS: &[(&str, &str)] = &[
+    ("aws_access_key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "github_token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})",
+    ),
+    ("npm_token", r"\bnpm_[A-Za-z0-9]{36}\b"),
+    ("pypi_token", r"\bpypi-[A-Za-z0-9_\-]{50,}"),
+    ("slack_token", r"\bxox[abposr]-[A-Za-z0-9\-]{10,}"),
+    ("private_key", r"-----BEGIN (?:[A-Z0-9]+ )*PRIVATE KEY-----"),
+];
+
+/// Assignments whose key names a credential; the value is reported when it
+/// looks random enough to be real rather than a placeholder.
+const CREDENTIAL_ASSIGNMENT: &str = r#"(?i)([A-Za-z0-9_.\-]*(?:secret|token|passw(?:or)?d|api[_\-]?key|auth)[A-Za-z0-9_.\-]*)\s*[:=]\s*["']?([^\s"'#]+)"#;
+
+const MIN_SECRET_LENGTH: usize = 16;
+const MIN_ENTROPY_BITS: f64 = 3.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SecretMatch {
+    pub line: usize,
+    pub kind: String,
+    /// The matching line with the secret value masked.
+    pub evidence: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SecretFile {
+    pub path: String,
+    pub matches: Vec<SecretMatch>,
+    pub in_repo: bool,
+    pub tracked: bool,
+    pub ignored: bool,
+    pub world_readable: bool,
+}
+
+fn is_candidate(name: &str) -> bool {
+    let compose = (name.starts_with("docker-compose") || name.starts_with("compose"))
+        && (name.ends_with(".yml") || name.ends_with(".yaml"));
+    name.starts_with(".env") || compose || matches!(name, ".npmrc" | ".pypirc" | "pip.conf")
+}
+
+/// Shannon entropy in bits per character.
+fn entropy(value: &str) -> f64 {
+    let mut counts = std::collections::BTreeMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    let len = value.chars().count() as f64;
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Keeps the first four characters so a finding can be matched to the
+/// credential it came from without revealing it.
+pub fn redact(secret: &str) -> String {
+    let prefix: String = secret.chars().take(4).collect();
+    format!("{}**** ({} chars)", prefix, secret.chars().count())
+}
+
+/// Secrets on each line of `contents`, with the values masked.
+pub fn find_secrets(contents: &str) -> Vec<SecretMatch> {
+    let providers: Vec<(&str, Regex)> = PROVIDER_PATTERNS
+        .iter()
+        .map(|(kind, pattern)| {
+            (
+                *kind,
+                Regex::new(pattern).expect("regex construction invariant: constant pattern"),
+            )
+        })
+        .collect();
+    let assignment =
+        Regex::new(CREDENTIAL_ASSIGNMENT).expect("regex construction invariant: constant pattern");
+
+    let mut matches = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        let mut hits: Vec<(&str, &str)> = providers
+            .iter()
+            .flat_map(|(kind, re)| re.find_iter(trimmed).map(|m| (*kind, m.as_str())))
+            .collect();
+        for captures in assignment.captures_iter(trimmed) {
+            let Some(value) = captures.get(2).map(|m| m.as_str()) else {
+                continue;
+            };
+            let placeholder = value.starts_with('$') || value.starts_with('<');
+            let covered = hits.iter().any(|(_, secret)| value.contains(secret));
+            if !placeholder
+                && !covered
+                && value.chars().count() >= MIN_SECRET_LENGTH
+                && entropy(value) >= MIN_ENTROPY_BITS
+            {
+                hits.push(("high_entropy", value));
+            }
+        }
+        let Some(&(kind, _)) = hits.first() else {
+            continue;
+        };
+        // Key material can follow the marker on the same line (an escaped
+        // PEM in an env file), so only the marker is kept.
+        let evidence = match hits.iter().find(|(kind, _)| *kind == "private_key") {
+            Some((_, marker)) => {
+                let rest = trimmed.chars().count() - marker.chars().count();
+                format!("{} **** ({} chars)", marker, rest)
+            }
+            None => hits.iter().fold(trimmed.to_string(), |line, (_, secret)| {
+                line.replace(secret, &redact(secret))
+            }),
+        };
+        matches.push(SecretMatch {
+            line: index + 1,
+            kind: kind.to_string(),
+            evidence,
+        });
+    }
+    matches
+}
+
+fn git_reports<P: SystemProvider>(provider: &P, args: &[&str]) -> bool {
+    provider
+        .command_output("git", args)
+        .map(|out| !out.trim().is_empty())
+        .unwrap_or(false)
+}
+
+/// Scans project configuration files for credentials and records how each
+/// file holding one is exposed.
+pub fn scan<P: SystemProvider>(provider: &P) -> Vec<SecretFile> {
+    let mut candidates: Vec<String> = provider
+        .list_dir(".")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| is_candidate(name))
+        .collect();
+    candidates.sort();
+    let in_repo = provider
+        .command_output("git", &["rev-parse", "--is-inside-work-tree"])
+        .map(|out| out.trim() == "true")
+        .unwrap_or(false);
+
+    let mut files = Vec::new();
+    for path in candidates {
+        let Some(contents) = provider.read_file(&path) else {
+            continue;
+        };
+        let matches = find_secrets(&contents);
+        if matches.is_empty() {
+            continue;
+        }
+        let tracked = in_repo && git_reports(provider, &["ls-files", "--", &path]);
+        let ignored = in_repo && git_reports(provider, &["check-ignore", "--no-index", &path]);
+        let world_readable = provider
+            .command_output("stat", &["-L", "-c", "%a", &path])
+            .and_then(|m| u32::from_str_radix(m.trim(), 8).ok())
+            .map(|mode| mode & 0o004 != 0)
+            .unwrap_or(false);
+        files.push(SecretFile {
+            path,
+            matches,
+            in_repo,
+            tracked,
+            ignored,
+            world_readable,
+        });
+    }
+    files
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let files = scan(provider);
+    if let Some(git) = nodes.iter_mut().find(|n| n.id == "git") {
+        git.metadata.insert("secret_files".into(), json!(files));
+    }
+}
+
+fn evidence(file: &SecretFile) -> String {
+    file.matches
+        .iter()
+        .map(|m| format!("{}:{}: {} [{}]", file.path, m.line, m.evidence, m.kind))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub fn issues(files: &[SecretFile]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for file in files {
+        let count = file.matches.len();
+        if file.tracked && !file.ignored {
+            issues.push(Issue {
+                code: "SEC_SECRET_COMMITTED".into(),
+                severity: Severity::Critical,
+                title: format!("Secrets committed to git in {}", file.path),
+                description: format!(
+                    "{} is tracked by git and holds {} secret(s): {}",
+                    file.path,
+                    count,
+                    evidence(file)
+                ),
+                suggestion: format!(
+                    "Rotate the exposed credentials, run `git rm --cached {}`, add it to .gitignore, and purge it from history.",
+                    file.path
+                ),
+            });
+        } else if file.in_repo && !file.tracked && !file.ignored {
+            issues.push(Issue {
+                code: "SEC_SECRET_UNIGNORED".into(),
+                severity: Severity::Warning,
+                title: format!("{} holds secrets but is not ignored by git", file.path),
+                description: format!(
+                    "{} is untracked and not covered by .gitignore, so it can be committed by accident: {}",
+                    file.path,
+                    evidence(file)
+                ),
+                suggestion: format!("Add {} to .gitignore.", file.path),
+            });
+        }
+        if file.world_readable {
+            issues.push(Issue {
+                code: "SEC_SECRET_WORLD_READABLE".into(),
+                severity: Severity::Warning,
+                title: format!("{} holds secrets and is world-readable", file.path),
+                description: format!("Any local user can read {}: {}", file.path, evidence(file)),
+                suggestion: format!("Run `chmod 600 {}`.", file.path),
+            });
+        }
+    }
+    issues
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let files: Vec<SecretFile> = state
+        .nodes
+        .iter()
+        .find(|n| n.id == "git")
+        .and_then(|n| n.metadata.get("secret_files"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    issues(&files)
+}
@@ -1,8 +1,451 @@
 use crate::fix;
-use crate::models::Issue;
+use crate::models::{Issue, Node, NodeType, Severity, Status, SystemState};
+use crate::secrets::{self, SecretFile};
+use crate::system_provider::{RealSystemProvider, SystemProvider};
 use crate::utils::json_envelope;
 use serde::Serialize;
 use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A hardening check: the node its findings are recorded on and the issue
+/// raised for them.
+struct HardeningCheck {
+    check: &'static str,
+    node_id: &'static str,
+    code: &'static str,
+    severity: Severity,
+    title: &'static str,
+    suggestion: &'static str,
+}
+
+const CHECKS: &[HardeningCheck] = &[
+    HardeningCheck {
+        check: "redis_public_no_auth",
+        node_id: "redis",
+        code: "SEC_REDIS_NO_AUTH",
+        severity: Severity::Critical,
+        title: "Redis accepts unauthenticated connections on all interfaces",
+        suggestion: "Bind Redis to 127.0.0.1 or set `requirepass` (or ACL users) in redis.conf, then restart redis-server.",
+    },
+    HardeningCheck {
+        check: "postgres_trust_remote",
+        node_id: "postgres",
+        code: "SEC_POSTGRES_TRUST_REMOTE",
+        severity: Severity::Critical,
+        title: "pg_hba.conf trusts network clients without a password",
+        suggestion: "Replace `trust` with `scram-sha-256` in pg_hba.conf and reload PostgreSQL.",
+    },
+    HardeningCheck {
+        check: "postgres_trust_local",
+        node_id: "postgres",
+        code: "SEC_POSTGRES_TRUST_LOCAL",
+        severity: Severity::Warning,
+        title: "pg_hba.conf trusts local connections without a password",
+        suggestion: "Replace `trust` with `peer` for local sockets or `scram-sha-256` for loopback hosts, then reload PostgreSQL.",
+    },
+    HardeningCheck {
+        check: "mysql_skip_grant_tables",
+        node_id: "mysql",
+        code: "SEC_MYSQL_SKIP_GRANT_TABLES",
+        severity: Severity::Critical,
+        title: "MySQL runs with skip-grant-tables",
+        suggestion: "Remove `skip-grant-tables` from the MySQL configuration and restart mysqld.",
+    },
+    HardeningCheck {
+        check: "docker_socket_world_writable",
+        node_id: "docker",
+        code: "SEC_DOCKER_SOCKET_WORLD_WRITABLE",
+        severity: Severity::Critical,
+        title: "Docker socket is world-writable",
+        suggestion: "Restrict the socket with `sudo chmod 660 /var/run/docker.sock` and grant access through the docker group.",
+    },
+    HardeningCheck {
+        check: "docker_tcp_no_tls",
+        node_id: "docker",
+        code: "SEC_DOCKER_TCP_NO_TLS",
+        severity: Severity::Critical,
+        title: "Docker daemon listens on TCP without TLS",
+        suggestion: "Listen only on unix:///var/run/docker.sock, or enable `tlsverify` with client certificates for the TCP socket.",
+    },
+    HardeningCheck {
+        check: "elasticsearch_open",
+        node_id: "elasticsearch",
+        code: "SEC_ELASTICSEARCH_OPEN",
+        severity: Severity::Critical,
+        title: "Elasticsearch security is disabled on a public interface",
+        suggestion: "Set `xpack.security.enabled: true` or bind `network.host` to localhost in elasticsearch.yml.",
+    },
+];
+
+const PG_HBA_PATHS: &[&str] = &[
+    "/var/lib/pgsql/data/pg_hba.conf",
+    "/var/lib/postgresql/data/pg_hba.conf",
+    "/usr/local/var/postgres/pg_hba.conf",
+];
+
+const MYSQL_CONFIG_PATHS: &[&str] = &[
+    "/etc/my.cnf",
+    "/etc/mysql/my.cnf",
+    "/etc/mysql/mysql.conf.d/mysqld.cnf",
+    "/etc/mysql/mariadb.conf.d/50-server.cnf",
+    "/usr/local/etc/my.cnf",
+];
+
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+const DOCKER_UNIT_PATHS: &[&str] = &[
+    "/lib/systemd/system/docker.service",
+    "/usr/lib/systemd/system/docker.service",
+    "/etc/systemd/system/docker.service",
+];
+
+const ELASTICSEARCH_CONFIG_PATHS: &[&str] = &[
+    "/etc/elasticsearch/elasticsearch.yml",
+    "/usr/share/elasticsearch/config/elasticsearch.yml",
+    "/usr/local/etc/elasticsearch/elasticsearch.yml",
+];
+
+const LOOPBACK: &[&str] = &[
+    "127.0.0.1",
+    "::1",
+    "localhost",
+    "_local_",
+    "127.0.0.1/32",
+    "::1/128",
+    "samehost",
+];
+
+/// Non-comment lines with their 1-based line numbers.
+fn config_lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+}
+
+fn evidence(path: &str, line_no: usize, line: &str) -> String {
+    format!("{}:{}: {}", path, line_no, line)
+}
+
+fn finding(check: &str, evidence: Vec<String>) -> Value {
+    json!({ "check": check, "evidence": evidence })
+}
+
+fn check_redis(path: &str, contents: &str) -> Option<Value> {
+    let mut bind: Option<(usize, &str)> = None;
+    let mut protected_off: Option<(usize, &str)> = None;
+    let mut authenticated = false;
+    for (no, line) in config_lines(contents) {
+        let mut parts = line.split_whitespace();
+        match parts.next().map(|d| d.to_lowercase()).as_deref() {
+            Some("bind") => bind = Some((no, line)),
+            Some("protected-mode") => {
+                protected_off = (parts.next() == Some("no")).then_some((no, line));
+            }
+            Some("requirepass") => authenticated = parts.next().is_some(),
+            Some("aclfile") => authenticated = true,
+            Some("user") => authenticated |= line.contains(" on ") && line.contains('>'),
+            _ => {}
+        }
+    }
+    if authenticated {
+        return None;
+    }
+    // Without a bind directive Redis listens everywhere but protected mode
+    // still refuses remote clients.
+    let exposed = match bind {
+        Some((_, line)) => line
+            .split_whitespace()
+            .skip(1)
+            .map(|a| a.trim_start_matches('-'))
+            .any(|a| matches!(a, "0.0.0.0" | "*" | "::" | "::*")),
+        None => protected_off.is_some(),
+    };
+    let (no, line) = bind.or(protected_off)?;
+    exposed.then(|| finding("redis_public_no_auth", vec![evidence(path, no, line)]))
+}
+
+fn check_pg_hba(path: &str, contents: &str) -> Vec<Value> {
+    let mut remote = Vec::new();
+    let mut local = Vec::new();
+    for (no, line) in config_lines(contents) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let Some(kind) = fields.first() else {
+            continue;
+        };
+        // local DATABASE USER METHOD, host DATABASE USER ADDRESS [MASK] METHOD
+        let (method_at, address) = match *kind {
+            "local" => (3, None),
+            k if k.starts_with("host") => {
+                let mask = fields
+                    .get(4)
+                    .is_some_and(|f| f.contains('.') && !f.contains('/'));
+                (if mask { 5 } else { 4 }, fields.get(3).copied())
+            }
+            _ => continue,
+        };
+        if fields.get(method_at) != Some(&"trust") {
+            continue;
+        }
+        match address {
+            Some(addr) if !LOOPBACK.contains(&addr) => remote.push(evidence(path, no, line)),
+            _ => local.push(evidence(path, no, line)),
+        }
+    }
+    let mut findings = Vec::new();
+    if !remote.is_empty() {
+        findings.push(finding("postgres_trust_remote", remote));
+    }
+    if !local.is_empty() {
+        findings.push(finding("postgres_trust_local", local));
+    }
+    findings
+}
+
+fn pg_hba_paths<P: SystemProvider>(provider: &P) -> Vec<String> {
+    let mut paths: Vec<String> = provider
+        .list_dir("/etc/postgresql")
+        .unwrap_or_default()
+        .iter()
+        .map(|version| format!("/etc/postgresql/{}/main/pg_hba.conf", version))
+        .collect();
+    paths.sort();
+    paths.extend(PG_HBA_PATHS.iter().map(|p| p.to_string()));
+    paths
+}
+
+fn check_docker_daemon_json(path: &str, contents: &str) -> Option<Value> {
+    let config: Value = serde_json::from_str(contents).ok()?;
+    let tls = ["tls", "tlsverify"]
+        .iter()
+        .any(|k| config.get(*k).and_then(|v| v.as_bool()).unwrap_or(false));
+    if tls {
+        return None;
+    }
+    let tcp_hosts: Vec<&str> = config
+        .get("hosts")?
+        .as_array()?
+        .iter()
+        .filter_map(|h| h.as_str())
+        .filter(|h| h.starts_with("tcp://"))
+        .collect();
+    let lines: Vec<String> = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| tcp_hosts.iter().any(|h| line.contains(h)))
+        .map(|(i, line)| evidence(path, i + 1, line.trim()))
+        .collect();
+    (!lines.is_empty()).then(|| finding("docker_tcp_no_tls", lines))
+}
+
+fn check_docker_unit(path: &str, contents: &str) -> Option<Value> {
+    let lines: Vec<String> = config_lines(contents)
+        .filter(|(_, line)| {
+            line.starts_with("ExecStart=") && line.contains("tcp://") && !line.contains("--tls")
+        })
+        .map(|(no, line)| evidence(path, no, line))
+        .collect();
+    (!lines.is_empty()).then(|| finding("docker_tcp_no_tls", lines))
+}
+
+fn check_elasticsearch(path: &str, contents: &str) -> Option<Value> {
+    let mut public_host = None;
+    let mut security_off = None;
+    for (no, line) in config_lines(contents) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim() {
+            "network.host" => {
+                let hosts = value.trim_matches(|c| c == '[' || c == ']');
+                let public = hosts
+                    .split(',')
+                    .map(|h| h.trim().trim_matches('"'))
+                    .any(|h| !LOOPBACK.contains(&h));
+                public_host = public.then(|| evidence(path, no, line));
+            }
+            "xpack.security.enabled" => {
+                security_off = (value == "false").then(|| evidence(path, no, line));
+            }
+            _ => {}
+        }
+    }
+    let (host, security) = (public_host?, security_off?);
+    Some(finding("elasticsearch_open", vec![host, security]))
+}
+
+fn record(nodes: &mut [Node], findings: Vec<Value>) {
+    for finding in findings {
+        let node_id = finding
+            .get("check")
+            .and_then(|c| c.as_str())
+            .and_then(|c| CHECKS.iter().find(|check| check.check == c))
+            .map(|check| check.node_id)
+            .unwrap_or("os");
+        let Some(node) = nodes.iter_mut().find(|n| n.id == node_id) else {
+            continue;
+        };
+        let entry = node
+            .metadata
+            .entry("security_findings".into())
+            .or_insert_with(|| json!([]));
+        if let Some(list) = entry.as_array_mut() {
+            list.push(finding);
+        }
+    }
+}
+
+/// Service node for an Elasticsearch install found by its config file, so
+/// its findings sit in the graph. The bundled JDK runs from the install
+/// directory, which is how a running node is recognised.
+fn ensure_elasticsearch_node<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>, path: &str) {
+    if nodes.iter().any(|n| n.id == "elasticsearch") {
+        return;
+    }
+    let running = provider.processes().iter().any(|p| {
+        p.exe
+            .as_deref()
+            .unwrap_or(&p.name)
+            .contains("elasticsearch")
+    });
+    let mut metadata = BTreeMap::new();
+    metadata.insert("config_path".into(), json!(path));
+    nodes.push(Node {
+        id: "elasticsearch".into(),
+        node_type: NodeType::Service,
+        label: "Elasticsearch".into(),
+        status: if running {
+            Status::Active
+        } else {
+            Status::Inactive
+        },
+        metadata,
+    });
+}
+
+/// Reads service configuration and records hardening findings, each with
+/// the file and line (or query) that shows the problem.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let mut findings = Vec::new();
+
+    let redis_conf = nodes
+        .iter()
+        .find(|n| n.id == "redis")
+        .and_then(|n| n.metadata.get("config_path"))
+        .and_then(|v| v.as_str())
+        .map(String::from);
+    if let Some(path) = redis_conf {
+        if let Some(contents) = provider.read_file(&path) {
+            findings.extend(check_redis(&path, &contents));
+        }
+    }
+
+    for path in pg_hba_paths(provider) {
+        if let Some(contents) = provider.read_file(&path) {
+            findings.extend(check_pg_hba(&path, &contents));
+        }
+    }
+
+    let skip_grants: Vec<String> = MYSQL_CONFIG_PATHS
+        .iter()
+        .filter_map(|path| Some((path, provider.read_file(path)?)))
+        .flat_map(|(path, contents)| {
+            config_lines(&contents)
+                .filter(|(_, line)| line.replace('_', "-").starts_with("skip-grant-tables"))
+                .map(|(no, line)| evidence(path, no, line))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    // Only configuration is read: logging in to check for an empty root
+    // password would be a credential attempt against the live server.
+    if !skip_grants.is_empty() {
+        findings.push(finding("mysql_skip_grant_tables", skip_grants));
+    }
+
+    if provider.file_exists(DOCKER_SOCKET) {
+        let mode = provider
+            .command_output("stat", &["-L", "-c", "%a", DOCKER_SOCKET])
+            .and_then(|m| u32::from_str_radix(m.trim(), 8).ok());
+        if let Some(mode) = mode.filter(|m| m & 0o002 != 0) {
+            findings.push(finding(
+                "docker_socket_world_writable",
+                vec![format!("{}: mode {:o}", DOCKER_SOCKET, mode)],
+            ));
+        }
+    }
+    if let Some(contents) = provider.read_file("/etc/docker/daemon.json") {
+        findings.extend(check_docker_daemon_json(
+            "/etc/docker/daemon.json",
+            &contents,
+        ));
+    }
+    let drop_in_dir = "/etc/systemd/system/docker.service.d";
+    let mut drop_ins: Vec<String> = provider
+        .list_dir(drop_in_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| f.ends_with(".conf"))
+        .map(|f| format!("{}/{}", drop_in_dir, f))
+        .collect();
+    drop_ins.sort();
+    for path in DOCKER_UNIT_PATHS
+        .iter()
+        .map(|p| p.to_string())
+        .chain(drop_ins)
+    {
+        if let Some(contents) = provider.read_file(&path) {
+            findings.extend(check_docker_unit(&path, &contents));
+        }
+    }
+
+    for path in ELASTICSEARCH_CONFIG_PATHS {
+        if let Some(contents) = provider.read_file(path) {
+            ensure_elasticsearch_node(provider, nodes, path);
+            findings.extend(check_elasticsearch(path, &contents));
+        }
+    }
+
+    record(nodes, findings);
+}
+
+/// Turns recorded hardening findings into `SEC_` issues.
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for node in &state.nodes {
+        let Some(findings) = node
+            .metadata
+            .get("security_findings")
+            .and_then(|v| v.as_array())
+        else {
+            continue;
+        };
+        for finding in findings {
+            let Some(check) = finding
+                .get("check")
+                .and_then(|c| c.as_str())
+                .and_then(|c| CHECKS.iter().find(|check| check.check == c))
+            else {
+                continue;
+            };
+            let evidence: Vec<&str> = finding
+                .get("evidence")
+                .and_then(|e| e.as_array())
+                .map(|e| e.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+            issues.push(Issue {
+                code: check.code.into(),
+                severity: check.severity.clone(),
+                title: check.title.into(),
+                description: format!("{}. Evidence: {}", check.title, evidence.join("; ")),
+                suggestion: check.suggestion.into(),
+            });
+        }
+    }
+    issues
+}
 
 #[derive(Debug, Serialize, Clone)]
 pub struct SecurityIssue {
@@ -11,23 +454,31 @@ pub struct SecurityIssue {
     pub description: String,
 }
 
-fn load_security_issues() -> Result<Vec<SecurityIssue>, String> {
+fn security_issue(issue: Issue) -> SecurityIssue {
+    SecurityIssue {
+        code: issue.code,
+        severity: format!("{:?}", issue.severity),
+        description: issue.description,
+    }
+}
+
+/// `SEC_` issues from the last scan, with secret findings replaced by a
+/// fresh scan of the working directory so fixed files drop out immediately.
+fn load_security_issues() -> Result<(Vec<SecurityIssue>, Vec<SecretFile>), String> {
     let state = fix::load_state()?;
+    let secret_files = secrets::scan(&RealSystemProvider);
     let issues: Vec<SecurityIssue> = state
         .issues
         .into_iter()
-        .filter(|i| i.code.starts_with("SEC_"))
-        .map(|issue| SecurityIssue {
-            code: issue.code,
-            severity: format!("{:?}", issue.severity),
-            description: issue.description,
-        })
+        .filter(|i| i.code.starts_with("SEC_") && !i.code.starts_with("SEC_SECRET_"))
+        .chain(secrets::issues(&secret_files))
+        .map(security_issue)
         .collect();
-    Ok(issues)
+    Ok((issues, secret_files))
 }
 
 pub fn run(json_output: bool) -> Result<(), String> {
-    let issues = load_security_issues()?;
+    let (issues, secret_files) = load_security_issues()?;
     let status = if issues.is_empty() { "ok" } else { "violation" };
 
     if json_output {
@@ -35,7 +486,8 @@ pub fn run(json_output: bool) -> Result<(), String> {
             "security",
             status,
             json!({
-                "security_issues": issues
+                "security_issues": issues,
+                "secret_files": secret_files
             }),
         );
         println!("{}", serde_json::to_string_pretty(&payload).unwrap());
@@ -45,6 +497,9 @@ pub fn run(json_output: bool) -> Result<(), String> {
         println!("Security findings ({}):", issues.len());
         for issue in &issues {
             println!("- {} ({})", issue.code, issue.severity);
+            if issue.code.starts_with("SEC_SECRET_") {
+                println!("  {}", issue.description);
+            }
         }
     }
     Ok(())
@@ -1,3 +1,4 @@
+use crate::cpu_features::{normalize_arch, X86_64_LEVELS};
 use crate::fix;
 use crate::models::SystemState;
 use crate::utils::json_envelope;
@@ -24,11 +25,19 @@ pub struct GpuSpec {
     pub allow_intel: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CpuSpec {
+    pub arch: Option<String>,
+    pub flags: Option<Vec<String>>,
+    pub microarch: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct EnvSpec {
     pub docker: Option<DockerSpec>,
     pub node: Option<NodeSpec>,
     pub gpu: Option<GpuSpec>,
+    pub cpu: Option<CpuSpec>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -157,6 +166,68 @@ fn validate_gpu(spec: &GpuSpec, state: &SystemState, violations: &mut Vec<EnvVio
     }
 }
 
+fn validate_cpu(spec: &CpuSpec, state: &SystemState, violations: &mut Vec<EnvViolation>) {
+    let os = find_node(state, "os");
+    let text = |key: &str| {
+        os.and_then(|n| n.metadata.get(key))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+
+    if let Some(arch) = &spec.arch {
+        let actual = text("arch");
+        if normalize_arch(&actual) != normalize_arch(arch) {
+            violations.push(EnvViolation {
+                r#type: "cpu_arch".into(),
+                expected: arch.clone(),
+                actual,
+            });
+        }
+    }
+
+    let flags: Vec<String> = os
+        .and_then(|n| n.metadata.get("cpu_flags"))
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|f| f.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    for flag in spec.flags.iter().flatten() {
+        if !flags.iter().any(|f| f.eq_ignore_ascii_case(flag)) {
+            violations.push(EnvViolation {
+                r#type: "cpu_flag".into(),
+                expected: flag.clone(),
+                actual: "missing".into(),
+            });
+        }
+    }
+
+    if let Some(required) = &spec.microarch {
+        let actual = text("cpu_microarch");
+        let rank = |level: &str| {
+            X86_64_LEVELS
+                .iter()
+                .position(|(name, _)| *name == level)
+                .map(|p| p + 1)
+                .or_else(|| (level == "x86-64").then_some(0))
+        };
+        let satisfied = match (rank(required), rank(&actual)) {
+            (Some(want), Some(have)) => have >= want,
+            _ => actual == *required,
+        };
+        if !satisfied {
+            violations.push(EnvViolation {
+                r#type: "cpu_microarch".into(),
+                expected: required.clone(),
+                actual,
+            });
+        }
+    }
+}
+
 fn evaluate(spec: &EnvSpec, state: &SystemState) -> Vec<EnvViolation> {
     let mut violations = Vec::new();
     if let Some(docker) = &spec.docker {
@@ -168,6 +239,9 @@ fn evaluate(spec: &EnvSpec, state: &SystemState) -> Vec<EnvViolation> {
     if let Some(gpu) = &spec.gpu {
         validate_gpu(gpu, state, &mut violations);
     }
+    if let Some(cpu) = &spec.cpu {
+        validate_cpu(cpu, state, &mut violations);
+    }
     violations
 }
 
@@ -1,7 +1,66 @@
+use std::collections::HashMap;
 use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
 use std::path::Path;
 use std::process::Command;
-use std::time::SystemTime;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::{Disks, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+/// Point-in-time host capacity as reported by the provider. Memory and
+/// swap values are in bytes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostResources {
+    pub kernel_version: Option<String>,
+    pub arch: Option<String>,
+    pub cpu_count: usize,
+    pub total_memory: u64,
+    pub available_memory: u64,
+    pub total_swap: u64,
+    pub free_swap: u64,
+    pub load_average: [f64; 3],
+}
+
+/// One entry of the process table. Command lines are left out on purpose:
+/// they change between runs and can carry passwords.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    /// Kernel process name, which Linux truncates to 15 characters.
+    pub name: String,
+    pub exe: Option<String>,
+    pub user: Option<String>,
+    /// TCP ports the process has a listening socket on.
+    pub listening_ports: Vec<u16>,
+}
+
+impl ProcessInfo {
+    /// Whether the process runs one of `names`, judged by the executable's
+    /// file name or, when that is unreadable, the process name.
+    pub fn runs_any(&self, names: &[&str]) -> bool {
+        let exe_name = self
+            .exe
+            .as_deref()
+            .and_then(|e| Path::new(e).file_name())
+            .and_then(|n| n.to_str());
+        match exe_name {
+            Some(exe) => names.contains(&exe),
+            None => names.contains(&self.name.as_str()),
+        }
+    }
+}
+
+/// Outcome of resolving a host and, when asked, connecting to a TCP port on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpProbe {
+    Unresolved,
+    /// The host resolved and no connection was attempted.
+    Resolved,
+    Reachable,
+    Unreachable,
+}
 
 pub trait SystemProvider: Send + Sync {
     fn file_exists(&self, path: &str) -> bool;
@@ -9,6 +68,101 @@ pub trait SystemProvider: Send + Sync {
     fn command_output(&self, cmd: &str, args: &[&str]) -> Option<String>;
     fn list_dir(&self, path: &str) -> Option<Vec<String>>;
     fn modification_time(&self, path: &str) -> Option<SystemTime>;
+
+    fn host_resources(&self) -> Option<HostResources> {
+        None
+    }
+
+    /// Bytes available to unprivileged users on the filesystem holding `path`.
+    fn disk_available(&self, _path: &str) -> Option<u64> {
+        None
+    }
+
+    fn env_var(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// Resolves symlinks to the final target path.
+    fn resolve_path(&self, path: &str) -> Option<String> {
+        Some(path.to_string())
+    }
+
+    fn processes(&self) -> Vec<ProcessInfo> {
+        Vec::new()
+    }
+
+    /// Resolves `host` and, if `connect` is set, opens a TCP connection to
+    /// `port`, giving up after `timeout` overall. `None` when the provider
+    /// cannot probe the network.
+    fn probe_tcp(
+        &self,
+        _host: &str,
+        _port: u16,
+        _connect: bool,
+        _timeout: Duration,
+    ) -> Option<TcpProbe> {
+        None
+    }
+}
+
+/// Listening TCP sockets from `/proc/net/tcp{,6}`, keyed by socket inode.
+fn listening_sockets() -> HashMap<u64, u16> {
+    let mut sockets = HashMap::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(contents) = fs::read_to_string(table) else {
+            continue;
+        };
+        for line in contents.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // 0A is TCP_LISTEN.
+            if fields.len() < 10 || fields[3] != "0A" {
+                continue;
+            }
+            let port = fields[1]
+                .rsplit(':')
+                .next()
+                .and_then(|p| u16::from_str_radix(p, 16).ok());
+            if let (Some(port), Ok(inode)) = (port, fields[9].parse()) {
+                sockets.insert(inode, port);
+            }
+        }
+    }
+    sockets
+}
+
+/// Ports among `sockets` that `pid` holds open. Other users' descriptors
+/// are unreadable without privileges, so this may come back empty.
+fn ports_of(pid: u32, sockets: &HashMap<u64, u16>) -> Vec<u16> {
+    let mut ports: Vec<u16> = fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| fs::read_link(e.path()).ok())
+                .filter_map(|target| {
+                    let target = target.to_string_lossy().to_string();
+                    let inode = target.strip_prefix("socket:[")?.strip_suffix(']')?;
+                    sockets.get(&inode.parse().ok()?).copied()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+}
+
+/// uid to login name from `/etc/passwd`.
+fn user_names() -> HashMap<u32, String> {
+    fs::read_to_string("/etc/passwd")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
 }
 
 pub struct RealSystemProvider;
@@ -53,4 +207,116 @@ impl SystemProvider for RealSystemProvider {
     fn modification_time(&self, path: &str) -> Option<SystemTime> {
         fs::metadata(path).ok().and_then(|m| m.modified().ok())
     }
+
+    fn host_resources(&self) -> Option<HostResources> {
+        let mut sys = System::new();
+        sys.refresh_memory();
+        let load = System::load_average();
+        let cpu_count = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(0);
+        Some(HostResources {
+            kernel_version: System::kernel_version(),
+            arch: System::cpu_arch(),
+            cpu_count,
+            total_memory: sys.total_memory(),
+            available_memory: sys.available_memory(),
+            total_swap: sys.total_swap(),
+            free_swap: sys.free_swap(),
+            load_average: [load.one, load.five, load.fifteen],
+        })
+    }
+
+    fn disk_available(&self, path: &str) -> Option<u64> {
+        let target = fs::canonicalize(path).ok()?;
+        let disks = Disks::new_with_refreshed_list();
+        disks
+            .list()
+            .iter()
+            .filter(|d| target.starts_with(d.mount_point()))
+            .max_by_key(|d| d.mount_point().as_os_str().len())
+            .map(|d| d.available_space())
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn resolve_path(&self, path: &str) -> Option<String> {
+        fs::canonicalize(path)
+            .ok()
+            .map(|p| p.to_string_lossy().to_string())
+    }
+
+    fn probe_tcp(
+        &self,
+        host: &str,
+        port: u16,
+        connect: bool,
+        timeout: Duration,
+    ) -> Option<TcpProbe> {
+        // Name resolution cannot be cancelled, so it runs on a worker thread
+        // that is abandoned once the deadline passes.
+        let deadline = Instant::now() + timeout;
+        let (sender, receiver) = mpsc::channel();
+        let target = (host.to_string(), port);
+        thread::spawn(move || {
+            let addresses: Vec<_> = target
+                .to_socket_addrs()
+                .map(|a| a.collect())
+                .unwrap_or_default();
+            let resolved = !addresses.is_empty();
+            if sender.send(resolved).is_err() || !resolved || !connect {
+                return;
+            }
+            let reachable = addresses.iter().any(|addr| {
+                let left = deadline.saturating_duration_since(Instant::now());
+                !left.is_zero() && TcpStream::connect_timeout(addr, left).is_ok()
+            });
+            let _ = sender.send(reachable);
+        });
+        let left = || deadline.saturating_duration_since(Instant::now());
+        if !receiver.recv_timeout(left()).unwrap_or(false) {
+            return Some(TcpProbe::Unresolved);
+        }
+        if !connect {
+            return Some(TcpProbe::Resolved);
+        }
+        Some(match receiver.recv_timeout(left()) {
+            Ok(true) => TcpProbe::Reachable,
+            _ => TcpProbe::Unreachable,
+        })
+    }
+
+    fn processes(&self) -> Vec<ProcessInfo> {
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            ProcessRefreshKind::new()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_user(UpdateKind::OnlyIfNotSet),
+        );
+        let sockets = listening_sockets();
+        let users = user_names();
+        let mut table: Vec<ProcessInfo> = sys
+            .processes()
+            .values()
+            // Threads show up as processes on Linux; keep the leaders.
+            .filter(|p| p.thread_kind().is_none())
+            .map(|p| {
+                let pid = p.pid().as_u32();
+                let uid = p.user_id().map(|u| **u);
+                ProcessInfo {
+                    pid,
+                    parent_pid: p.parent().map(|pp| pp.as_u32()),
+                    name: p.name().to_string_lossy().to_string(),
+                    exe: p.exe().map(|e| e.to_string_lossy().to_string()),
+                    user: uid.map(|u| users.get(&u).cloned().unwrap_or_else(|| u.to_string())),
+                    listening_ports: ports_of(pid, &sockets),
+                }
+            })
+            .collect();
+        table.sort_by_key(|p| p.pid);
+        table
+    }
 }
@@ -0,0 +1,203 @@
+use crate::models::{Issue, Node, Severity, SystemState};
+use crate::system_provider::SystemProvider;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Service nodes, the issue code prefix used for them, and the unit names
+/// distributions ship them under, in lookup order.
+const SERVICE_UNITS: &[(&str, &str, &[&str])] = &[
+    ("docker", "DOCKER", &["docker.service"]),
+    ("postgres", "POSTGRES", &["postgresql.service"]),
+    (
+        "mysql",
+        "MYSQL",
+        &["mysql.service", "mysqld.service", "mariadb.service"],
+    ),
+    ("redis", "REDIS", &["redis-server.service", "redis.service"]),
+];
+
+const PROPERTIES: &str =
+    "LoadState,ActiveState,SubState,UnitFileState,Result,ExecMainStatus,NRestarts";
+
+const JOURNAL_LINES: &str = "10";
+
+/// `systemctl show` output as a property map.
+pub fn parse_show(output: &str) -> BTreeMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Collapses systemd's properties into the states the fixes act on.
+pub fn unit_state(props: &BTreeMap<String, String>) -> &'static str {
+    let get = |key: &str| props.get(key).map(String::as_str).unwrap_or("");
+    if get("LoadState") == "masked" || get("UnitFileState").starts_with("masked") {
+        "masked"
+    } else if get("ActiveState") == "failed" {
+        "failed"
+    } else if get("ActiveState") == "active" || get("ActiveState") == "activating" {
+        "active"
+    } else if get("UnitFileState") == "disabled" {
+        "disabled"
+    } else {
+        "inactive"
+    }
+}
+
+/// Queries systemd for the unit behind each detected service and records
+/// its state, last exit status and, for failed units, the journal tail.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    for (node_id, _, units) in SERVICE_UNITS {
+        let Some(node) = nodes.iter_mut().find(|n| n.id == *node_id) else {
+            continue;
+        };
+        let found = units.iter().find_map(|unit| {
+            let props = parse_show(&provider.command_output(
+                "systemctl",
+                &["show", unit, "--property", PROPERTIES, "--no-pager"],
+            )?);
+            let load = props.get("LoadState").map(String::as_str);
+            (load.is_some() && load != Some("not-found")).then_some((*unit, props))
+        });
+        let Some((unit, props)) = found else {
+            continue;
+        };
+        let state = unit_state(&props);
+        node.metadata.insert("systemd_unit".into(), json!(unit));
+        node.metadata.insert("unit_state".into(), json!(state));
+        node.metadata.insert("systemd".into(), json!(props));
+        if state == "failed" {
+            let journal: Vec<String> = provider
+                .command_output(
+                    "journalctl",
+                    &["-u", unit, "-n", JOURNAL_LINES, "--no-pager", "-o", "cat"],
+                )
+                .unwrap_or_default()
+                .lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect();
+            node.metadata.insert("journal_tail".into(), json!(journal));
+        }
+    }
+}
+
+/// Unit-state issues for masked, failed and disabled units. Each replaces
+/// the generic `*_INACTIVE` issue named by [`replaced_code`].
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for (node_id, prefix, _) in SERVICE_UNITS {
+        let Some(node) = state.nodes.iter().find(|n| n.id == *node_id) else {
+            continue;
+        };
+        let unit_state = node.metadata.get("unit_state").and_then(|v| v.as_str());
+        let unit = node
+            .metadata
+            .get("systemd_unit")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let prop = |key: &str| {
+            node.metadata
+                .get("systemd")
+                .and_then(|p| p.get(key))
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string()
+        };
+        let issue = match unit_state {
+            Some("masked") => Issue {
+                code: format!("{}_UNIT_MASKED", prefix),
+                severity: Severity::Critical,
+                title: format!("{} is masked", unit),
+                description: format!(
+                    "{} is masked, so it cannot be started by hand or at boot.",
+                    unit
+                ),
+                suggestion: format!(
+                    "Run `sudo systemctl unmask {0} && sudo systemctl enable --now {0}`.",
+                    unit
+                ),
+            },
+            Some("failed") => {
+                let journal: Vec<&str> = node
+                    .metadata
+                    .get("journal_tail")
+                    .and_then(|v| v.as_array())
+                    .map(|a| a.iter().filter_map(|l| l.as_str()).collect())
+                    .unwrap_or_default();
+                let tail = if journal.is_empty() {
+                    String::new()
+                } else {
+                    format!(" Recent journal: {}", journal.join(" | "))
+                };
+                Issue {
+                    code: format!("{}_UNIT_FAILED", prefix),
+                    severity: Severity::Critical,
+                    title: format!("{} failed", unit),
+                    description: format!(
+                        "{} is in the failed state (result {}, exit status {}, {} restarts).{}",
+                        unit,
+                        prop("Result"),
+                        prop("ExecMainStatus"),
+                        prop("NRestarts"),
+                        tail
+                    ),
+                    suggestion: format!(
+                        "Fix the cause in the journal (`journalctl -u {0}`), then run `sudo systemctl restart {0}`.",
+                        unit
+                    ),
+                }
+            }
+            Some("disabled") => Issue {
+                code: format!("{}_UNIT_DISABLED", prefix),
+                severity: Severity::Warning,
+                title: format!("{} is installed but disabled", unit),
+                description: format!(
+                    "{} is installed but neither running nor enabled at boot.",
+                    unit
+                ),
+                suggestion: format!("Run `sudo systemctl enable --now {}`.", unit),
+            },
+            _ => continue,
+        };
+        issues.push(issue);
+    }
+    issues
+}
+
+/// The `*_INACTIVE` code a unit-state issue stands in for.
+pub fn replaced_code(issue: &Issue) -> Option<String> {
+    SERVICE_UNITS
+        .iter()
+        .find(|(_, prefix, _)| issue.code.starts_with(&format!("{}_UNIT_", prefix)))
+        .map(|(_, prefix, _)| format!("{}_INACTIVE", prefix))
+}
+
+/// `preflight fix` command for a unit-state issue: unmask, restart or
+/// enable the unit the scan recorded for the service.
+pub fn fix_command(state: &SystemState, code: &str) -> Option<String> {
+    let (node_id, kind) = SERVICE_UNITS.iter().find_map(|(node_id, prefix, _)| {
+        let kind = code.strip_prefix(prefix)?.strip_prefix("_UNIT_")?;
+        Some((node_id, kind))
+    })?;
+    let unit = state
+        .nodes
+        .iter()
+        .find(|n| n.id == *node_id)?
+        .metadata
+        .get("systemd_unit")?
+        .as_str()?;
+    match kind {
+        "MASKED" => Some(format!(
+            "sudo systemctl unmask {0} && sudo systemctl enable --now {0}",
+            unit
+        )),
+        "FAILED" => Some(format!("sudo systemctl restart {}", unit)),
+        "DISABLED" => Some(format!("sudo systemctl enable --now {}", unit)),
+        _ => None,
+    }
+}
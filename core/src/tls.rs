@@ -0,0 +1,499 @@
+use crate::models::{Issue, Node, Severity, SystemState};
+use crate::oracle_config::OracleConfig;
+use crate::system_provider::SystemProvider;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const COMPOSE_FILES: &[&str] = &[
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+const NGINX_CONFIG_DIRS: &[&str] = &[
+    "/etc/nginx/conf.d",
+    "/etc/nginx/sites-enabled",
+    "nginx",
+    "nginx/conf.d",
+];
+
+const NGINX_CONFIG_FILES: &[&str] = &["/etc/nginx/nginx.conf", "nginx.conf"];
+
+/// Variables naming CA bundles that Python, OpenSSL and Node.js load.
+const CA_BUNDLE_VARIABLES: &[&str] = &["SSL_CERT_FILE", "NODE_EXTRA_CA_CERTS"];
+
+const CERT_EXTENSIONS: &[&str] = &[".pem", ".crt", ".cer"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Certificate {
+    pub path: String,
+    /// `file:line` of each reference to the certificate.
+    pub referenced_by: Vec<String>,
+    pub key_path: Option<String>,
+    /// Host names the certificate is served for, from nginx `server_name`.
+    pub hostnames: Vec<String>,
+    pub subject: Option<String>,
+    pub issuer: Option<String>,
+    /// RFC 3339 expiry time.
+    pub not_after: Option<String>,
+    pub sans: Vec<String>,
+    /// Whether the private key belongs to the certificate; `None` when
+    /// there is no key or it could not be read.
+    pub key_matches: Option<bool>,
+}
+
+impl Certificate {
+    pub fn issued_by_mkcert(&self) -> bool {
+        self.issuer
+            .as_deref()
+            .is_some_and(|i| i.contains("mkcert development CA"))
+    }
+}
+
+/// A certificate reference found in configuration, before it is inspected.
+struct Reference {
+    cert: String,
+    key: Option<String>,
+    origin: String,
+    hostnames: Vec<String>,
+}
+
+fn is_cert_path(token: &str) -> bool {
+    !token.ends_with("-key.pem") && CERT_EXTENSIONS.iter().any(|ext| token.ends_with(ext))
+}
+
+/// The key mkcert or openssl would have written next to `cert`.
+fn sibling_key<P: SystemProvider>(provider: &P, cert: &str) -> Option<String> {
+    let stem = CERT_EXTENSIONS
+        .iter()
+        .find_map(|ext| cert.strip_suffix(ext))?;
+    [format!("{}-key.pem", stem), format!("{}.key", stem)]
+        .into_iter()
+        .find(|k| provider.file_exists(k))
+}
+
+/// Host-side certificate paths in compose files, including the source half
+/// of `./certs/site.pem:/etc/nginx/site.pem:ro` volume mounts.
+fn compose_references<P: SystemProvider>(provider: &P) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    for file in COMPOSE_FILES {
+        let Some(contents) = provider.read_file(file) else {
+            continue;
+        };
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            let tokens = line
+                .split(|c: char| c.is_whitespace() || "\"',[]=".contains(c))
+                .flat_map(|t| t.split(':'));
+            for token in tokens {
+                if is_cert_path(token) && provider.file_exists(token) {
+                    refs.push(Reference {
+                        cert: token.to_string(),
+                        key: sibling_key(provider, token),
+                        origin: format!("{}:{}", file, index + 1),
+                        hostnames: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    refs
+}
+
+fn directive_value(line: &str, name: &str) -> Option<String> {
+    let rest = line.strip_prefix(name)?;
+    rest.starts_with(char::is_whitespace)
+        .then(|| rest.trim().trim_end_matches(';').trim().to_string())
+}
+
+struct ServerBlock {
+    depth: usize,
+    cert: Option<(String, usize)>,
+    key: Option<String>,
+    names: Vec<String>,
+}
+
+/// `ssl_certificate` directives in each nginx `server` block, with the
+/// block's key and `server_name`s.
+fn nginx_references(path: &str, contents: &str) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    let mut depth = 0usize;
+    let mut block: Option<ServerBlock> = None;
+    for (index, raw) in contents.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if block.is_none() && (line.starts_with("server {") || line.starts_with("server{")) {
+            block = Some(ServerBlock {
+                depth,
+                cert: None,
+                key: None,
+                names: Vec::new(),
+            });
+        }
+        if let Some(current) = block.as_mut() {
+            if let Some(value) = directive_value(line, "ssl_certificate") {
+                current.cert = Some((value, index + 1));
+            } else if let Some(value) = directive_value(line, "ssl_certificate_key") {
+                current.key = Some(value);
+            } else if let Some(value) = directive_value(line, "server_name") {
+                current.names.extend(
+                    value
+                        .split_whitespace()
+                        .filter(|n| *n != "_" && !n.starts_with('~'))
+                        .map(String::from),
+                );
+            }
+        }
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+        if block.as_ref().is_some_and(|b| depth <= b.depth) {
+            let closed = block.take().expect("server block checked above");
+            if let Some((cert, line_no)) = closed.cert {
+                refs.push(Reference {
+                    cert,
+                    key: closed.key,
+                    origin: format!("{}:{}", path, line_no),
+                    hostnames: closed.names,
+                });
+            }
+        }
+    }
+    refs
+}
+
+fn nginx_config_paths<P: SystemProvider>(provider: &P) -> Vec<String> {
+    let mut paths: Vec<String> = NGINX_CONFIG_FILES.iter().map(|p| p.to_string()).collect();
+    for dir in NGINX_CONFIG_DIRS {
+        let mut entries: Vec<String> = provider
+            .list_dir(dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|f| !f.starts_with('.'))
+            .map(|f| format!("{}/{}", dir, f))
+            .collect();
+        entries.sort();
+        paths.extend(entries);
+    }
+    paths
+}
+
+/// CA bundles named by `SSL_CERT_FILE` or `NODE_EXTRA_CA_CERTS`, in the
+/// environment or the project's `.env`.
+fn ca_bundle_references<P: SystemProvider>(provider: &P) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    for var in CA_BUNDLE_VARIABLES {
+        if let Some(path) = provider.env_var(var) {
+            refs.push(Reference {
+                cert: path,
+                key: None,
+                origin: format!("${}", var),
+                hostnames: Vec::new(),
+            });
+        }
+    }
+    if let Some(contents) = provider.read_file(".env") {
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim().trim_start_matches("export ").trim();
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if CA_BUNDLE_VARIABLES.contains(&key.trim()) {
+                refs.push(Reference {
+                    cert: value.trim().trim_matches(['"', '\'']).to_string(),
+                    key: None,
+                    origin: format!(".env:{}", index + 1),
+                    hostnames: Vec::new(),
+                });
+            }
+        }
+    }
+    refs
+}
+
+/// `openssl x509 -noout -enddate -subject -issuer -ext subjectAltName`
+/// output as (subject, issuer, not_after, sans).
+pub fn parse_x509(output: &str) -> (Option<String>, Option<String>, Option<String>, Vec<String>) {
+    let mut subject = None;
+    let mut issuer = None;
+    let mut not_after = None;
+    let mut sans = Vec::new();
+    let mut in_san = false;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(value) = trimmed.strip_prefix("notAfter=") {
+            let normalized = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            not_after = NaiveDateTime::parse_from_str(&normalized, "%b %d %H:%M:%S %Y GMT")
+                .ok()
+                .map(|t| t.and_utc().to_rfc3339());
+        } else if let Some(value) = trimmed.strip_prefix("subject=") {
+            subject = Some(value.trim().to_string());
+        } else if let Some(value) = trimmed.strip_prefix("issuer=") {
+            issuer = Some(value.trim().to_string());
+        } else if trimmed.starts_with("X509v3 Subject Alternative Name") {
+            in_san = true;
+        } else if in_san {
+            sans.extend(trimmed.split(',').filter_map(|entry| {
+                let entry = entry.trim();
+                entry
+                    .strip_prefix("DNS:")
+                    .or_else(|| entry.strip_prefix("IP Address:"))
+                    .map(|v| v.trim().to_lowercase())
+            }));
+            in_san = false;
+        }
+    }
+    (subject, issuer, not_after, sans)
+}
+
+fn inspect<P: SystemProvider>(provider: &P, reference: &Reference) -> Certificate {
+    let (subject, issuer, not_after, sans) = provider
+        .command_output(
+            "openssl",
+            &[
+                "x509",
+                "-in",
+                &reference.cert,
+                "-noout",
+                "-enddate",
+                "-subject",
+                "-issuer",
+                "-ext",
+                "subjectAltName",
+            ],
+        )
+        .map(|out| parse_x509(&out))
+        .unwrap_or_default();
+    let key_matches = reference.key.as_deref().and_then(|key| {
+        let cert_pub = provider.command_output(
+            "openssl",
+            &["x509", "-in", &reference.cert, "-noout", "-pubkey"],
+        )?;
+        let key_pub = provider.command_output("openssl", &["pkey", "-in", key, "-pubout"])?;
+        Some(cert_pub.trim() == key_pub.trim())
+    });
+    Certificate {
+        path: reference.cert.clone(),
+        referenced_by: vec![reference.origin.clone()],
+        key_path: reference.key.clone(),
+        hostnames: reference.hostnames.clone(),
+        subject,
+        issuer,
+        not_after,
+        sans,
+        key_matches,
+    }
+}
+
+/// Whether `hostname` is covered by a SAN, allowing one-label wildcards.
+pub fn san_covers(sans: &[String], hostname: &str) -> bool {
+    let hostname = hostname.to_lowercase();
+    sans.iter().any(|san| match san.strip_prefix("*.") {
+        Some(domain) => hostname
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == domain),
+        None => *san == hostname,
+    })
+}
+
+/// Finds certificates referenced by compose files, nginx configuration and
+/// CA bundle variables, records their expiry, SANs and key match on the os
+/// node, and whether mkcert's root CA is in the system trust store.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut [Node]) {
+    let mut references = compose_references(provider);
+    for path in nginx_config_paths(provider) {
+        if let Some(contents) = provider.read_file(&path) {
+            references.extend(nginx_references(&path, &contents));
+        }
+    }
+    references.extend(ca_bundle_references(provider));
+
+    let mut certificates: Vec<Certificate> = Vec::new();
+    for reference in references.iter().filter(|r| provider.file_exists(&r.cert)) {
+        if let Some(existing) = certificates.iter_mut().find(|c| c.path == reference.cert) {
+            existing.referenced_by.push(reference.origin.clone());
+            for name in &reference.hostnames {
+                if !existing.hostnames.contains(name) {
+                    existing.hostnames.push(name.clone());
+                }
+            }
+            continue;
+        }
+        certificates.push(inspect(provider, reference));
+    }
+
+    // `openssl verify` exits non-zero unless the root chains to the system
+    // trust store, which for a root means it is installed there.
+    let mkcert_ca = provider
+        .command_output("mkcert", &["-CAROOT"])
+        .map(|dir| format!("{}/rootCA.pem", dir.trim().trim_end_matches('/')))
+        .filter(|root| provider.file_exists(root))
+        .map(|root| {
+            let trusted = provider
+                .command_output("openssl", &["verify", &root])
+                .is_some();
+            json!({ "path": root, "trusted": trusted })
+        });
+
+    if let Some(os) = nodes.iter_mut().find(|n| n.id == "os") {
+        os.metadata
+            .insert("tls_certificates".into(), json!(certificates));
+        os.metadata.insert("mkcert_ca".into(), json!(mkcert_ca));
+    }
+}
+
+fn scan_date(config: &OracleConfig) -> NaiveDate {
+    config
+        .tls
+        .scan_date
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d.get(..10)?, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| Utc::now().date_naive())
+}
+
+/// Expiry is measured against `--scan-date` or `[tls] scan_date` when one
+/// is given and today otherwise; unlike EOL dates, a certificate cannot be
+/// judged against a bundled calendar, so `[eol] scan_date` is not used.
+pub fn evaluate(state: &SystemState, config: &OracleConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let Some(os) = state.nodes.iter().find(|n| n.id == "os") else {
+        return issues;
+    };
+    let certificates: Vec<Certificate> = os
+        .metadata
+        .get("tls_certificates")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let today = scan_date(config);
+
+    for cert in &certificates {
+        let used_by = cert.referenced_by.join(", ");
+        let renew = if cert.issued_by_mkcert() {
+            // Keep the names the certificate already has and add the ones
+            // it is served for, so reissuing fixes a hostname mismatch.
+            let mut names: Vec<&str> = cert.sans.iter().map(String::as_str).collect();
+            for hostname in &cert.hostnames {
+                if !names.contains(&hostname.as_str()) {
+                    names.push(hostname);
+                }
+            }
+            let key_file = cert
+                .key_path
+                .as_deref()
+                .map(|key| format!(" -key-file {}", key))
+                .unwrap_or_default();
+            format!(
+                "Reissue it with `mkcert -cert-file {}{} {}`.",
+                cert.path,
+                key_file,
+                names.join(" ")
+            )
+        } else {
+            format!("Renew {} and reload the services using it.", cert.path)
+        };
+        let expiry = cert
+            .not_after
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.date_naive());
+        if let Some(expiry) = expiry {
+            let days_left = (expiry - today).num_days();
+            if days_left < 0 {
+                issues.push(Issue {
+                    code: "TLS_CERT_EXPIRED".into(),
+                    severity: Severity::Critical,
+                    title: format!("Certificate {} has expired", cert.path),
+                    description: format!(
+                        "{} expired on {} (scan date {}); used by {}.",
+                        cert.path, expiry, today, used_by
+                    ),
+                    suggestion: renew.clone(),
+                });
+            } else if days_left <= config.tls.warn_days {
+                issues.push(Issue {
+                    code: "TLS_CERT_EXPIRING".into(),
+                    severity: Severity::Warning,
+                    title: format!("Certificate {} expires soon", cert.path),
+                    description: format!(
+                        "{} expires on {}, {} days after the scan date {}; used by {}.",
+                        cert.path, expiry, days_left, today, used_by
+                    ),
+                    suggestion: renew.clone(),
+                });
+            }
+        }
+
+        let uncovered: Vec<&str> = cert
+            .hostnames
+            .iter()
+            .filter(|h| !san_covers(&cert.sans, h))
+            .map(String::as_str)
+            .collect();
+        if !uncovered.is_empty() {
+            issues.push(Issue {
+                code: "TLS_HOSTNAME_MISMATCH".into(),
+                severity: Severity::Warning,
+                title: format!(
+                    "Certificate {} does not cover {}",
+                    cert.path,
+                    uncovered.join(", ")
+                ),
+                description: format!(
+                    "{} is served for {} but its subject alternative names are [{}]; used by {}.",
+                    cert.path,
+                    uncovered.join(", "),
+                    cert.sans.join(", "),
+                    used_by
+                ),
+                suggestion: renew.clone(),
+            });
+        }
+
+        if cert.key_matches == Some(false) {
+            issues.push(Issue {
+                code: "TLS_KEY_MISMATCH".into(),
+                severity: Severity::Critical,
+                title: format!("Private key does not match {}", cert.path),
+                description: format!(
+                    "{} was not generated for {}, so TLS handshakes will fail; used by {}.",
+                    cert.key_path.as_deref().unwrap_or("The configured key"),
+                    cert.path,
+                    used_by
+                ),
+                suggestion: "Point the key setting at the key generated with this certificate, or reissue both together.".into(),
+            });
+        }
+    }
+
+    let mkcert_untrusted = os
+        .metadata
+        .get("mkcert_ca")
+        .filter(|ca| ca.get("trusted").and_then(|v| v.as_bool()) == Some(false))
+        .and_then(|ca| ca.get("path"))
+        .and_then(|v| v.as_str());
+    if let Some(root) = mkcert_untrusted {
+        let issued: Vec<&str> = certificates
+            .iter()
+            .filter(|c| c.issued_by_mkcert())
+            .map(|c| c.path.as_str())
+            .collect();
+        let affected = if issued.is_empty() {
+            String::new()
+        } else {
+            format!(" Certificates it issued: {}.", issued.join(", "))
+        };
+        issues.push(Issue {
+            code: "TLS_MKCERT_CA_UNTRUSTED".into(),
+            severity: Severity::Warning,
+            title: "mkcert root CA is not trusted".into(),
+            description: format!(
+                "{} is not in the system trust store, so browsers, curl and language runtimes reject certificates it issued.{}",
+                root, affected
+            ),
+            suggestion: "Run `mkcert -install`.".into(),
+        });
+    }
+    issues
+}
@@ -0,0 +1,402 @@
+use crate::eol::EolCalendar;
+use crate::models::{Node, NodeType, Status};
+use crate::path_scan::path_entries;
+use crate::system_provider::SystemProvider;
+use regex::Regex;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A runtime version pinned by a project file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionPin {
+    pub tool: String,
+    pub version: String,
+    pub source: String,
+    pub manager: &'static str,
+}
+
+/// Maps a tool name used in pin files onto the canonical name Preflight uses.
+fn canonical_tool(name: &str) -> Option<&'static str> {
+    match name.trim().to_lowercase().as_str() {
+        "node" | "nodejs" => Some("node"),
+        "python" => Some("python"),
+        "ruby" => Some("ruby"),
+        "java" => Some("java"),
+        "go" | "golang" => Some("go"),
+        _ => None,
+    }
+}
+
+fn node_id_for(tool: &str) -> &'static str {
+    match tool {
+        "node" => "nodejs",
+        "python" => "python",
+        "ruby" => "ruby",
+        "java" => "java",
+        _ => "go",
+    }
+}
+
+fn single_line(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .find(|l| !l.is_empty())
+        .map(|l| l.to_string())
+}
+
+pub fn parse_tool_versions(contents: &str, manager: &'static str) -> Vec<VersionPin> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?.trim();
+            let mut parts = line.split_whitespace();
+            let tool = canonical_tool(parts.next()?)?;
+            let version = parts.next()?;
+            Some(VersionPin {
+                tool: tool.into(),
+                version: version.into(),
+                source: ".tool-versions".into(),
+                manager,
+            })
+        })
+        .collect()
+}
+
+pub fn parse_mise_toml(contents: &str, source: &str) -> Vec<VersionPin> {
+    let Ok(parsed) = toml::from_str::<toml::Value>(contents) else {
+        return Vec::new();
+    };
+    let Some(tools) = parsed.get("tools").and_then(|t| t.as_table()) else {
+        return Vec::new();
+    };
+    tools
+        .iter()
+        .filter_map(|(name, value)| {
+            let tool = canonical_tool(name)?;
+            let version = match value {
+                toml::Value::String(v) => v.clone(),
+                toml::Value::Array(items) => items.first()?.as_str()?.to_string(),
+                toml::Value::Table(table) => table.get("version")?.as_str()?.to_string(),
+                _ => return None,
+            };
+            Some(VersionPin {
+                tool: tool.into(),
+                version,
+                source: source.into(),
+                manager: "mise",
+            })
+        })
+        .collect()
+}
+
+fn collect_pins<P: SystemProvider>(provider: &P) -> Vec<VersionPin> {
+    let mut pins = Vec::new();
+
+    if let Some(contents) = provider.read_file(".tool-versions") {
+        let manager = if provider.command_output("asdf", &["--version"]).is_none()
+            && provider.command_output("mise", &["--version"]).is_some()
+        {
+            "mise"
+        } else {
+            "asdf"
+        };
+        pins.extend(parse_tool_versions(&contents, manager));
+    }
+    for source in ["mise.toml", ".mise.toml"] {
+        if let Some(contents) = provider.read_file(source) {
+            pins.extend(parse_mise_toml(&contents, source));
+        }
+    }
+
+    let single_file_pins = [
+        (".nvmrc", "node", "nvm"),
+        (".python-version", "python", "pyenv"),
+        (".ruby-version", "ruby", "rbenv"),
+    ];
+    for (source, tool, manager) in single_file_pins {
+        if let Some(version) = provider.read_file(source).and_then(|c| single_line(&c)) {
+            pins.push(VersionPin {
+                tool: tool.into(),
+                version,
+                source: source.into(),
+                manager,
+            });
+        }
+    }
+    if let Some(contents) = provider.read_file(".sdkmanrc") {
+        for line in contents.lines() {
+            if let Some((name, version)) = line.split('#').next().unwrap_or("").split_once('=') {
+                if let Some(tool) = canonical_tool(name) {
+                    pins.push(VersionPin {
+                        tool: tool.into(),
+                        version: version.trim().into(),
+                        source: ".sdkmanrc".into(),
+                        manager: "sdkman",
+                    });
+                }
+            }
+        }
+    }
+
+    pins
+}
+
+/// Root directory of a version manager, honouring its override variable.
+fn manager_root<P: SystemProvider>(provider: &P, manager: &str) -> Option<String> {
+    let (var, default) = match manager {
+        "asdf" => ("ASDF_DATA_DIR", ".asdf"),
+        "mise" => ("MISE_DATA_DIR", ".local/share/mise"),
+        "nvm" => ("NVM_DIR", ".nvm"),
+        "pyenv" => ("PYENV_ROOT", ".pyenv"),
+        "rbenv" => ("RBENV_ROOT", ".rbenv"),
+        "sdkman" => ("SDKMAN_DIR", ".sdkman"),
+        _ => return None,
+    };
+    provider.env_var(var).or_else(|| {
+        provider
+            .env_var("HOME")
+            .map(|home| format!("{}/{}", home.trim_end_matches('/'), default))
+    })
+}
+
+fn asdf_plugin(tool: &str) -> &str {
+    match tool {
+        "node" => "nodejs",
+        "go" => "golang",
+        other => other,
+    }
+}
+
+fn lines_to_versions(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|l| l.trim().trim_start_matches('*').trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("No "))
+        .map(|l| l.split_whitespace().next().unwrap_or(l).to_string())
+        .collect()
+}
+
+fn mise_versions(output: &str) -> Vec<String> {
+    let Ok(parsed) = serde_json::from_str::<Value>(output) else {
+        return Vec::new();
+    };
+    let entries = match &parsed {
+        Value::Array(items) => items.clone(),
+        Value::Object(map) => map
+            .values()
+            .filter_map(|v| v.as_array())
+            .flatten()
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    };
+    entries
+        .iter()
+        .filter_map(|e| e.get("version").and_then(|v| v.as_str()))
+        .map(|v| v.to_string())
+        .collect()
+}
+
+/// Installed versions reported by the manager, or `None` when the manager
+/// itself is unavailable.
+fn installed_versions<P: SystemProvider>(
+    provider: &P,
+    manager: &str,
+    tool: &str,
+    root: Option<&str>,
+) -> Option<Vec<String>> {
+    let versions = match manager {
+        "asdf" => {
+            lines_to_versions(&provider.command_output("asdf", &["list", asdf_plugin(tool)])?)
+        }
+        "mise" => {
+            mise_versions(&provider.command_output("mise", &["ls", "--installed", "--json", tool])?)
+        }
+        "pyenv" => lines_to_versions(&provider.command_output("pyenv", &["versions", "--bare"])?),
+        "rbenv" => lines_to_versions(&provider.command_output("rbenv", &["versions", "--bare"])?),
+        "nvm" => provider
+            .list_dir(&format!("{}/versions/node", root?))?
+            .into_iter()
+            .map(|v| v.trim_start_matches('v').to_string())
+            .collect(),
+        "sdkman" => provider
+            .list_dir(&format!("{}/candidates/{}", root?, tool))?
+            .into_iter()
+            .filter(|v| v != "current")
+            .collect(),
+        _ => return None,
+    };
+    Some(versions)
+}
+
+/// True when `pin` selects `version`: an exact match or a shorter prefix
+/// such as `20` or `3.11` ending on a component boundary.
+pub fn pin_matches(pin: &str, version: &str) -> bool {
+    let pin = pin.trim().trim_start_matches('v');
+    let version = version.trim().trim_start_matches('v');
+    version == pin
+        || version.starts_with(&format!("{}.", pin))
+        || version.starts_with(&format!("{}-", pin))
+}
+
+/// Node.js major an nvm `lts` alias selects. Codenames name one release
+/// line; `lts/*` is the newest even-numbered line the EOL calendar still
+/// supports and `lts/-N` the one N lines before it. `None` when the alias
+/// cannot be resolved.
+fn lts_major(pin: &str, calendar: &EolCalendar) -> Option<String> {
+    let alias = pin.trim().strip_prefix("lts")?.trim_start_matches('/');
+    let codename = match alias.to_lowercase().as_str() {
+        "argon" => Some("4"),
+        "boron" => Some("6"),
+        "carbon" => Some("8"),
+        "dubnium" => Some("10"),
+        "erbium" => Some("12"),
+        "fermium" => Some("14"),
+        "gallium" => Some("16"),
+        "hydrogen" => Some("18"),
+        "iron" => Some("20"),
+        "jod" => Some("22"),
+        "krypton" => Some("24"),
+        _ => None,
+    };
+    if let Some(major) = codename {
+        return Some(major.to_string());
+    }
+    let back: usize = match alias {
+        "" | "*" => 0,
+        relative => relative.strip_prefix('-')?.parse().ok()?,
+    };
+    let mut majors: Vec<u64> = calendar
+        .supported_cycles("nodejs")
+        .into_iter()
+        .filter_map(|cycle| cycle.parse::<u64>().ok())
+        .filter(|major| major % 2 == 0)
+        .collect();
+    majors.sort_unstable_by(|a, b| b.cmp(a));
+    majors.get(back).map(|major| major.to_string())
+}
+
+fn extract_version(version_re: &Regex, raw: &str) -> Option<String> {
+    version_re.find(raw).map(|m| m.as_str().to_string())
+}
+
+fn active_version<P: SystemProvider>(
+    provider: &P,
+    nodes: &[Node],
+    tool: &str,
+    version_re: &Regex,
+) -> Option<String> {
+    let from_node = nodes
+        .iter()
+        .find(|n| n.id == node_id_for(tool))
+        .and_then(|n| n.metadata.get("version"))
+        .and_then(|v| v.as_str())
+        .and_then(|raw| extract_version(version_re, raw));
+    from_node.or_else(|| {
+        let raw = match tool {
+            "java" => provider.command_output("java", &["-version"]),
+            "go" => provider.command_output("go", &["version"]),
+            other => provider.command_output(other, &["--version"]),
+        };
+        raw.as_deref()
+            .and_then(|raw| extract_version(version_re, raw))
+    })
+}
+
+fn runtime_label(tool: &str) -> &'static str {
+    match tool {
+        "ruby" => "Ruby",
+        "java" => "Java",
+        _ => "Go",
+    }
+}
+
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let pins = collect_pins(provider);
+    if pins.is_empty() {
+        return;
+    }
+    let path = path_entries(provider);
+    let calendar = EolCalendar::load();
+    let version_re =
+        Regex::new(r"\d+(\.\d+){0,2}").expect("regex construction invariant: constant pattern");
+
+    let mut findings: BTreeMap<&'static str, Vec<Value>> = BTreeMap::new();
+    for pin in &pins {
+        let root = manager_root(provider, pin.manager);
+        let installed = installed_versions(provider, pin.manager, &pin.tool, root.as_deref());
+        // sdkman pins carry a vendor suffix (17.0.9-tem) the runtime does not report.
+        let comparable_pin = pin.version.split('-').next().unwrap_or(&pin.version);
+        let (installed_pin, active_pin) = if pin.version.starts_with("lts") {
+            let major = lts_major(&pin.version, &calendar);
+            (major.clone(), major)
+        } else {
+            (Some(pin.version.clone()), Some(comparable_pin.to_string()))
+        };
+        // An alias the calendar cannot resolve is not checked at all.
+        let selects = |pin: &Option<String>, version: &str| {
+            pin.as_ref()
+                .map(|pin| pin_matches(pin, version))
+                .unwrap_or(true)
+        };
+        let pin_installed = installed.as_ref().map(|versions| {
+            installed_pin.is_none() || versions.iter().any(|v| selects(&installed_pin, v))
+        });
+        let active = active_version(provider, nodes, &pin.tool, &version_re);
+        let pin_active = match &active {
+            Some(a) => selects(&active_pin, a),
+            None => active_pin.is_none(),
+        };
+        let shim_on_path = root
+            .as_ref()
+            .map(|r| path.iter().any(|entry| entry.starts_with(r.as_str())))
+            .unwrap_or(false);
+
+        findings
+            .entry(node_id_for(&pin.tool))
+            .or_default()
+            .push(json!({
+                "tool": pin.tool,
+                "pinned": pin.version,
+                "source": pin.source,
+                "manager": pin.manager,
+                "manager_available": installed.is_some(),
+                "installed": pin_installed.unwrap_or(false),
+                "installed_versions": installed.unwrap_or_default(),
+                "active_version": active,
+                "active": pin_active,
+                "shim_on_path": shim_on_path,
+            }));
+    }
+
+    for (node_id, entries) in findings {
+        if let Some(node) = nodes.iter_mut().find(|n| n.id == node_id) {
+            node.metadata.insert("version_pins".into(), json!(entries));
+            continue;
+        }
+        let tool = entries[0]
+            .get("tool")
+            .and_then(|v| v.as_str())
+            .unwrap_or(node_id)
+            .to_string();
+        let active = active_version(provider, nodes, &tool, &version_re);
+        let mut metadata = BTreeMap::new();
+        if let Some(v) = &active {
+            metadata.insert("version".into(), json!(v));
+        }
+        metadata.insert("version_pins".into(), json!(entries));
+        nodes.push(Node {
+            id: node_id.into(),
+            node_type: NodeType::Runtime,
+            label: runtime_label(&tool).into(),
+            status: if active.is_some() {
+                Status::Active
+            } else {
+                Status::Inactive
+            },
+            metadata,
+        });
+    }
+}
@@ -0,0 +1,598 @@
+use crate::models::{Issue, Node, NodeType, Severity, Status, SystemState};
+use crate::scanner::ensure_port_node;
+use crate::system_provider::SystemProvider;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+struct WebServer {
+    id: &'static str,
+    label: &'static str,
+    /// Issue code prefix.
+    prefix: &'static str,
+    executables: &'static [&'static str],
+    /// Main config files, in lookup order.
+    configs: &'static [&'static str],
+}
+
+const SERVERS: &[WebServer] = &[
+    WebServer {
+        id: "nginx",
+        label: "nginx",
+        prefix: "NGINX",
+        executables: &["nginx"],
+        configs: &[
+            "/etc/nginx/nginx.conf",
+            "/usr/local/etc/nginx/nginx.conf",
+            "/opt/homebrew/etc/nginx/nginx.conf",
+        ],
+    },
+    WebServer {
+        id: "caddy",
+        label: "Caddy",
+        prefix: "CADDY",
+        executables: &["caddy"],
+        configs: &["/etc/caddy/Caddyfile", "Caddyfile"],
+    },
+    WebServer {
+        id: "apache",
+        label: "Apache httpd",
+        prefix: "APACHE",
+        executables: &["apache2", "httpd"],
+        configs: &["/etc/apache2/apache2.conf", "/etc/httpd/conf/httpd.conf"],
+    },
+];
+
+/// Apache splits `Listen` and vhosts across these in Debian and Red Hat
+/// layouts; they are read in addition to following `Include` lines.
+const APACHE_EXTRA: &[&str] = &[
+    "/etc/apache2/ports.conf",
+    "/etc/apache2/sites-enabled/*",
+    "/etc/httpd/conf.d/*.conf",
+];
+
+/// Hosts that mean "this machine" in an upstream address.
+const LOCAL_HOSTS: &[&str] = &["", "localhost", "127.0.0.1", "0.0.0.0", "::1", "[::1]", "*"];
+
+/// Includes nested deeper than this are ignored, which also stops cycles.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Upstream {
+    /// `host:port` as written in the config.
+    pub target: String,
+    pub port: u16,
+    /// Whether the upstream is on this host, so its port can be checked.
+    pub local: bool,
+    /// `file:line` of the directive.
+    pub origin: String,
+}
+
+#[derive(Debug, Default)]
+struct ServerConfig {
+    files: Vec<String>,
+    listen: BTreeSet<u16>,
+    upstreams: Vec<Upstream>,
+}
+
+/// Splits `host:port`, `[v6]:port`, `:port` or a bare port, with an
+/// optional scheme and path, into host and port.
+pub fn parse_address(raw: &str, default_port: Option<u16>) -> Option<(String, u16)> {
+    let (scheme, rest) = match raw.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, raw),
+    };
+    let authority = rest.split('/').next()?;
+    if let Ok(port) = authority.parse::<u16>() {
+        return Some((String::new(), port));
+    }
+    let scheme_port = match scheme {
+        Some("https") | Some("grpcs") => Some(443),
+        Some(_) => Some(80),
+        None => default_port,
+    };
+    match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => Some((host.to_string(), port.parse().ok()?)),
+        _ => Some((authority.to_string(), scheme_port?)),
+    }
+}
+
+fn upstream(target: &str, default_port: Option<u16>, origin: String) -> Option<Upstream> {
+    let (host, port) = parse_address(target, default_port)?;
+    Some(Upstream {
+        target: target
+            .split_once("://")
+            .map_or(target, |(_, rest)| rest)
+            .trim_end_matches('/')
+            .to_string(),
+        port,
+        local: LOCAL_HOSTS.contains(&host.to_lowercase().as_str()),
+        origin,
+    })
+}
+
+/// Expands an include pattern whose last component may hold a `*`.
+fn expand<P: SystemProvider>(provider: &P, pattern: &str) -> Vec<String> {
+    let Some((dir, name)) = pattern.rsplit_once('/') else {
+        return vec![pattern.to_string()];
+    };
+    let Some((prefix, suffix)) = name.split_once('*') else {
+        return vec![pattern.to_string()];
+    };
+    let mut files: Vec<String> = provider
+        .list_dir(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| f.starts_with(prefix) && f.ends_with(suffix) && !f.starts_with('.'))
+        .map(|f| format!("{}/{}", dir, f))
+        .collect();
+    files.sort();
+    files
+}
+
+fn relative_to(base: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_string();
+    }
+    match base.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, path),
+        None => path.to_string(),
+    }
+}
+
+/// nginx statements as (words, line, enclosing blocks), where a block is
+/// the words that opened it, e.g. `upstream backend`.
+fn nginx_statements(contents: &str) -> Vec<(Vec<String>, usize, Vec<String>)> {
+    let mut statements = Vec::new();
+    let mut blocks: Vec<String> = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut start_line = 1;
+    for (index, line) in contents.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or("");
+        for c in code.chars() {
+            if c.is_whitespace() || matches!(c, ';' | '{' | '}') {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            } else {
+                if words.is_empty() && word.is_empty() {
+                    start_line = index + 1;
+                }
+                word.push(c);
+            }
+            match c {
+                ';' if !words.is_empty() => {
+                    statements.push((std::mem::take(&mut words), start_line, blocks.clone()))
+                }
+                '{' => blocks.push(std::mem::take(&mut words).join(" ")),
+                '}' => {
+                    words.clear();
+                    blocks.pop();
+                }
+                _ => {}
+            }
+        }
+        if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+    }
+    statements
+}
+
+/// nginx state gathered across included files; `proxy_pass` targets are
+/// resolved against `upstream` blocks once every file has been read.
+#[derive(Default)]
+struct NginxState {
+    /// Relative includes resolve against the main config's directory.
+    main: String,
+    config: ServerConfig,
+    upstream_blocks: BTreeMap<String, Vec<Upstream>>,
+    passes: Vec<(String, String)>,
+}
+
+fn read_nginx<P: SystemProvider>(provider: &P, path: &str, depth: usize, state: &mut NginxState) {
+    if depth > MAX_INCLUDE_DEPTH || state.config.files.iter().any(|f| f == path) {
+        return;
+    }
+    let Some(contents) = provider.read_file(path) else {
+        return;
+    };
+    state.config.files.push(path.to_string());
+    for (words, line, blocks) in nginx_statements(&contents) {
+        let origin = format!("{}:{}", path, line);
+        let arg = words.get(1).map(String::as_str).unwrap_or("");
+        let upstream_name = blocks
+            .last()
+            .and_then(|b| b.strip_prefix("upstream "))
+            .map(str::trim);
+        match (words[0].as_str(), upstream_name) {
+            ("include", _) => {
+                let pattern = relative_to(&state.main, arg);
+                for file in expand(provider, &pattern) {
+                    read_nginx(provider, &file, depth + 1, state);
+                }
+            }
+            ("listen", None) if !arg.starts_with("unix:") => {
+                if let Some((_, port)) = parse_address(arg, Some(80)) {
+                    state.config.listen.insert(port);
+                }
+            }
+            ("server", Some(name)) => {
+                state
+                    .upstream_blocks
+                    .entry(name.to_string())
+                    .or_default()
+                    .extend(upstream(arg, Some(80), origin));
+            }
+            ("proxy_pass" | "fastcgi_pass" | "uwsgi_pass" | "grpc_pass" | "scgi_pass", _) => {
+                state.passes.push((arg.to_string(), origin));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn nginx<P: SystemProvider>(provider: &P, main: &str) -> ServerConfig {
+    let mut state = NginxState {
+        main: main.to_string(),
+        ..NginxState::default()
+    };
+    read_nginx(provider, main, 0, &mut state);
+    for (target, origin) in state.passes {
+        let name = target
+            .split_once("://")
+            .map_or(target.as_str(), |(_, rest)| rest)
+            .split('/')
+            .next()
+            .unwrap_or("");
+        match state.upstream_blocks.get(name) {
+            Some(members) => state.config.upstreams.extend(members.iter().cloned()),
+            None => state
+                .config
+                .upstreams
+                .extend(upstream(&target, None, origin)),
+        }
+    }
+    state.config
+}
+
+/// Caddy site addresses at the top level of a Caddyfile and the upstreams
+/// of `reverse_proxy` and `php_fastcgi` inside them.
+fn caddy<P: SystemProvider>(provider: &P, path: &str) -> ServerConfig {
+    let mut config = ServerConfig::default();
+    let Some(contents) = provider.read_file(path) else {
+        return config;
+    };
+    config.files.push(path.to_string());
+    let mut depth = 0usize;
+    let mut lines = Vec::new();
+    for (index, raw) in contents.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        let words: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty() && *w != "{")
+            .collect();
+        // Snippet definitions and imports are not site addresses.
+        let top = depth == 0
+            && words
+                .first()
+                .is_some_and(|w| *w != "import" && !w.starts_with('('));
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+        lines.push((index, line, words, top));
+    }
+    // A lone site may drop its braces, in which case only its first line
+    // holds addresses and the top-level lines after it are directives.
+    let braced = lines
+        .iter()
+        .any(|(_, line, _, top)| *top && line.contains('{'));
+    let mut addressed = false;
+    for (index, _, words, top) in lines {
+        let origin = format!("{}:{}", path, index + 1);
+        if top && (braced || !addressed) {
+            addressed = true;
+            for address in &words {
+                // Sites without a port are served over HTTPS.
+                let default = if address.starts_with("http://") {
+                    80
+                } else {
+                    443
+                };
+                if let Some((_, port)) = parse_address(address, Some(default)) {
+                    config.listen.insert(port);
+                }
+            }
+        } else if let Some((&directive, args)) = words.split_first() {
+            // `to` lists upstreams inside a `reverse_proxy { }` block.
+            if matches!(directive, "reverse_proxy" | "php_fastcgi" | "to") {
+                for target in args
+                    .iter()
+                    .filter(|a| !a.starts_with(['/', '@', '*']) && **a != "}")
+                {
+                    config
+                        .upstreams
+                        .extend(upstream(target, None, origin.clone()));
+                }
+            }
+        }
+    }
+    config
+}
+
+fn read_apache<P: SystemProvider>(
+    provider: &P,
+    path: &str,
+    depth: usize,
+    config: &mut ServerConfig,
+) {
+    if depth > MAX_INCLUDE_DEPTH || config.files.iter().any(|f| f == path) {
+        return;
+    }
+    let Some(contents) = provider.read_file(path) else {
+        return;
+    };
+    config.files.push(path.to_string());
+    let server_root = if path.starts_with("/etc/httpd") {
+        "/etc/httpd"
+    } else {
+        "/etc/apache2"
+    };
+    for (index, raw) in contents.lines().enumerate() {
+        let words: Vec<&str> = raw.split_whitespace().collect();
+        let Some((directive, args)) = words.split_first() else {
+            continue;
+        };
+        let origin = format!("{}:{}", path, index + 1);
+        match directive.to_lowercase().as_str() {
+            "listen" => {
+                if let Some((_, port)) = args.first().and_then(|a| parse_address(a, None)) {
+                    config.listen.insert(port);
+                }
+            }
+            "include" | "includeoptional" => {
+                let pattern = args.first().copied().unwrap_or("");
+                // Relative includes resolve against ServerRoot.
+                let pattern = if pattern.starts_with('/') {
+                    pattern.to_string()
+                } else {
+                    format!("{}/{}", server_root, pattern)
+                };
+                for file in expand(provider, &pattern) {
+                    read_apache(provider, &file, depth + 1, config);
+                }
+            }
+            "proxypass" => {
+                if let Some(target) = args.get(1).filter(|t| t.contains("://")) {
+                    config.upstreams.extend(upstream(target, None, origin));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn apache<P: SystemProvider>(provider: &P, main: &str) -> ServerConfig {
+    let mut config = ServerConfig::default();
+    read_apache(provider, main, 0, &mut config);
+    let prefix = if main.starts_with("/etc/httpd") {
+        "/etc/httpd"
+    } else {
+        "/etc/apache2"
+    };
+    for pattern in APACHE_EXTRA.iter().filter(|p| p.starts_with(prefix)) {
+        for file in expand(provider, pattern) {
+            read_apache(provider, &file, 1, &mut config);
+        }
+    }
+    config
+}
+
+/// Host ports published by each compose service.
+pub fn compose_ports(contents: &str) -> BTreeMap<String, Vec<u16>> {
+    let mut published = BTreeMap::new();
+    let Ok(doc) = serde_yaml::from_str::<serde_yaml::Value>(contents) else {
+        return published;
+    };
+    let Some(services) = doc.get("services").and_then(|s| s.as_mapping()) else {
+        return published;
+    };
+    for (name, service) in services {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let mut ports: Vec<u16> = service
+            .get("ports")
+            .and_then(|p| p.as_sequence())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| match entry {
+                // "8080:80", "127.0.0.1:443:443/tcp"; a lone container port
+                // publishes on a random host port.
+                serde_yaml::Value::String(spec) => {
+                    let spec = spec.split('/').next().unwrap_or(spec);
+                    let parts: Vec<&str> = spec.rsplitn(2, ':').collect();
+                    let host = parts.get(1)?;
+                    host.rsplit(':').next()?.parse().ok()
+                }
+                serde_yaml::Value::Mapping(_) => entry.get("published").and_then(|p| {
+                    p.as_u64()
+                        .and_then(|n| u16::try_from(n).ok())
+                        .or_else(|| p.as_str()?.parse().ok())
+                }),
+                _ => None,
+            })
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        if !ports.is_empty() {
+            published.insert(name.to_string(), ports);
+        }
+    }
+    published
+}
+
+/// Parses nginx, Caddy and Apache configuration into service nodes with
+/// their listen and upstream ports, adds port nodes for those ports, and
+/// records the host ports compose services publish on the docker node.
+pub fn detect<P: SystemProvider>(provider: &P, nodes: &mut Vec<Node>) {
+    let processes = provider.processes();
+    for server in SERVERS {
+        let Some(main) = server.configs.iter().find(|p| provider.file_exists(p)) else {
+            continue;
+        };
+        let config = match server.id {
+            "nginx" => nginx(provider, main),
+            "caddy" => caddy(provider, main),
+            _ => apache(provider, main),
+        };
+        let running = processes.iter().any(|p| p.runs_any(server.executables));
+        let upstream_ports: BTreeSet<u16> = config
+            .upstreams
+            .iter()
+            .filter(|u| u.local)
+            .map(|u| u.port)
+            .collect();
+
+        for port in config.listen.iter().chain(&upstream_ports) {
+            ensure_port_node(provider, nodes, *port);
+        }
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("config_files".into(), json!(config.files));
+        metadata.insert("listen_ports".into(), json!(config.listen));
+        metadata.insert("upstreams".into(), json!(config.upstreams));
+        metadata.insert("upstream_ports".into(), json!(upstream_ports));
+        nodes.push(Node {
+            id: server.id.to_string(),
+            node_type: NodeType::Service,
+            label: server.label.to_string(),
+            status: if running {
+                Status::Active
+            } else {
+                Status::Inactive
+            },
+            metadata,
+        });
+    }
+
+    let Some(docker) = nodes.iter_mut().find(|n| n.id == "docker") else {
+        return;
+    };
+    let published = docker
+        .metadata
+        .get("compose_file")
+        .and_then(|f| f.as_str())
+        .and_then(|f| provider.read_file(f))
+        .map(|contents| compose_ports(&contents));
+    if let Some(published) = published {
+        docker
+            .metadata
+            .insert("compose_ports".into(), json!(published));
+    }
+}
+
+fn port_list(node: &Node, key: &str) -> Vec<u16> {
+    node.metadata
+        .get(key)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// The systemd unit for a server, named after the layout of the main config
+/// it was found with (`/etc/httpd` on Red Hat, `/etc/apache2` on Debian).
+fn unit(server: &WebServer, node: &Node) -> &'static str {
+    let main = node
+        .metadata
+        .get("config_files")
+        .and_then(|f| f.get(0))
+        .and_then(|f| f.as_str())
+        .unwrap_or("");
+    server
+        .executables
+        .iter()
+        .find(|e| main.contains(&format!("/{}/", e)))
+        .unwrap_or(&server.executables[0])
+}
+
+pub fn evaluate(state: &SystemState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let compose: BTreeMap<String, Vec<u16>> = state
+        .nodes
+        .iter()
+        .find(|n| n.id == "docker")
+        .and_then(|n| n.metadata.get("compose_ports"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let listening = |port: u16| {
+        state
+            .nodes
+            .iter()
+            .any(|n| n.id == format!("port{}", port) && n.status == Status::Active)
+    };
+
+    for server in SERVERS {
+        let Some(node) = state.nodes.iter().find(|n| n.id == server.id) else {
+            continue;
+        };
+        let upstreams: Vec<Upstream> = node
+            .metadata
+            .get("upstreams")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+        let mut dead: BTreeMap<u16, Vec<String>> = BTreeMap::new();
+        for up in upstreams.iter().filter(|u| u.local && !listening(u.port)) {
+            dead.entry(up.port)
+                .or_default()
+                .push(format!("{} ({})", up.target, up.origin));
+        }
+        for (port, targets) in dead {
+            issues.push(Issue {
+                code: format!("{}_UPSTREAM_NO_LISTENER", server.prefix),
+                severity: Severity::Warning,
+                title: format!("{} proxies to port {} but nothing listens there", server.label, port),
+                description: format!(
+                    "{} forwards requests to {}, but no process is listening on port {}, so those routes return 502.",
+                    server.label,
+                    targets.join(", "),
+                    port
+                ),
+                suggestion: format!(
+                    "Start the application that should serve port {}, or update the upstream in the {} config.",
+                    port, server.label
+                ),
+            });
+        }
+
+        if node.status != Status::Active {
+            continue;
+        }
+        for port in port_list(node, "listen_ports") {
+            let services: Vec<&str> = compose
+                .iter()
+                .filter(|(_, ports)| ports.contains(&port))
+                .map(|(service, _)| service.as_str())
+                .collect();
+            if services.is_empty() {
+                continue;
+            }
+            issues.push(Issue {
+                code: format!("{}_COMPOSE_PORT_CONFLICT", server.prefix),
+                severity: Severity::Critical,
+                title: format!("{} and compose both want port {}", server.label, port),
+                description: format!(
+                    "{} is running and listens on port {}, which compose service {} publishes; `docker compose up` will fail with \"port is already allocated\".",
+                    server.label,
+                    port,
+                    services.join(", ")
+                ),
+                suggestion: format!(
+                    "Stop {} (for example `sudo systemctl stop {}`) or publish the compose service on another host port.",
+                    server.label,
+                    unit(server, node)
+                ),
+            });
+        }
+    }
+    issues
+}